@@ -0,0 +1,138 @@
+use plonky2::{
+    field::extension::Extendable, hash::hash_types::RichField, iop::target::BoolTarget,
+    plonk::circuit_builder::CircuitBuilder,
+};
+
+use crate::{
+    biguint::{BigUintTarget, CircuitBuilderBiguint},
+    ssz::hash_tree_root_container,
+    utils::{create_bool_target_array, ssz_num_to_bits, ETH_SHA256_BIT_SIZE},
+};
+
+pub struct BeaconBlockHeaderTargets {
+    pub slot: BigUintTarget,
+    pub proposer_index: BigUintTarget,
+    pub parent_root: [BoolTarget; ETH_SHA256_BIT_SIZE],
+    pub state_root: [BoolTarget; ETH_SHA256_BIT_SIZE],
+    pub body_root: [BoolTarget; ETH_SHA256_BIT_SIZE],
+}
+
+pub struct BeaconBlockHeaderHashTreeRootTargets {
+    pub header: BeaconBlockHeaderTargets,
+    pub hash_tree_root: [BoolTarget; ETH_SHA256_BIT_SIZE],
+}
+
+pub fn hash_tree_root_beacon_block_header<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+) -> BeaconBlockHeaderHashTreeRootTargets {
+    let header = BeaconBlockHeaderTargets {
+        slot: builder.add_virtual_biguint_target(2),
+        proposer_index: builder.add_virtual_biguint_target(2),
+        parent_root: create_bool_target_array(builder),
+        state_root: create_bool_target_array(builder),
+        body_root: create_bool_target_array(builder),
+    };
+
+    let slot_bits = ssz_num_to_bits(builder, &header.slot, 64);
+    let proposer_index_bits = ssz_num_to_bits(builder, &header.proposer_index, 64);
+
+    let hash_tree_root = hash_tree_root_container(
+        builder,
+        &[
+            slot_bits.try_into().unwrap(),
+            proposer_index_bits.try_into().unwrap(),
+            header.parent_root,
+            header.state_root,
+            header.body_root,
+        ],
+    );
+
+    BeaconBlockHeaderHashTreeRootTargets {
+        header,
+        hash_tree_root,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use num::BigUint;
+    use plonky2::{
+        field::goldilocks_field::GoldilocksField,
+        iop::witness::{PartialWitness, WitnessWrite},
+        plonk::{
+            circuit_builder::CircuitBuilder, circuit_data::CircuitConfig,
+            config::PoseidonGoldilocksConfig,
+        },
+    };
+    use sha2::{Digest, Sha256};
+
+    use crate::{
+        beacon_block_header_hash_tree_root::hash_tree_root_beacon_block_header,
+        biguint::WitnessBigUint, sha256::array_to_bits,
+    };
+
+    fn sha256(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(left);
+        hasher.update(right);
+        hasher.finalize().as_slice().try_into().unwrap()
+    }
+
+    #[test]
+    fn test_hash_tree_root_beacon_block_header() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = GoldilocksField;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+
+        let targets = hash_tree_root_beacon_block_header(&mut builder);
+
+        let slot = 6953401u64;
+        let proposer_index = 421337u64;
+        let parent_root = [1u8; 32];
+        let state_root = [2u8; 32];
+        let body_root = [3u8; 32];
+
+        let mut slot_chunk = [0u8; 32];
+        slot_chunk[..8].copy_from_slice(&slot.to_le_bytes());
+
+        let mut proposer_index_chunk = [0u8; 32];
+        proposer_index_chunk[..8].copy_from_slice(&proposer_index.to_le_bytes());
+
+        let zero_hash = sha256(&[0; 32], &[0; 32]);
+
+        let expected = sha256(
+            &sha256(
+                &sha256(&slot_chunk, &proposer_index_chunk),
+                &sha256(&parent_root, &state_root),
+            ),
+            &sha256(&sha256(&body_root, &[0; 32]), &zero_hash),
+        );
+
+        let mut pw = PartialWitness::new();
+
+        pw.set_biguint_target(&targets.header.slot, &BigUint::from(slot));
+        pw.set_biguint_target(
+            &targets.header.proposer_index,
+            &BigUint::from(proposer_index),
+        );
+
+        for (root_targets, root) in [
+            (targets.header.parent_root, parent_root),
+            (targets.header.state_root, state_root),
+            (targets.header.body_root, body_root),
+            (targets.hash_tree_root, expected),
+        ] {
+            for (target, bit) in root_targets.iter().zip(array_to_bits(&root)) {
+                pw.set_bool_target(*target, bit);
+            }
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        data.verify(proof)
+    }
+}
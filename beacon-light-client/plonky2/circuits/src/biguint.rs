@@ -3,7 +3,7 @@ use std::println;
 
 use num::{BigUint, Integer, Zero};
 use plonky2::field::extension::Extendable;
-use plonky2::field::types::{PrimeField, PrimeField64};
+use plonky2::field::types::{Field, PrimeField, PrimeField64};
 use plonky2::hash::hash_types::RichField;
 use plonky2::iop::generator::{GeneratedValues, SimpleGenerator};
 use plonky2::iop::target::{BoolTarget, Target};
@@ -45,16 +45,46 @@ pub trait CircuitBuilderBiguint<F: RichField + Extendable<D>, const D: usize> {
 
     fn cmp_biguint(&mut self, a: &BigUintTarget, b: &BigUintTarget) -> BoolTarget;
 
+    /// Returns `a < b`.
+    fn lt_biguint(&mut self, a: &BigUintTarget, b: &BigUintTarget) -> BoolTarget;
+
+    /// Returns `a <= b`. Same as `cmp_biguint`.
+    fn le_biguint(&mut self, a: &BigUintTarget, b: &BigUintTarget) -> BoolTarget;
+
+    fn is_zero_biguint(&mut self, a: &BigUintTarget) -> BoolTarget;
+
     fn add_virtual_biguint_target(&mut self, num_limbs: usize) -> BigUintTarget;
 
+    /// Same as `add_virtual_biguint_target`, but every limb is range checked to 32 bits.
+    fn add_virtual_biguint_target_safe(&mut self, num_limbs: usize) -> BigUintTarget;
+
+    fn range_check_biguint(&mut self, a: &BigUintTarget);
+
+    /// Converts a target holding a value of at most `num_bits` bits, which has to be less than 64.
+    fn biguint_from_target(&mut self, x: Target, num_bits: usize) -> BigUintTarget;
+
+    /// Converts a `BigUintTarget` to a single target, asserting that its value is less than 2^63.
+    fn biguint_to_target(&mut self, a: &BigUintTarget) -> Target;
+
     /// Add two `BigUintTarget`s.
     fn add_biguint(&mut self, a: &BigUintTarget, b: &BigUintTarget) -> BigUintTarget;
 
-    /// Subtract two `BigUintTarget`s. We assume that the first is larger than the second.
+    /// Add two `BigUintTarget`s, asserting that the sum fits in the limbs of the wider one.
+    fn add_biguint_checked(&mut self, a: &BigUintTarget, b: &BigUintTarget) -> BigUintTarget;
+
+    /// Subtract two `BigUintTarget`s, asserting that the first is not smaller than the second.
     fn sub_biguint(&mut self, a: &BigUintTarget, b: &BigUintTarget) -> BigUintTarget;
 
     fn mul_biguint(&mut self, a: &BigUintTarget, b: &BigUintTarget) -> BigUintTarget;
 
+    fn mul_biguint_by_constant(&mut self, a: &BigUintTarget, c: u32) -> BigUintTarget;
+
+    /// Shifts left by a constant number of bits. The result has enough limbs to hold all bits.
+    fn shl_biguint(&mut self, a: &BigUintTarget, n: usize) -> BigUintTarget;
+
+    /// Shifts right by a constant number of bits.
+    fn shr_biguint(&mut self, a: &BigUintTarget, n: usize) -> BigUintTarget;
+
     fn mul_biguint_by_bool(&mut self, a: &BigUintTarget, b: BoolTarget) -> BigUintTarget;
 
     /// Returns x * y + z. This is no more efficient than mul-then-add; it's purely for convenience (only need to call one CircuitBuilder function).
@@ -132,12 +162,81 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilderBiguint<F, D>
         list_le_u32_circuit(self, a.limbs, b.limbs)
     }
 
+    fn lt_biguint(&mut self, a: &BigUintTarget, b: &BigUintTarget) -> BoolTarget {
+        let b_le_a = self.cmp_biguint(b, a);
+
+        self.not(b_le_a)
+    }
+
+    fn le_biguint(&mut self, a: &BigUintTarget, b: &BigUintTarget) -> BoolTarget {
+        self.cmp_biguint(a, b)
+    }
+
+    fn is_zero_biguint(&mut self, a: &BigUintTarget) -> BoolTarget {
+        let zero = self.zero();
+        let mut result = self._true();
+
+        for limb in &a.limbs {
+            let is_zero_limb = self.is_equal(limb.0, zero);
+            result = self.and(result, is_zero_limb);
+        }
+
+        result
+    }
+
     fn add_virtual_biguint_target(&mut self, num_limbs: usize) -> BigUintTarget {
         let limbs = self.add_virtual_u32_targets(num_limbs);
 
         BigUintTarget { limbs }
     }
 
+    fn add_virtual_biguint_target_safe(&mut self, num_limbs: usize) -> BigUintTarget {
+        let target = self.add_virtual_biguint_target(num_limbs);
+        self.range_check_biguint(&target);
+
+        target
+    }
+
+    fn range_check_biguint(&mut self, a: &BigUintTarget) {
+        for limb in &a.limbs {
+            self.range_check(limb.0, 32);
+        }
+    }
+
+    fn biguint_from_target(&mut self, x: Target, num_bits: usize) -> BigUintTarget {
+        assert!(num_bits < 64);
+
+        let bits = self.split_le(x, num_bits);
+
+        BigUintTarget {
+            limbs: bits
+                .chunks(32)
+                .map(|chunk| U32Target(self.le_sum(chunk.iter())))
+                .collect(),
+        }
+    }
+
+    fn biguint_to_target(&mut self, a: &BigUintTarget) -> Target {
+        for i in 2..a.num_limbs() {
+            self.assert_zero_u32(a.get_limb(i));
+        }
+
+        match a.num_limbs() {
+            0 => self.zero(),
+            1 => a.get_limb(0).0,
+            _ => {
+                // keeps the value below the field order
+                self.range_check(a.get_limb(1).0, 31);
+
+                self.mul_const_add(
+                    F::from_canonical_u64(1 << 32),
+                    a.get_limb(1).0,
+                    a.get_limb(0).0,
+                )
+            }
+        }
+    }
+
     fn add_biguint(&mut self, a: &BigUintTarget, b: &BigUintTarget) -> BigUintTarget {
         let num_limbs = a.num_limbs().max(b.num_limbs());
 
@@ -162,19 +261,27 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilderBiguint<F, D>
         }
     }
 
+    fn add_biguint_checked(&mut self, a: &BigUintTarget, b: &BigUintTarget) -> BigUintTarget {
+        let mut sum = self.add_biguint(a, b);
+
+        let carry = sum.limbs.pop().unwrap();
+        self.assert_zero_u32(carry);
+
+        sum
+    }
+
     fn sub_biguint(&mut self, a: &BigUintTarget, b: &BigUintTarget) -> BigUintTarget {
         let (a, b) = self.pad_biguints(a, b);
-        let num_limbs = a.limbs.len();
 
         let mut result_limbs = vec![];
 
         let mut borrow = self.zero_u32();
-        for i in 0..num_limbs {
+        for i in 0..a.num_limbs() {
             let (result, new_borrow) = self.sub_u32(a.limbs[i], b.limbs[i], borrow);
             result_limbs.push(result);
             borrow = new_borrow;
         }
-        // Borrow should be zero here.
+        self.assert_zero_u32(borrow);
 
         BigUintTarget {
             limbs: result_limbs,
@@ -207,6 +314,41 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilderBiguint<F, D>
         }
     }
 
+    fn mul_biguint_by_constant(&mut self, a: &BigUintTarget, c: u32) -> BigUintTarget {
+        let c = self.constant_u32(c);
+
+        let mut combined_limbs = vec![];
+        let mut carry = self.zero_u32();
+        for &limb in &a.limbs {
+            let (new_limb, new_carry) = self.mul_add_u32(limb, c, carry);
+            combined_limbs.push(new_limb);
+            carry = new_carry;
+        }
+        combined_limbs.push(carry);
+
+        BigUintTarget {
+            limbs: combined_limbs,
+        }
+    }
+
+    fn shl_biguint(&mut self, a: &BigUintTarget, n: usize) -> BigUintTarget {
+        let mut bits = vec![self._false(); n];
+        for limb in &a.limbs {
+            bits.extend(self.split_le(limb.0, 32));
+        }
+
+        limbs_from_le_bits(self, bits)
+    }
+
+    fn shr_biguint(&mut self, a: &BigUintTarget, n: usize) -> BigUintTarget {
+        let mut bits = vec![];
+        for limb in &a.limbs {
+            bits.extend(self.split_le(limb.0, 32));
+        }
+
+        limbs_from_le_bits(self, bits.into_iter().skip(n).collect())
+    }
+
     fn mul_biguint_by_bool(&mut self, a: &BigUintTarget, b: BoolTarget) -> BigUintTarget {
         let t = b.target;
 
@@ -273,6 +415,21 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilderBiguint<F, D>
     }
 }
 
+fn limbs_from_le_bits<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    mut bits: Vec<BoolTarget>,
+) -> BigUintTarget {
+    let num_limbs = (bits.len() + 31) / 32;
+    bits.resize(num_limbs * 32, builder._false());
+
+    BigUintTarget {
+        limbs: bits
+            .chunks(32)
+            .map(|chunk| U32Target(builder.le_sum(chunk.iter())))
+            .collect(),
+    }
+}
+
 pub trait WitnessBigUint<F: PrimeField64>: Witness<F> {
     fn get_biguint_target(&self, target: BigUintTarget) -> BigUint;
     fn set_biguint_target(&mut self, target: &BigUintTarget, value: &BigUint);
@@ -422,7 +579,8 @@ impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F>
 mod tests {
     use anyhow::Result;
     use num::{BigUint, FromPrimitive, Integer};
-    use plonky2::iop::witness::PartialWitness;
+    use plonky2::field::types::Field;
+    use plonky2::iop::witness::{PartialWitness, WitnessWrite};
     use plonky2::plonk::circuit_builder::CircuitBuilder;
     use plonky2::plonk::circuit_data::CircuitConfig;
     use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
@@ -461,6 +619,63 @@ mod tests {
         data.verify(proof)
     }
 
+    #[test]
+    fn test_biguint_add_checked() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let x_value = BigUint::from_u64(u64::MAX - 1).unwrap();
+        let y_value = BigUint::from_u64(1).unwrap();
+        let expected_z_value = &x_value + &y_value;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let x = builder.add_virtual_biguint_target(2);
+        let y = builder.add_virtual_biguint_target(2);
+        let z = builder.add_biguint_checked(&x, &y);
+        assert_eq!(z.num_limbs(), 2);
+
+        let expected_z = builder.add_virtual_biguint_target(2);
+        builder.connect_biguint(&z, &expected_z);
+
+        pw.set_biguint_target(&x, &x_value);
+        pw.set_biguint_target(&y, &y_value);
+        pw.set_biguint_target(&expected_z, &expected_z_value);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw).unwrap();
+        data.verify(proof)
+    }
+
+    #[test]
+    #[should_panic(expected = "set twice with different values")]
+    fn test_biguint_add_checked_overflow() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let x_value = BigUint::from_u64(u64::MAX).unwrap();
+        let y_value = BigUint::from_u64(1).unwrap();
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let x = builder.add_virtual_biguint_target(2);
+        let y = builder.add_virtual_biguint_target(2);
+        builder.add_biguint_checked(&x, &y);
+
+        pw.set_biguint_target(&x, &x_value);
+        pw.set_biguint_target(&y, &y_value);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw).unwrap();
+        data.verify(proof).unwrap();
+    }
+
     #[test]
     fn test_biguint_sub() -> Result<()> {
         const D: usize = 2;
@@ -579,4 +794,214 @@ mod tests {
         let proof = data.prove(pw).unwrap();
         data.verify(proof)
     }
+
+    #[test]
+    #[should_panic(expected = "set twice with different values")]
+    fn test_biguint_add_virtual_safe_overflow() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let x = builder.add_virtual_biguint_target_safe(2);
+
+        pw.set_target(x.limbs[0].0, F::from_canonical_u64(1 << 32));
+        pw.set_target(x.limbs[1].0, F::ZERO);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw).unwrap();
+        data.verify(proof).unwrap();
+    }
+
+    #[test]
+    fn test_biguint_sub_with_borrow() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let x_value = BigUint::from_u64(1 << 32).unwrap();
+        let y_value = BigUint::from_u64(1).unwrap();
+        let expected_z_value = &x_value - &y_value;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let x = builder.constant_biguint(&x_value);
+        let y = builder.constant_biguint(&y_value);
+        let z = builder.sub_biguint(&x, &y);
+        let expected_z = builder.constant_biguint(&expected_z_value);
+
+        builder.connect_biguint(&z, &expected_z);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw).unwrap();
+        data.verify(proof)
+    }
+
+    #[test]
+    #[should_panic(expected = "set twice with different values")]
+    fn test_biguint_sub_underflow() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let x = builder.add_virtual_biguint_target(2);
+        let y = builder.add_virtual_biguint_target(2);
+        builder.sub_biguint(&x, &y);
+
+        pw.set_biguint_target(&x, &BigUint::from_u64(1).unwrap());
+        pw.set_biguint_target(&y, &BigUint::from_u64(2).unwrap());
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw).unwrap();
+        data.verify(proof).unwrap();
+    }
+
+    #[test]
+    fn test_biguint_lt_le() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        for (x_value, y_value) in [(5u64, 1u64 << 40), (1 << 40, 1 << 40), (1 << 40, 5)] {
+            let x = builder.constant_biguint(&BigUint::from_u64(x_value).unwrap());
+            let y = builder.constant_biguint(&BigUint::from_u64(y_value).unwrap());
+
+            let lt = builder.lt_biguint(&x, &y);
+            let expected_lt = builder.constant_bool(x_value < y_value);
+            builder.connect(lt.target, expected_lt.target);
+
+            let le = builder.le_biguint(&x, &y);
+            let expected_le = builder.constant_bool(x_value <= y_value);
+            builder.connect(le.target, expected_le.target);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw).unwrap();
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_biguint_is_zero() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        for value in [0u64, 1, 1 << 32] {
+            let x = builder.add_virtual_biguint_target(2);
+            pw.set_biguint_target(&x, &BigUint::from_u64(value).unwrap());
+
+            let is_zero = builder.is_zero_biguint(&x);
+            let expected_is_zero = builder.constant_bool(value == 0);
+            builder.connect(is_zero.target, expected_is_zero.target);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw).unwrap();
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_biguint_mul_by_constant() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let mut rng = OsRng;
+
+        let x_value = BigUint::from_u128(rng.gen()).unwrap();
+        let c: u32 = rng.gen();
+        let expected_z_value = &x_value * c;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let x = builder.add_virtual_biguint_target(4);
+        let z = builder.mul_biguint_by_constant(&x, c);
+        let expected_z = builder.add_virtual_biguint_target(5);
+        builder.connect_biguint(&z, &expected_z);
+
+        pw.set_biguint_target(&x, &x_value);
+        pw.set_biguint_target(&expected_z, &expected_z_value);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw).unwrap();
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_biguint_shl_shr() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let mut rng = OsRng;
+
+        let x_value = BigUint::from_u64(rng.gen()).unwrap();
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let x = builder.add_virtual_biguint_target(2);
+        pw.set_biguint_target(&x, &x_value);
+
+        for n in [0, 5, 32, 45] {
+            let shl = builder.shl_biguint(&x, n);
+            let expected_shl = builder.constant_biguint(&(&x_value << n));
+            builder.connect_biguint(&shl, &expected_shl);
+
+            let shr = builder.shr_biguint(&x, n);
+            let expected_shr = builder.constant_biguint(&(&x_value >> n));
+            builder.connect_biguint(&shr, &expected_shr);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw).unwrap();
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_biguint_target_conversion() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let value: u64 = (1 << 40) + 7;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let x = builder.add_virtual_target();
+        pw.set_target(x, F::from_canonical_u64(value));
+
+        let x_biguint = builder.biguint_from_target(x, 48);
+        assert_eq!(x_biguint.num_limbs(), 2);
+
+        let expected_x_biguint = builder.constant_biguint(&BigUint::from_u64(value).unwrap());
+        builder.connect_biguint(&x_biguint, &expected_x_biguint);
+
+        let y = builder.biguint_to_target(&x_biguint);
+        builder.connect(x, y);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw).unwrap();
+        data.verify(proof)
+    }
 }
@@ -1,5 +1,7 @@
 use plonky2::{
-    hash::poseidon::PoseidonHash,
+    field::goldilocks_field::GoldilocksField,
+    hash::{hash_types::HashOutTarget, poseidon::PoseidonHash},
+    iop::target::BoolTarget,
     plonk::{
         circuit_builder::CircuitBuilder,
         circuit_data::{CircuitConfig, CircuitData, VerifierCircuitTarget},
@@ -10,27 +12,31 @@ use plonky2::{
 };
 
 use crate::{
-    biguint::{CircuitBuilderBiguint},
+    biguint::{BigUintTarget, CircuitBuilderBiguint},
     build_validator_balance_circuit::{
-        set_public_variables, ValidatorBalanceProofTargetsExt,
+        ValidatorBalanceProofTargetsExt, ValidatorBalancePublicInputsTargets,
     },
     sha256::make_circuits,
     targets_serialization::{ReadTargets, WriteTargets},
-    utils::{ETH_SHA256_BIT_SIZE},
+    utils::{if_biguint, ETH_SHA256_BIT_SIZE},
 };
 
 pub struct BalanceInnerCircuitTargets {
-    pub proof1: ProofWithPublicInputsTarget<2>,
-    pub proof2: ProofWithPublicInputsTarget<2>,
+    pub proofs: Vec<ProofWithPublicInputsTarget<2>>,
     pub verifier_circuit_target: VerifierCircuitTarget,
+    pub current_epoch: BigUintTarget,
 }
 
 impl ReadTargets for BalanceInnerCircuitTargets {
     fn read_targets(data: &mut Buffer) -> IoResult<Self> {
+        let proofs_len = data.read_usize()?;
+
         Ok(BalanceInnerCircuitTargets {
-            proof1: data.read_target_proof_with_public_inputs()?,
-            proof2: data.read_target_proof_with_public_inputs()?,
+            proofs: (0..proofs_len)
+                .map(|_| data.read_target_proof_with_public_inputs())
+                .collect::<IoResult<Vec<_>>>()?,
             verifier_circuit_target: data.read_target_verifier_circuit()?,
+            current_epoch: BigUintTarget::read_targets(data)?,
         })
     }
 }
@@ -39,28 +45,57 @@ impl WriteTargets for BalanceInnerCircuitTargets {
     fn write_targets(&self) -> IoResult<Vec<u8>> {
         let mut data = Vec::<u8>::new();
 
-        data.write_target_proof_with_public_inputs(&self.proof1)?;
-        data.write_target_proof_with_public_inputs(&self.proof2)?;
+        data.write_usize(self.proofs.len())?;
+
+        for proof in &self.proofs {
+            data.write_target_proof_with_public_inputs(proof)?;
+        }
+
         data.write_target_verifier_circuit(&self.verifier_circuit_target)?;
+        data.extend(BigUintTarget::write_targets(&self.current_epoch)?);
 
         Ok(data)
     }
 }
 
+/// The arity of each inner level when `levels` binary levels are merged into
+/// levels with `arity` children. The levels that don't add up to a full one
+/// are merged into the last level.
+pub fn balance_inner_level_arities(levels: usize, arity: usize) -> Vec<usize> {
+    assert!(arity.is_power_of_two() && arity >= 2);
+
+    let levels_per_circuit = arity.trailing_zeros() as usize;
+
+    let mut arities = vec![arity; levels / levels_per_circuit];
+
+    if levels % levels_per_circuit != 0 {
+        arities.push(1 << (levels % levels_per_circuit));
+    }
+
+    arities
+}
+
 pub fn build_inner_level_circuit(
-    inner_circuit_data: &CircuitData<
-        plonky2::field::goldilocks_field::GoldilocksField,
-        PoseidonGoldilocksConfig,
-        2,
-    >,
+    inner_circuit_data: &CircuitData<GoldilocksField, PoseidonGoldilocksConfig, 2>,
 ) -> (
     BalanceInnerCircuitTargets,
-    plonky2::plonk::circuit_data::CircuitData<
-        plonky2::field::goldilocks_field::GoldilocksField,
-        PoseidonGoldilocksConfig,
-        2,
-    >,
+    CircuitData<GoldilocksField, PoseidonGoldilocksConfig, 2>,
 ) {
+    build_inner_level_circuit_with_arity(inner_circuit_data, 2)
+}
+
+/// Verifies `arity` consecutive proofs of the level below. Their roots are
+/// hashed as a binary subtree, so the result is the same as the one of
+/// `log2(arity)` binary levels.
+pub fn build_inner_level_circuit_with_arity(
+    inner_circuit_data: &CircuitData<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+    arity: usize,
+) -> (
+    BalanceInnerCircuitTargets,
+    CircuitData<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+) {
+    assert!(arity.is_power_of_two() && arity >= 2);
+
     const D: usize = 2;
     type C = PoseidonGoldilocksConfig;
     type F = <C as GenericConfig<D>>::F;
@@ -69,84 +104,402 @@ pub fn build_inner_level_circuit(
 
     let mut builder = CircuitBuilder::<F, D>::new(standard_recursion_config);
 
-    let verifier_circuit_target = VerifierCircuitTarget {
-        constants_sigmas_cap: builder
-            .add_virtual_cap(inner_circuit_data.common.config.fri_config.cap_height),
-        circuit_digest: builder.add_virtual_hash(),
-    };
-
-    let pt1: ProofWithPublicInputsTarget<2> =
-        builder.add_virtual_proof_with_pis(&inner_circuit_data.common);
-    let pt2: ProofWithPublicInputsTarget<2> =
-        builder.add_virtual_proof_with_pis(&inner_circuit_data.common);
+    let verifier_circuit_target = builder.constant_verifier_data(&inner_circuit_data.verifier_only);
 
-    builder.verify_proof::<C>(&pt1, &verifier_circuit_target, &inner_circuit_data.common);
+    let proofs: Vec<ProofWithPublicInputsTarget<2>> = (0..arity)
+        .map(|_| builder.add_virtual_proof_with_pis(&inner_circuit_data.common))
+        .collect();
 
-    builder.verify_proof::<C>(&pt2, &verifier_circuit_target, &inner_circuit_data.common);
+    for proof in &proofs {
+        builder.verify_proof::<C>(proof, &verifier_circuit_target, &inner_circuit_data.common);
+    }
 
-    let poseidon_hash = pt1.get_range_validator_commitment();
+    let mut sha256_hashes: Vec<[BoolTarget; ETH_SHA256_BIT_SIZE]> = proofs
+        .iter()
+        .map(|proof| proof.get_range_balances_root())
+        .collect();
 
-    let sha256_hash = pt1.get_range_balances_root();
+    let mut poseidon_hashes: Vec<HashOutTarget> = proofs
+        .iter()
+        .map(|proof| proof.get_range_validator_commitment())
+        .collect();
 
-    let poseidon_hash2 = pt2.get_range_validator_commitment();
+    while sha256_hashes.len() > 1 {
+        sha256_hashes = sha256_hashes
+            .chunks(2)
+            .map(|pair| {
+                let hasher = make_circuits(&mut builder, (2 * ETH_SHA256_BIT_SIZE) as u64);
 
-    let sha256_hash2 = pt2.get_range_balances_root();
+                for i in 0..ETH_SHA256_BIT_SIZE {
+                    builder.connect(hasher.message[i].target, pair[0][i].target);
+                    builder.connect(
+                        hasher.message[i + ETH_SHA256_BIT_SIZE].target,
+                        pair[1][i].target,
+                    );
+                }
 
-    let hasher = make_circuits(&mut builder, (2 * ETH_SHA256_BIT_SIZE) as u64);
+                hasher.digest.try_into().unwrap()
+            })
+            .collect();
 
-    for i in 0..ETH_SHA256_BIT_SIZE {
-        builder.connect(hasher.message[i].target, sha256_hash[i].target);
-        builder.connect(
-            hasher.message[i + ETH_SHA256_BIT_SIZE].target,
-            sha256_hash2[i].target,
-        );
+        poseidon_hashes = poseidon_hashes
+            .chunks(2)
+            .map(|pair| {
+                builder.hash_n_to_hash_no_pad::<PoseidonHash>(
+                    pair[0]
+                        .elements
+                        .iter()
+                        .chain(pair[1].elements.iter())
+                        .cloned()
+                        .collect(),
+                )
+            })
+            .collect();
     }
 
-    let hash = builder.hash_n_to_hash_no_pad::<PoseidonHash>(
-        poseidon_hash
-            .elements
+    let mut sum_biguints = |get_value: fn(&ProofWithPublicInputsTarget<2>) -> BigUintTarget| {
+        let values: Vec<BigUintTarget> = proofs.iter().map(get_value).collect();
+
+        values[1..].iter().fold(values[0].clone(), |sum, value| {
+            builder.add_biguint_checked(&sum, value)
+        })
+    };
+
+    let sum = sum_biguints(|proof| proof.get_range_total_value());
+    let slashed_sum = sum_biguints(|proof| proof.get_range_slashed_total_value());
+    let effective_balance_sum =
+        sum_biguints(|proof| proof.get_range_effective_balance_total_value());
+    let pending_sum = sum_biguints(|proof| proof.get_range_pending_total_value());
+    let active_sum = sum_biguints(|proof| proof.get_range_active_total_value());
+    let exited_sum = sum_biguints(|proof| proof.get_range_exited_total_value());
+    let withdrawable_sum = sum_biguints(|proof| proof.get_range_withdrawable_total_value());
+
+    let counted_validators = builder.add_many(
+        proofs
             .iter()
-            .chain(poseidon_hash2.elements.iter())
-            .cloned()
-            .collect(),
+            .map(|proof| proof.get_range_counted_validators()),
     );
 
-    let sum1 = pt1.get_range_total_value();
+    let withdrawal_credentials_commitment = proofs[0].get_withdrawal_credentials_commitment();
 
-    let sum2 = pt2.get_range_total_value();
-
-    let mut sum = builder.add_biguint(&sum1, &sum2);
+    for proof in &proofs[1..] {
+        builder.connect_hashes(
+            withdrawal_credentials_commitment,
+            proof.get_withdrawal_credentials_commitment(),
+        );
+    }
 
-    // pop carry
-    sum.limbs.pop();
+    // A child proven at an earlier epoch is carried forward as long as none of
+    // its validators has changed its state since
+    let current_epoch = builder.add_virtual_biguint_target_safe(2);
+    let mut valid_until_epoch = proofs[0].get_range_valid_until_epoch();
 
-    let withdrawal_credentials1 = pt1.get_withdrawal_credentials();
-    let withdrawal_credentials2 = pt2.get_withdrawal_credentials();
+    for proof in &proofs {
+        let child_epoch = proof.get_current_epoch();
+        let child_valid_until_epoch = proof.get_range_valid_until_epoch();
 
-    builder.connect_biguint(&withdrawal_credentials1, &withdrawal_credentials2);
+        let is_not_later = builder.le_biguint(&child_epoch, &current_epoch);
+        builder.assert_one(is_not_later.target);
 
-    let current_epoch1 = pt1.get_current_epoch();
-    let current_epoch2 = pt2.get_current_epoch();
+        let is_still_valid = builder.lt_biguint(&current_epoch, &child_valid_until_epoch);
+        builder.assert_one(is_still_valid.target);
 
-    builder.connect_biguint(&current_epoch1, &current_epoch2);
+        let is_sooner = builder.lt_biguint(&child_valid_until_epoch, &valid_until_epoch);
+        valid_until_epoch = if_biguint(
+            &mut builder,
+            is_sooner,
+            &child_valid_until_epoch,
+            &valid_until_epoch,
+        );
+    }
 
-    set_public_variables(
-        &mut builder,
-        &sum,
-        hasher.digest.try_into().unwrap(),
-        &withdrawal_credentials1,
-        hash,
-        &current_epoch1,
-    );
+    ValidatorBalancePublicInputsTargets {
+        range_total_value: sum,
+        range_balances_root: sha256_hashes[0],
+        withdrawal_credentials_commitment,
+        range_validator_commitment: poseidon_hashes[0],
+        current_epoch: current_epoch.clone(),
+        range_slashed_total_value: slashed_sum,
+        range_counted_validators: counted_validators,
+        range_effective_balance_total_value: effective_balance_sum,
+        range_pending_total_value: pending_sum,
+        range_active_total_value: active_sum,
+        range_exited_total_value: exited_sum,
+        range_withdrawable_total_value: withdrawable_sum,
+        range_valid_until_epoch: valid_until_epoch,
+    }
+    .register(&mut builder);
 
     let data = builder.build::<C>();
 
     (
         BalanceInnerCircuitTargets {
-            proof1: pt1,
-            proof2: pt2,
-            verifier_circuit_target: verifier_circuit_target,
+            proofs,
+            verifier_circuit_target,
+            current_epoch,
         },
         data,
     )
 }
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use num::BigUint;
+    use plonky2::{
+        field::{goldilocks_field::GoldilocksField, types::Field},
+        iop::{
+            target::Target,
+            witness::{PartialWitness, WitnessWrite},
+        },
+        plonk::{
+            circuit_builder::CircuitBuilder,
+            circuit_data::{CircuitConfig, CircuitData},
+            config::PoseidonGoldilocksConfig,
+            proof::ProofWithPublicInputs,
+        },
+    };
+
+    use crate::{
+        biguint::WitnessBigUint,
+        build_validator_balance_circuit::{
+            ValidatorBalanceProofExt, CURRENT_EPOCH_PUB_INDEX, RANGE_ACTIVE_TOTAL_VALUE_PUB_INDEX,
+            RANGE_BALANCES_ROOT_PUB_INDEX, RANGE_COUNTED_VALIDATORS_PUB_INDEX,
+            RANGE_EFFECTIVE_BALANCE_TOTAL_VALUE_PUB_INDEX, RANGE_EXITED_TOTAL_VALUE_PUB_INDEX,
+            RANGE_PENDING_TOTAL_VALUE_PUB_INDEX, RANGE_SLASHED_TOTAL_VALUE_PUB_INDEX,
+            RANGE_TOTAL_VALUE_PUB_INDEX, RANGE_VALIDATOR_COMMITMENT_PUB_INDEX,
+            RANGE_VALID_UNTIL_EPOCH_PUB_INDEX, RANGE_WITHDRAWABLE_TOTAL_VALUE_PUB_INDEX,
+        },
+        utils::{ETH_SHA256_BIT_SIZE, POSEIDON_HASH_SIZE},
+    };
+
+    use super::{
+        balance_inner_level_arities, build_inner_level_circuit,
+        build_inner_level_circuit_with_arity, BalanceInnerCircuitTargets,
+    };
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = GoldilocksField;
+
+    type ChildCircuit = (Vec<Target>, CircuitData<F, C, D>);
+    type InnerCircuit = (BalanceInnerCircuitTargets, CircuitData<F, C, D>);
+
+    /// A circuit with the public inputs of a balance proof, all of them taken
+    /// from the witness.
+    fn build_child_circuit() -> ChildCircuit {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+
+        let public_inputs = builder.add_virtual_targets(RANGE_VALID_UNTIL_EPOCH_PUB_INDEX + 2);
+        builder.register_public_inputs(&public_inputs);
+
+        (public_inputs, builder.build::<C>())
+    }
+
+    fn set_u64(values: &mut [u64], index: usize, value: u64) {
+        values[index] = value & 0xFFFFFFFF;
+        values[index + 1] = value >> 32;
+    }
+
+    /// Proves a child whose roots and sums are derived from `seed`.
+    fn prove_child(
+        (public_inputs, data): &ChildCircuit,
+        seed: u64,
+        current_epoch: u64,
+        valid_until_epoch: u64,
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        let mut values = vec![0; public_inputs.len()];
+
+        for i in 0..ETH_SHA256_BIT_SIZE {
+            values[RANGE_BALANCES_ROOT_PUB_INDEX + i] =
+                (seed.wrapping_mul(0x9E3779B97F4A7C15) >> (i % 64)) & 1;
+        }
+
+        for i in 0..POSEIDON_HASH_SIZE {
+            values[RANGE_VALIDATOR_COMMITMENT_PUB_INDEX + i] = seed * 10 + i as u64;
+        }
+
+        for (index, value) in [
+            (RANGE_TOTAL_VALUE_PUB_INDEX, seed * 32_000_000_000),
+            (RANGE_SLASHED_TOTAL_VALUE_PUB_INDEX, seed),
+            (
+                RANGE_EFFECTIVE_BALANCE_TOTAL_VALUE_PUB_INDEX,
+                seed * 31_000_000_000,
+            ),
+            (RANGE_PENDING_TOTAL_VALUE_PUB_INDEX, seed * 2),
+            (RANGE_ACTIVE_TOTAL_VALUE_PUB_INDEX, seed * 3),
+            (RANGE_EXITED_TOTAL_VALUE_PUB_INDEX, seed * 4),
+            (RANGE_WITHDRAWABLE_TOTAL_VALUE_PUB_INDEX, seed * 5),
+            (CURRENT_EPOCH_PUB_INDEX, current_epoch),
+            (RANGE_VALID_UNTIL_EPOCH_PUB_INDEX, valid_until_epoch),
+        ] {
+            set_u64(&mut values, index, value);
+        }
+
+        values[RANGE_COUNTED_VALIDATORS_PUB_INDEX] = seed;
+
+        let mut pw = PartialWitness::new();
+
+        for (target, value) in public_inputs.iter().zip(values) {
+            pw.set_target(*target, F::from_canonical_u64(value));
+        }
+
+        data.prove(pw)
+    }
+
+    fn prove_inner(
+        (targets, data): &InnerCircuit,
+        children: &[ProofWithPublicInputs<F, C, D>],
+        current_epoch: u64,
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        let mut pw = PartialWitness::new();
+
+        for (target, child) in targets.proofs.iter().zip(children) {
+            pw.set_proof_with_pis_target(target, child);
+        }
+
+        pw.set_biguint_target(&targets.current_epoch, &BigUint::from(current_epoch));
+
+        data.prove(pw)
+    }
+
+    #[test]
+    fn test_4_ary_level_matches_binary_levels() -> Result<()> {
+        let child = build_child_circuit();
+
+        let binary_level = build_inner_level_circuit(&child.1);
+        let second_binary_level = build_inner_level_circuit(&binary_level.1);
+        let quaternary_level = build_inner_level_circuit_with_arity(&child.1, 4);
+
+        let children = (1..=4)
+            .map(|seed| prove_child(&child, seed, 10, 20))
+            .collect::<Result<Vec<_>>>()?;
+
+        let binary_proofs = [
+            prove_inner(&binary_level, &children[0..2], 10)?,
+            prove_inner(&binary_level, &children[2..4], 10)?,
+        ];
+
+        let binary_proof = prove_inner(&second_binary_level, &binary_proofs, 10)?;
+        let quaternary_proof = prove_inner(&quaternary_level, &children, 10)?;
+
+        assert_eq!(
+            quaternary_proof.get_range_balances_root(),
+            binary_proof.get_range_balances_root()
+        );
+        assert_eq!(
+            quaternary_proof.get_range_validator_commitment(),
+            binary_proof.get_range_validator_commitment()
+        );
+        assert_eq!(
+            quaternary_proof.get_range_total_value(),
+            BigUint::from(10 * 32_000_000_000u64)
+        );
+        assert_eq!(
+            quaternary_proof.get_range_counted_validators(),
+            binary_proof.get_range_counted_validators()
+        );
+
+        for (quaternary_sum, binary_sum) in [
+            (
+                quaternary_proof.get_range_total_value(),
+                binary_proof.get_range_total_value(),
+            ),
+            (
+                quaternary_proof.get_range_slashed_total_value(),
+                binary_proof.get_range_slashed_total_value(),
+            ),
+            (
+                quaternary_proof.get_range_effective_balance_total_value(),
+                binary_proof.get_range_effective_balance_total_value(),
+            ),
+            (
+                quaternary_proof.get_range_pending_total_value(),
+                binary_proof.get_range_pending_total_value(),
+            ),
+            (
+                quaternary_proof.get_range_active_total_value(),
+                binary_proof.get_range_active_total_value(),
+            ),
+            (
+                quaternary_proof.get_range_exited_total_value(),
+                binary_proof.get_range_exited_total_value(),
+            ),
+            (
+                quaternary_proof.get_range_withdrawable_total_value(),
+                binary_proof.get_range_withdrawable_total_value(),
+            ),
+        ] {
+            assert_eq!(quaternary_sum, binary_sum);
+        }
+
+        quaternary_level.1.verify(quaternary_proof)
+    }
+
+    #[test]
+    fn test_children_of_earlier_epochs_are_carried_forward() -> Result<()> {
+        let child = build_child_circuit();
+        let inner = build_inner_level_circuit(&child.1);
+
+        let children = [
+            prove_child(&child, 1, 10, 20)?,
+            prove_child(&child, 2, 12, 15)?,
+        ];
+
+        let proof = prove_inner(&inner, &children, 14)?;
+
+        assert_eq!(proof.get_current_epoch(), BigUint::from(14u64));
+        assert_eq!(proof.get_range_valid_until_epoch(), BigUint::from(15u64));
+
+        inner.1.verify(proof)
+    }
+
+    #[test]
+    #[should_panic(expected = "set twice with different values")]
+    fn test_children_past_their_valid_until_epoch_are_rejected() {
+        let child = build_child_circuit();
+        let inner = build_inner_level_circuit(&child.1);
+
+        let children = [
+            prove_child(&child, 1, 10, 20).unwrap(),
+            prove_child(&child, 2, 12, 15).unwrap(),
+        ];
+
+        let proof = prove_inner(&inner, &children, 15).unwrap();
+
+        inner.1.verify(proof).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "set twice with different values")]
+    fn test_children_of_later_epochs_are_rejected() {
+        let child = build_child_circuit();
+        let inner = build_inner_level_circuit(&child.1);
+
+        let children = [
+            prove_child(&child, 1, 10, 20).unwrap(),
+            prove_child(&child, 2, 13, 20).unwrap(),
+        ];
+
+        let proof = prove_inner(&inner, &children, 12).unwrap();
+
+        inner.1.verify(proof).unwrap();
+    }
+
+    #[test]
+    fn test_balance_inner_level_arities() {
+        assert_eq!(balance_inner_level_arities(37, 2), vec![2; 37]);
+
+        let mut arities = vec![4; 18];
+        arities.push(2);
+        assert_eq!(balance_inner_level_arities(37, 4), arities);
+
+        let mut arities = vec![8; 12];
+        arities.push(2);
+        assert_eq!(balance_inner_level_arities(37, 8), arities);
+
+        let mut arities = vec![8; 12];
+        arities.push(4);
+        assert_eq!(balance_inner_level_arities(38, 8), arities);
+    }
+}
@@ -0,0 +1,546 @@
+use plonky2::{
+    field::{goldilocks_field::GoldilocksField, types::Field},
+    hash::{
+        hash_types::{HashOutTarget, MerkleCapTarget},
+        poseidon::PoseidonHash,
+    },
+    iop::target::{BoolTarget, Target},
+    plonk::{
+        circuit_builder::CircuitBuilder,
+        circuit_data::{CircuitConfig, CircuitData, CommonCircuitData, VerifierCircuitTarget},
+        config::{GenericConfig, PoseidonGoldilocksConfig},
+        proof::ProofWithPublicInputsTarget,
+    },
+    util::serialization::{Buffer, IoResult, Read, Write},
+};
+
+use crate::{
+    build_commitment_mapper_first_level_circuit::{
+        CommitmentMapperProofTargetExt, SHA256_HASH_PUB_INDEX,
+    },
+    sha256::make_circuits,
+    targets_serialization::{ReadTargets, WriteTargets},
+    utils::{ETH_SHA256_BIT_SIZE, POSEIDON_HASH_SIZE},
+};
+
+// The first public inputs are the same as the ones of the level circuits
+pub const LEVEL_PUB_INDEX: usize = SHA256_HASH_PUB_INDEX + ETH_SHA256_BIT_SIZE;
+pub const VERIFIER_DATA_PUB_INDEX: usize = LEVEL_PUB_INDEX + 1;
+
+pub struct CommitmentMapperCyclicCircuitTargets {
+    pub leaf_proof1: ProofWithPublicInputsTarget<2>,
+    pub leaf_proof2: ProofWithPublicInputsTarget<2>,
+    pub cyclic_proof1: ProofWithPublicInputsTarget<2>,
+    pub cyclic_proof2: ProofWithPublicInputsTarget<2>,
+    pub verifier_circuit_target: VerifierCircuitTarget,
+    pub is_leaf_level: BoolTarget,
+    pub is_zero: BoolTarget,
+}
+
+impl ReadTargets for CommitmentMapperCyclicCircuitTargets {
+    fn read_targets(data: &mut Buffer) -> IoResult<CommitmentMapperCyclicCircuitTargets> {
+        Ok(CommitmentMapperCyclicCircuitTargets {
+            leaf_proof1: data.read_target_proof_with_public_inputs()?,
+            leaf_proof2: data.read_target_proof_with_public_inputs()?,
+            cyclic_proof1: data.read_target_proof_with_public_inputs()?,
+            cyclic_proof2: data.read_target_proof_with_public_inputs()?,
+            verifier_circuit_target: data.read_target_verifier_circuit()?,
+            is_leaf_level: data.read_target_bool()?,
+            is_zero: data.read_target_bool()?,
+        })
+    }
+}
+
+impl WriteTargets for CommitmentMapperCyclicCircuitTargets {
+    fn write_targets(&self) -> IoResult<Vec<u8>> {
+        let mut data = Vec::<u8>::new();
+
+        data.write_target_proof_with_public_inputs(&self.leaf_proof1)?;
+        data.write_target_proof_with_public_inputs(&self.leaf_proof2)?;
+        data.write_target_proof_with_public_inputs(&self.cyclic_proof1)?;
+        data.write_target_proof_with_public_inputs(&self.cyclic_proof2)?;
+        data.write_target_verifier_circuit(&self.verifier_circuit_target)?;
+        data.write_target_bool(self.is_leaf_level)?;
+        data.write_target_bool(self.is_zero)?;
+
+        Ok(data)
+    }
+}
+
+pub trait CommitmentMapperCyclicProofTargetExt {
+    fn get_commitment_mapper_level(&self) -> Target;
+
+    fn get_commitment_mapper_verifier_data(
+        &self,
+        common_data: &CommonCircuitData<GoldilocksField, 2>,
+    ) -> VerifierCircuitTarget;
+}
+
+impl CommitmentMapperCyclicProofTargetExt for ProofWithPublicInputsTarget<2> {
+    fn get_commitment_mapper_level(&self) -> Target {
+        self.public_inputs[LEVEL_PUB_INDEX]
+    }
+
+    fn get_commitment_mapper_verifier_data(
+        &self,
+        common_data: &CommonCircuitData<GoldilocksField, 2>,
+    ) -> VerifierCircuitTarget {
+        let hash_at = |index: usize| HashOutTarget {
+            elements: self.public_inputs[index..index + POSEIDON_HASH_SIZE]
+                .try_into()
+                .unwrap(),
+        };
+
+        VerifierCircuitTarget {
+            circuit_digest: hash_at(VERIFIER_DATA_PUB_INDEX),
+            constants_sigmas_cap: MerkleCapTarget(
+                (1..=common_data.config.fri_config.num_cap_elements())
+                    .map(|i| hash_at(VERIFIER_DATA_PUB_INDEX + i * POSEIDON_HASH_SIZE))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Checks a proof of the cyclic circuit outside of the cycle. The verifier data
+/// in its public inputs must be the one of the cyclic circuit and the proof must
+/// be for the root of a tree with `level` levels.
+pub fn verify_commitment_mapper_cyclic_proof_public_inputs(
+    builder: &mut CircuitBuilder<GoldilocksField, 2>,
+    proof: &ProofWithPublicInputsTarget<2>,
+    verifier_circuit_target: &VerifierCircuitTarget,
+    common_data: &CommonCircuitData<GoldilocksField, 2>,
+    level: usize,
+) {
+    let verifier_data = proof.get_commitment_mapper_verifier_data(common_data);
+
+    builder.connect_hashes(
+        verifier_data.circuit_digest,
+        verifier_circuit_target.circuit_digest,
+    );
+    builder.connect_merkle_caps(
+        &verifier_data.constants_sigmas_cap,
+        &verifier_circuit_target.constants_sigmas_cap,
+    );
+
+    let level = builder.constant(GoldilocksField::from_canonical_usize(level));
+    builder.connect(proof.get_commitment_mapper_level(), level);
+}
+
+/// A single circuit for every inner level of the commitment mapper tree. It
+/// hashes either two leaf proofs (`is_leaf_level`) or two of its own proofs
+/// and publishes the level of the resulting node.
+pub fn build_commitment_mapper_cyclic_circuit(
+    leaf_circuit_data: &CircuitData<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+) -> (
+    CommitmentMapperCyclicCircuitTargets,
+    CircuitData<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+) {
+    // The circuit verifies proofs with its own common data, so it is rebuilt
+    // until the common data it expects is the one it ends up with
+    let mut common_data = leaf_circuit_data.common.clone();
+
+    loop {
+        let (targets, data) = build_commitment_mapper_cyclic_circuit_with_common_data(
+            leaf_circuit_data,
+            &common_data,
+        );
+
+        if data.common == common_data {
+            return (targets, data);
+        }
+
+        common_data = data.common;
+    }
+}
+
+fn build_commitment_mapper_cyclic_circuit_with_common_data(
+    leaf_circuit_data: &CircuitData<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+    common_data: &CommonCircuitData<GoldilocksField, 2>,
+) -> (
+    CommitmentMapperCyclicCircuitTargets,
+    CircuitData<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+) {
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    let standard_recursion_config = CircuitConfig::standard_recursion_config();
+
+    let mut builder = CircuitBuilder::<F, D>::new(standard_recursion_config);
+
+    let poseidon_hash_tree_root = builder.add_virtual_hash();
+    let sha256_hash_tree_root = builder.add_virtual_targets(ETH_SHA256_BIT_SIZE);
+    let level = builder.add_virtual_target();
+
+    builder.register_public_inputs(&poseidon_hash_tree_root.elements);
+    builder.register_public_inputs(&sha256_hash_tree_root);
+    builder.register_public_input(level);
+
+    let verifier_circuit_target = builder.add_verifier_data_public_inputs();
+
+    let mut common_data = common_data.clone();
+    common_data.num_public_inputs = builder.num_public_inputs();
+
+    let is_leaf_level = builder.add_virtual_bool_target_safe();
+    let is_inner_level = builder.not(is_leaf_level);
+
+    let leaf_verifier_circuit_target =
+        builder.constant_verifier_data(&leaf_circuit_data.verifier_only);
+
+    let leaf_proof1 = builder.add_virtual_proof_with_pis(&leaf_circuit_data.common);
+    let leaf_proof2 = builder.add_virtual_proof_with_pis(&leaf_circuit_data.common);
+
+    for leaf_proof in [&leaf_proof1, &leaf_proof2] {
+        builder
+            .conditionally_verify_proof_or_dummy::<C>(
+                is_leaf_level,
+                leaf_proof,
+                &leaf_verifier_circuit_target,
+                &leaf_circuit_data.common,
+            )
+            .unwrap();
+    }
+
+    let cyclic_proof1 = builder.add_virtual_proof_with_pis(&common_data);
+    let cyclic_proof2 = builder.add_virtual_proof_with_pis(&common_data);
+
+    for cyclic_proof in [&cyclic_proof1, &cyclic_proof2] {
+        builder
+            .conditionally_verify_cyclic_proof_or_dummy::<C>(
+                is_inner_level,
+                cyclic_proof,
+                &common_data,
+            )
+            .unwrap();
+    }
+
+    // Both children of an inner node are on the same level
+    let level1 = cyclic_proof1.get_commitment_mapper_level();
+    let level2 = cyclic_proof2.get_commitment_mapper_level();
+
+    let levels_diff = builder.sub(level1, level2);
+    let levels_diff = builder.mul(levels_diff, is_inner_level.target);
+    builder.assert_zero(levels_diff);
+
+    let one = builder.one();
+    let next_level = builder.add(level1, one);
+    let new_level = builder.select(is_leaf_level, one, next_level);
+    builder.connect(level, new_level);
+
+    let is_zero = builder.add_virtual_bool_target_safe();
+    let is_one = builder.not(is_zero);
+
+    let mut children_hashes = Vec::new();
+
+    for (leaf_proof, cyclic_proof) in [
+        (&leaf_proof1, &cyclic_proof1),
+        (&leaf_proof2, &cyclic_proof2),
+    ] {
+        let poseidon_hash = leaf_proof
+            .get_commitment_mapper_poseidon_hash_tree_root()
+            .elements
+            .iter()
+            .zip(
+                cyclic_proof
+                    .get_commitment_mapper_poseidon_hash_tree_root()
+                    .elements
+                    .iter(),
+            )
+            .map(|(leaf_element, cyclic_element)| {
+                let element = builder.select(is_leaf_level, *leaf_element, *cyclic_element);
+                builder.mul(element, is_one.target)
+            })
+            .collect::<Vec<Target>>();
+
+        let sha256_hash = leaf_proof
+            .get_commitment_mapper_sha256_hash_tree_root()
+            .iter()
+            .zip(
+                cyclic_proof
+                    .get_commitment_mapper_sha256_hash_tree_root()
+                    .iter(),
+            )
+            .map(|(leaf_bit, cyclic_bit)| {
+                let bit = builder.select(is_leaf_level, leaf_bit.target, cyclic_bit.target);
+                builder.mul(bit, is_one.target)
+            })
+            .collect::<Vec<Target>>();
+
+        children_hashes.push((poseidon_hash, sha256_hash));
+    }
+
+    let hasher = make_circuits(&mut builder, (2 * ETH_SHA256_BIT_SIZE) as u64);
+
+    for i in 0..ETH_SHA256_BIT_SIZE {
+        builder.connect(hasher.message[i].target, children_hashes[0].1[i]);
+        builder.connect(
+            hasher.message[i + ETH_SHA256_BIT_SIZE].target,
+            children_hashes[1].1[i],
+        );
+    }
+
+    let hash = builder.hash_n_to_hash_no_pad::<PoseidonHash>(
+        children_hashes[0]
+            .0
+            .iter()
+            .chain(children_hashes[1].0.iter())
+            .cloned()
+            .collect(),
+    );
+
+    builder.connect_hashes(poseidon_hash_tree_root, hash);
+
+    for i in 0..ETH_SHA256_BIT_SIZE {
+        builder.connect(sha256_hash_tree_root[i], hasher.digest[i].target);
+    }
+
+    let data = builder.build::<C>();
+
+    (
+        CommitmentMapperCyclicCircuitTargets {
+            leaf_proof1,
+            leaf_proof2,
+            cyclic_proof1,
+            cyclic_proof2,
+            verifier_circuit_target,
+            is_leaf_level,
+            is_zero,
+        },
+        data,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use anyhow::Result;
+    use plonky2::{
+        field::{goldilocks_field::GoldilocksField, types::Field},
+        iop::{
+            target::Target,
+            witness::{PartialWitness, WitnessWrite},
+        },
+        plonk::{
+            circuit_builder::CircuitBuilder,
+            circuit_data::{CircuitConfig, CircuitData},
+            config::PoseidonGoldilocksConfig,
+            proof::ProofWithPublicInputs,
+        },
+        recursion::dummy_circuit::cyclic_base_proof,
+    };
+
+    use crate::{
+        build_commitment_mapper_first_level_circuit::CommitmentMapperProofExt,
+        build_commitment_mapper_inner_level_circuit::{
+            build_commitment_mapper_inner_circuit, CommitmentMapperInnerCircuitTargets,
+        },
+        utils::{ETH_SHA256_BIT_SIZE, POSEIDON_HASH_SIZE},
+    };
+
+    use super::{
+        build_commitment_mapper_cyclic_circuit, CommitmentMapperCyclicCircuitTargets,
+        LEVEL_PUB_INDEX,
+    };
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = GoldilocksField;
+
+    type Proof = ProofWithPublicInputs<F, C, D>;
+    type LeafCircuit = (Vec<Target>, CircuitData<F, C, D>);
+    type LevelCircuit = (CommitmentMapperInnerCircuitTargets, CircuitData<F, C, D>);
+    type CyclicCircuit = (CommitmentMapperCyclicCircuitTargets, CircuitData<F, C, D>);
+
+    /// A circuit with the public inputs of a leaf proof, all of them taken from
+    /// the witness.
+    fn build_leaf_circuit() -> LeafCircuit {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+
+        let public_inputs = builder.add_virtual_targets(POSEIDON_HASH_SIZE + ETH_SHA256_BIT_SIZE);
+        builder.register_public_inputs(&public_inputs);
+
+        (public_inputs, builder.build::<C>())
+    }
+
+    /// Proves a leaf whose roots are derived from `seed`.
+    fn prove_leaf((public_inputs, data): &LeafCircuit, seed: u64) -> Result<Proof> {
+        let mut pw = PartialWitness::new();
+
+        for (i, target) in public_inputs.iter().enumerate() {
+            let value = if i < POSEIDON_HASH_SIZE {
+                seed * 10 + i as u64
+            } else {
+                (seed.wrapping_mul(0x9E3779B97F4A7C15) >> (i % 64)) & 1
+            };
+
+            pw.set_target(*target, F::from_canonical_u64(value));
+        }
+
+        data.prove(pw)
+    }
+
+    fn prove_level(
+        (targets, data): &LevelCircuit,
+        children: [&Proof; 2],
+        is_zero: bool,
+    ) -> Result<Proof> {
+        let mut pw = PartialWitness::new();
+
+        pw.set_proof_with_pis_target(&targets.proof1, children[0]);
+        pw.set_proof_with_pis_target(&targets.proof2, children[1]);
+        pw.set_bool_target(targets.is_zero, is_zero);
+
+        data.prove(pw)
+    }
+
+    fn prove_cyclic(
+        leaf_data: &CircuitData<F, C, D>,
+        (targets, data): &CyclicCircuit,
+        children: [&Proof; 2],
+        is_leaf_level: bool,
+        is_zero: bool,
+    ) -> Result<Proof> {
+        let (proof_targets, dummy_proof_targets, dummy_proof) = if is_leaf_level {
+            (
+                [&targets.leaf_proof1, &targets.leaf_proof2],
+                [&targets.cyclic_proof1, &targets.cyclic_proof2],
+                cyclic_base_proof(&data.common, &data.verifier_only, HashMap::new()),
+            )
+        } else {
+            (
+                [&targets.cyclic_proof1, &targets.cyclic_proof2],
+                [&targets.leaf_proof1, &targets.leaf_proof2],
+                cyclic_base_proof(&leaf_data.common, &leaf_data.verifier_only, HashMap::new()),
+            )
+        };
+
+        let mut pw = PartialWitness::new();
+
+        for (proof_target, child) in proof_targets.into_iter().zip(children) {
+            pw.set_proof_with_pis_target(proof_target, child);
+        }
+
+        for dummy_proof_target in dummy_proof_targets {
+            pw.set_proof_with_pis_target(dummy_proof_target, &dummy_proof);
+        }
+
+        pw.set_verifier_data_target(&targets.verifier_circuit_target, &data.verifier_only);
+        pw.set_bool_target(targets.is_leaf_level, is_leaf_level);
+        pw.set_bool_target(targets.is_zero, is_zero);
+
+        data.prove(pw)
+    }
+
+    fn assert_same_roots(cyclic_proof: &Proof, level_proof: &Proof, level: u64) {
+        assert_eq!(
+            cyclic_proof.get_commitment_mapper_poseidon_hash_tree_root(),
+            level_proof.get_commitment_mapper_poseidon_hash_tree_root()
+        );
+        assert_eq!(
+            cyclic_proof.get_commitment_mapper_sha256_hash_tree_root(),
+            level_proof.get_commitment_mapper_sha256_hash_tree_root()
+        );
+        assert_eq!(
+            cyclic_proof.public_inputs[LEVEL_PUB_INDEX],
+            F::from_canonical_u64(level)
+        );
+    }
+
+    #[test]
+    fn test_cyclic_proofs_match_level_proofs() -> Result<()> {
+        let leaf = build_leaf_circuit();
+        let first_level = build_commitment_mapper_inner_circuit(&leaf.1);
+        let second_level = build_commitment_mapper_inner_circuit(&first_level.1);
+        let cyclic = build_commitment_mapper_cyclic_circuit(&leaf.1);
+
+        let leaves = (1..=4)
+            .map(|seed| prove_leaf(&leaf, seed))
+            .collect::<Result<Vec<_>>>()?;
+
+        let level_proofs = [
+            prove_level(&first_level, [&leaves[0], &leaves[1]], false)?,
+            prove_level(&first_level, [&leaves[2], &leaves[3]], false)?,
+        ];
+        let level_root = prove_level(&second_level, [&level_proofs[0], &level_proofs[1]], false)?;
+
+        let cyclic_proofs = [
+            prove_cyclic(&leaf.1, &cyclic, [&leaves[0], &leaves[1]], true, false)?,
+            prove_cyclic(&leaf.1, &cyclic, [&leaves[2], &leaves[3]], true, false)?,
+        ];
+        let cyclic_root = prove_cyclic(
+            &leaf.1,
+            &cyclic,
+            [&cyclic_proofs[0], &cyclic_proofs[1]],
+            false,
+            false,
+        )?;
+
+        for (cyclic_proof, level_proof) in cyclic_proofs.iter().zip(level_proofs.iter()) {
+            assert_same_roots(cyclic_proof, level_proof, 1);
+        }
+
+        assert_same_roots(&cyclic_root, &level_root, 2);
+
+        cyclic.1.verify(cyclic_root)
+    }
+
+    #[test]
+    fn test_cyclic_zero_proofs_match_level_proofs() -> Result<()> {
+        let leaf = build_leaf_circuit();
+        let first_level = build_commitment_mapper_inner_circuit(&leaf.1);
+        let second_level = build_commitment_mapper_inner_circuit(&first_level.1);
+        let cyclic = build_commitment_mapper_cyclic_circuit(&leaf.1);
+
+        let leaves = [prove_leaf(&leaf, 1)?, prove_leaf(&leaf, 2)?];
+
+        let level_proof = prove_level(&first_level, [&leaves[0], &leaves[1]], true)?;
+        let level_root = prove_level(&second_level, [&level_proof, &level_proof], true)?;
+
+        let cyclic_proof = prove_cyclic(&leaf.1, &cyclic, [&leaves[0], &leaves[1]], true, true)?;
+        let cyclic_root = prove_cyclic(
+            &leaf.1,
+            &cyclic,
+            [&cyclic_proof, &cyclic_proof],
+            false,
+            true,
+        )?;
+
+        assert_same_roots(&cyclic_proof, &level_proof, 1);
+        assert_same_roots(&cyclic_root, &level_root, 2);
+
+        cyclic.1.verify(cyclic_root)
+    }
+
+    #[test]
+    #[should_panic(expected = "set twice with different values")]
+    fn test_children_on_different_levels_are_rejected() {
+        let leaf = build_leaf_circuit();
+        let cyclic = build_commitment_mapper_cyclic_circuit(&leaf.1);
+
+        let leaves = [prove_leaf(&leaf, 1).unwrap(), prove_leaf(&leaf, 2).unwrap()];
+
+        let first_level_proof =
+            prove_cyclic(&leaf.1, &cyclic, [&leaves[0], &leaves[1]], true, false).unwrap();
+        let second_level_proof = prove_cyclic(
+            &leaf.1,
+            &cyclic,
+            [&first_level_proof, &first_level_proof],
+            false,
+            false,
+        )
+        .unwrap();
+
+        let proof = prove_cyclic(
+            &leaf.1,
+            &cyclic,
+            [&first_level_proof, &second_level_proof],
+            false,
+            false,
+        )
+        .unwrap();
+
+        cyclic.1.verify(proof).unwrap();
+    }
+}
@@ -5,7 +5,7 @@ use plonky2::{
     iop::target::BoolTarget,
     plonk::{
         circuit_builder::CircuitBuilder,
-        circuit_data::CircuitConfig,
+        circuit_data::{CircuitConfig, CircuitData},
         config::{GenericConfig, PoseidonGoldilocksConfig},
         proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget},
     },
@@ -13,7 +13,10 @@ use plonky2::{
 
 use crate::{
     utils::{ETH_SHA256_BIT_SIZE, POSEIDON_HASH_SIZE},
-    validator_commitment_mapper::{validator_commitment_mapper, ValidatorCommitmentTargets},
+    validator_commitment_mapper::{
+        validator_commitment_mapper, validators_commitment_mapper, ValidatorCommitmentTargets,
+        ValidatorsCommitmentTargets,
+    },
 };
 
 pub const POSEIDON_HASH_PUB_INDEX: usize = 0;
@@ -105,6 +108,40 @@ pub fn build_commitment_mapper_first_level_circuit() -> (
     (validator_commitment_result, data)
 }
 
+/// The same public inputs as `build_commitment_mapper_first_level_circuit`, for
+/// the subtree of `validators_len` validators.
+pub fn build_commitment_mapper_batch_first_level_circuit(
+    validators_len: usize,
+) -> (
+    ValidatorsCommitmentTargets,
+    CircuitData<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+) {
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    let standard_recursion_config = CircuitConfig::standard_recursion_config();
+
+    let mut builder = CircuitBuilder::<F, D>::new(standard_recursion_config);
+
+    let validators_commitment_result = validators_commitment_mapper(&mut builder, validators_len);
+
+    builder.register_public_inputs(
+        &validators_commitment_result
+            .poseidon_hash_tree_root
+            .elements,
+    );
+    builder.register_public_inputs(
+        &validators_commitment_result
+            .sha256_hash_tree_root
+            .map(|x| x.target),
+    );
+
+    let data = builder.build::<C>();
+
+    (validators_commitment_result, data)
+}
+
 #[cfg(test)]
 mod test {
     use anyhow::Result;
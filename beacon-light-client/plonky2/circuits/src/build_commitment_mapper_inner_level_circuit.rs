@@ -72,11 +72,7 @@ pub fn build_commitment_mapper_inner_circuit(
 
     let mut builder = CircuitBuilder::<F, D>::new(standard_recursion_config);
 
-    let verifier_circuit_target = VerifierCircuitTarget {
-        constants_sigmas_cap: builder
-            .add_virtual_cap(inner_circuit_data.common.config.fri_config.cap_height),
-        circuit_digest: builder.add_virtual_hash(),
-    };
+    let verifier_circuit_target = builder.constant_verifier_data(&inner_circuit_data.verifier_only);
 
     let pt1 = builder.add_virtual_proof_with_pis(&inner_circuit_data.common);
     let pt2: ProofWithPublicInputsTarget<2> =
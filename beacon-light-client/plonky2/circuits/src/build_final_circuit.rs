@@ -10,21 +10,54 @@ use plonky2::{
     iop::target::{BoolTarget, Target},
     plonk::{
         circuit_builder::CircuitBuilder,
-        circuit_data::{CircuitConfig, CircuitData, VerifierCircuitTarget},
+        circuit_data::{CircuitConfig, CircuitData, CommonCircuitData, VerifierCircuitTarget},
         config::{GenericConfig, PoseidonGoldilocksConfig},
         proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget},
     },
 };
+use plonky2_u32::gadgets::arithmetic_u32::{CircuitBuilderU32, U32Target};
+use sha2::{Digest, Sha256};
 
 use crate::{
+    beacon_block_header_hash_tree_root::{
+        hash_tree_root_beacon_block_header, BeaconBlockHeaderTargets,
+    },
     biguint::{BigUintTarget, CircuitBuilderBiguint},
+    build_commitment_mapper_cyclic_circuit::verify_commitment_mapper_cyclic_proof_public_inputs,
     build_commitment_mapper_first_level_circuit::CommitmentMapperProofTargetExt,
     build_validator_balance_circuit::ValidatorBalanceProofTargetsExt,
-    is_valid_merkle_branch::{is_valid_merkle_branch, IsValidMerkleBranchTargets},
+    fork::Fork,
+    is_valid_merkle_multiproof::{is_valid_merkle_multiproof, IsValidMerkleMultiproofTargets},
+    network_preset::NetworkPreset,
     sha256::make_circuits,
-    utils::{create_bool_target_array, ssz_num_to_bits, ETH_SHA256_BIT_SIZE},
+    ssz::extend_merkle_root,
+    utils::{
+        biguint_to_bits_target, create_bool_target_array, ssz_num_to_bits, ProofBiguintExt,
+        ProofTargetBiguintExt, ETH_SHA256_BIT_SIZE, POSEIDON_HASH_SIZE,
+    },
 };
 
+pub const FINAL_STATE_ROOT_PUB_INDEX: usize = 0;
+pub const FINAL_WITHDRAWAL_CREDENTIALS_COMMITMENT_PUB_INDEX: usize = 256;
+pub const FINAL_BALANCE_SUM_PUB_INDEX: usize = 260;
+pub const FINAL_SLASHED_BALANCE_SUM_PUB_INDEX: usize = 262;
+pub const FINAL_COUNTED_VALIDATORS_PUB_INDEX: usize = 264;
+pub const FINAL_EFFECTIVE_BALANCE_SUM_PUB_INDEX: usize = 265;
+pub const FINAL_PENDING_BALANCE_SUM_PUB_INDEX: usize = 267;
+pub const FINAL_ACTIVE_BALANCE_SUM_PUB_INDEX: usize = 269;
+pub const FINAL_EXITED_BALANCE_SUM_PUB_INDEX: usize = 271;
+pub const FINAL_WITHDRAWABLE_BALANCE_SUM_PUB_INDEX: usize = 273;
+// Only for proofs anchored to the block root
+pub const FINAL_BLOCK_ROOT_PUB_INDEX: usize = 275;
+pub const FINAL_BLOCK_SLOT_PUB_INDEX: usize = 531;
+// Only for proofs built with `commit_public_inputs`
+pub const FINAL_PUBLIC_INPUTS_COMMITMENT_PUB_INDEX: usize = 0;
+pub const FINAL_PUBLIC_INPUTS_COMMITMENT_LEN: usize = 8;
+
+// The number of public inputs of proofs built without `commit_public_inputs`
+pub const FINAL_PUBLIC_INPUTS_LEN: usize = FINAL_BLOCK_ROOT_PUB_INDEX;
+pub const FINAL_ANCHORED_PUBLIC_INPUTS_LEN: usize = FINAL_BLOCK_SLOT_PUB_INDEX + 2;
+
 pub struct BalanceFinalLayerTargets {
     pub proof: ProofWithPublicInputsTarget<2>,
     pub verifier_circuit_target: VerifierCircuitTarget,
@@ -39,13 +72,193 @@ pub struct FinalCircuitTargets {
     pub balance_circuit_targets: BalanceFinalLayerTargets,
     pub commitment_mapper_circuit_targets: CommitmentMapperFinalLayerTargets,
     pub slot: BigUintTarget,
-    pub slot_branch: [[BoolTarget; ETH_SHA256_BIT_SIZE]; 5],
     pub state_root: [BoolTarget; ETH_SHA256_BIT_SIZE],
-    pub validators_branch: [[BoolTarget; ETH_SHA256_BIT_SIZE]; 5],
-    pub balance_branch: [[BoolTarget; ETH_SHA256_BIT_SIZE]; 5],
+    pub state_root_multiproof: Vec<[BoolTarget; ETH_SHA256_BIT_SIZE]>,
     pub balance_sum: BigUintTarget,
-    pub withdrawal_credentials: BigUintTarget,
     pub validator_size_bits: [BoolTarget; ETH_SHA256_BIT_SIZE],
+    pub block_header: Option<BeaconBlockHeaderTargets>,
+}
+
+pub struct FinalCircuitPublicInputsTargets {
+    pub state_root: [BoolTarget; ETH_SHA256_BIT_SIZE],
+    pub withdrawal_credentials_commitment: HashOutTarget,
+    pub balance_sum: BigUintTarget,
+    pub slashed_balance_sum: BigUintTarget,
+    pub counted_validators: Target,
+    pub effective_balance_sum: BigUintTarget,
+    // pending, active, exited and withdrawable
+    pub lifecycle_sums: [BigUintTarget; 4],
+    pub block_root: Option<[BoolTarget; ETH_SHA256_BIT_SIZE]>,
+    pub block_slot: Option<BigUintTarget>,
+}
+
+impl FinalCircuitPublicInputsTargets {
+    pub fn register(&self, builder: &mut CircuitBuilder<F, D>) {
+        builder.register_public_inputs(&self.state_root.map(|x| x.target));
+
+        builder.register_public_inputs(&self.withdrawal_credentials_commitment.elements);
+
+        for sum in [&self.balance_sum, &self.slashed_balance_sum] {
+            builder.register_public_inputs(&sum.limbs.iter().map(|x| x.0).collect_vec());
+        }
+
+        builder.register_public_input(self.counted_validators);
+
+        for sum in [&self.effective_balance_sum]
+            .into_iter()
+            .chain(self.lifecycle_sums.iter())
+        {
+            builder.register_public_inputs(&sum.limbs.iter().map(|x| x.0).collect_vec());
+        }
+
+        if let (Some(block_root), Some(block_slot)) = (&self.block_root, &self.block_slot) {
+            builder.register_public_inputs(&block_root.map(|x| x.target));
+
+            builder.register_public_inputs(&block_slot.limbs.iter().map(|x| x.0).collect_vec());
+        }
+    }
+}
+
+/// The values committed to by a final proof built with `commit_public_inputs`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FinalCircuitPublicInputs {
+    pub state_root: [u8; 32],
+    pub withdrawal_credentials_commitment: [u64; POSEIDON_HASH_SIZE],
+    pub balance_sum: u64,
+    pub slashed_balance_sum: u64,
+    pub counted_validators: u64,
+    pub effective_balance_sum: u64,
+    pub pending_balance_sum: u64,
+    pub active_balance_sum: u64,
+    pub exited_balance_sum: u64,
+    pub withdrawable_balance_sum: u64,
+    pub block_root: Option<[u8; 32]>,
+    pub block_slot: Option<u64>,
+}
+
+impl FinalCircuitPublicInputs {
+    /// SHA-256 of the big-endian encoding of the values in public input order,
+    /// packed into big-endian u32 words.
+    pub fn commitment(&self) -> [u32; 8] {
+        let mut hasher = Sha256::new();
+
+        hasher.update(self.state_root);
+
+        for element in self.withdrawal_credentials_commitment {
+            hasher.update(element.to_be_bytes());
+        }
+
+        for value in [
+            self.balance_sum,
+            self.slashed_balance_sum,
+            self.counted_validators,
+            self.effective_balance_sum,
+            self.pending_balance_sum,
+            self.active_balance_sum,
+            self.exited_balance_sum,
+            self.withdrawable_balance_sum,
+        ] {
+            hasher.update(value.to_be_bytes());
+        }
+
+        if let (Some(block_root), Some(block_slot)) = (self.block_root, self.block_slot) {
+            hasher.update(block_root);
+            hasher.update(block_slot.to_be_bytes());
+        }
+
+        hasher
+            .finalize()
+            .chunks(4)
+            .map(|word| u32::from_be_bytes(word.try_into().unwrap()))
+            .collect_vec()
+            .try_into()
+            .unwrap()
+    }
+}
+
+pub fn final_circuit_public_inputs_commitment(
+    builder: &mut CircuitBuilder<F, D>,
+    public_inputs: &FinalCircuitPublicInputsTargets,
+) -> [U32Target; 8] {
+    let mut message = public_inputs.state_root.to_vec();
+
+    for element in public_inputs.withdrawal_credentials_commitment.elements {
+        let element = target_to_u64_biguint(builder, element);
+        message.extend(u64_biguint_to_be_bits(builder, &element));
+    }
+
+    for sum in [
+        &public_inputs.balance_sum,
+        &public_inputs.slashed_balance_sum,
+    ] {
+        message.extend(u64_biguint_to_be_bits(builder, sum));
+    }
+
+    let mut counted_validators = builder.biguint_from_target(public_inputs.counted_validators, 32);
+    counted_validators.limbs.push(builder.zero_u32());
+
+    message.extend(u64_biguint_to_be_bits(builder, &counted_validators));
+
+    for sum in [&public_inputs.effective_balance_sum]
+        .into_iter()
+        .chain(public_inputs.lifecycle_sums.iter())
+    {
+        message.extend(u64_biguint_to_be_bits(builder, sum));
+    }
+
+    if let (Some(block_root), Some(block_slot)) =
+        (&public_inputs.block_root, &public_inputs.block_slot)
+    {
+        message.extend(block_root);
+        message.extend(u64_biguint_to_be_bits(builder, block_slot));
+    }
+
+    let hasher = make_circuits(builder, message.len() as u64);
+
+    for i in 0..message.len() {
+        builder.connect(hasher.message[i].target, message[i].target);
+    }
+
+    hasher
+        .digest
+        .chunks(32)
+        .map(|word| U32Target(builder.le_sum(word.iter().rev())))
+        .collect_vec()
+        .try_into()
+        .unwrap()
+}
+
+/// The canonical u64 value of a field element, as two range checked limbs.
+fn target_to_u64_biguint(builder: &mut CircuitBuilder<F, D>, target: Target) -> BigUintTarget {
+    let (low, high) = builder.split_low_high(target, 32, 64);
+
+    let value = BigUintTarget {
+        limbs: vec![U32Target(low), U32Target(high)],
+    };
+
+    assert_is_canonical_u64(builder, &value);
+
+    value
+}
+
+/// Both `x` and `x + p` fit in 64 bits for any `x < 2^32 - 1`. The encoding is
+/// canonical only if it is below `p = 2^64 - 2^32 + 1`, that is if the high
+/// limb is all ones only when the low limb is zero.
+fn assert_is_canonical_u64(builder: &mut CircuitBuilder<F, D>, value: &BigUintTarget) {
+    let max_limb = builder.constant(F::from_canonical_u32(u32::MAX));
+    let is_high_limb_max = builder.is_equal(value.limbs[1].0, max_limb);
+
+    let low_limb_if_high_limb_max = builder.mul(is_high_limb_max.target, value.limbs[0].0);
+    builder.assert_zero(low_limb_if_high_limb_max);
+}
+
+fn u64_biguint_to_be_bits(
+    builder: &mut CircuitBuilder<F, D>,
+    value: &BigUintTarget,
+) -> Vec<BoolTarget> {
+    assert_eq!(value.num_limbs(), 2);
+
+    biguint_to_bits_target::<F, D, 2>(builder, value)
 }
 
 pub type FinalCircuitProof = ProofWithPublicInputs<GoldilocksField, PoseidonGoldilocksConfig, 2>;
@@ -53,14 +266,44 @@ pub type FinalCircuitProof = ProofWithPublicInputs<GoldilocksField, PoseidonGold
 pub trait FinalCircuitProofExt {
     fn get_final_circuit_state_root(&self) -> [u64; ETH_SHA256_BIT_SIZE];
 
-    fn get_final_circuit_withdrawal_credentials(&self) -> BigUint;
+    fn get_final_circuit_withdrawal_credentials_commitment(&self) -> [u64; POSEIDON_HASH_SIZE];
 
     fn get_final_circuit_balance_sum(&self) -> BigUint;
+
+    fn get_final_circuit_slashed_balance_sum(&self) -> BigUint;
+
+    fn get_final_circuit_counted_validators(&self) -> u64;
+
+    fn get_final_circuit_effective_balance_sum(&self) -> BigUint;
+
+    fn get_final_circuit_pending_balance_sum(&self) -> BigUint;
+
+    fn get_final_circuit_active_balance_sum(&self) -> BigUint;
+
+    fn get_final_circuit_exited_balance_sum(&self) -> BigUint;
+
+    fn get_final_circuit_withdrawable_balance_sum(&self) -> BigUint;
+
+    // Only for proofs anchored to the block root
+    fn get_final_circuit_block_root(&self) -> [u64; ETH_SHA256_BIT_SIZE];
+
+    fn get_final_circuit_block_slot(&self) -> BigUint;
+
+    // Only for proofs built with `commit_public_inputs`
+    fn get_final_circuit_public_inputs_commitment(
+        &self,
+    ) -> [u32; FINAL_PUBLIC_INPUTS_COMMITMENT_LEN];
+
+    fn is_final_circuit_public_inputs_commitment_valid(
+        &self,
+        public_inputs: &FinalCircuitPublicInputs,
+    ) -> bool;
 }
 
 impl FinalCircuitProofExt for FinalCircuitProof {
     fn get_final_circuit_state_root(&self) -> [u64; ETH_SHA256_BIT_SIZE] {
-        self.public_inputs[0..256]
+        self.public_inputs
+            [FINAL_STATE_ROOT_PUB_INDEX..FINAL_STATE_ROOT_PUB_INDEX + ETH_SHA256_BIT_SIZE]
             .iter()
             .map(|x| x.0 % GoldilocksField::ORDER)
             .collect_vec()
@@ -68,22 +311,123 @@ impl FinalCircuitProofExt for FinalCircuitProof {
             .unwrap()
     }
 
-    fn get_final_circuit_withdrawal_credentials(&self) -> BigUint {
-        BigUint::new(
-            self.public_inputs[256..264]
-                .iter()
-                .map(|x| (x.0 % GoldilocksField::ORDER) as u32)
-                .collect_vec(),
-        )
+    fn get_final_circuit_withdrawal_credentials_commitment(&self) -> [u64; POSEIDON_HASH_SIZE] {
+        self.public_inputs[FINAL_WITHDRAWAL_CREDENTIALS_COMMITMENT_PUB_INDEX
+            ..FINAL_WITHDRAWAL_CREDENTIALS_COMMITMENT_PUB_INDEX + POSEIDON_HASH_SIZE]
+            .iter()
+            .map(|x| x.0 % GoldilocksField::ORDER)
+            .collect_vec()
+            .try_into()
+            .unwrap()
     }
 
     fn get_final_circuit_balance_sum(&self) -> BigUint {
-        BigUint::new(
-            self.public_inputs[264..266]
-                .iter()
-                .map(|x| (x.0 % GoldilocksField::ORDER) as u32)
-                .collect_vec(),
-        )
+        self.biguint_at(FINAL_BALANCE_SUM_PUB_INDEX)
+    }
+
+    fn get_final_circuit_slashed_balance_sum(&self) -> BigUint {
+        self.biguint_at(FINAL_SLASHED_BALANCE_SUM_PUB_INDEX)
+    }
+
+    fn get_final_circuit_counted_validators(&self) -> u64 {
+        self.public_inputs[FINAL_COUNTED_VALIDATORS_PUB_INDEX].0 % GoldilocksField::ORDER
+    }
+
+    fn get_final_circuit_effective_balance_sum(&self) -> BigUint {
+        self.biguint_at(FINAL_EFFECTIVE_BALANCE_SUM_PUB_INDEX)
+    }
+
+    fn get_final_circuit_pending_balance_sum(&self) -> BigUint {
+        self.biguint_at(FINAL_PENDING_BALANCE_SUM_PUB_INDEX)
+    }
+
+    fn get_final_circuit_active_balance_sum(&self) -> BigUint {
+        self.biguint_at(FINAL_ACTIVE_BALANCE_SUM_PUB_INDEX)
+    }
+
+    fn get_final_circuit_exited_balance_sum(&self) -> BigUint {
+        self.biguint_at(FINAL_EXITED_BALANCE_SUM_PUB_INDEX)
+    }
+
+    fn get_final_circuit_withdrawable_balance_sum(&self) -> BigUint {
+        self.biguint_at(FINAL_WITHDRAWABLE_BALANCE_SUM_PUB_INDEX)
+    }
+
+    fn get_final_circuit_block_root(&self) -> [u64; ETH_SHA256_BIT_SIZE] {
+        self.public_inputs
+            [FINAL_BLOCK_ROOT_PUB_INDEX..FINAL_BLOCK_ROOT_PUB_INDEX + ETH_SHA256_BIT_SIZE]
+            .iter()
+            .map(|x| x.0 % GoldilocksField::ORDER)
+            .collect_vec()
+            .try_into()
+            .unwrap()
+    }
+
+    fn get_final_circuit_block_slot(&self) -> BigUint {
+        self.biguint_at(FINAL_BLOCK_SLOT_PUB_INDEX)
+    }
+
+    fn get_final_circuit_public_inputs_commitment(
+        &self,
+    ) -> [u32; FINAL_PUBLIC_INPUTS_COMMITMENT_LEN] {
+        self.public_inputs[FINAL_PUBLIC_INPUTS_COMMITMENT_PUB_INDEX
+            ..FINAL_PUBLIC_INPUTS_COMMITMENT_PUB_INDEX + FINAL_PUBLIC_INPUTS_COMMITMENT_LEN]
+            .iter()
+            .map(|x| (x.0 % GoldilocksField::ORDER) as u32)
+            .collect_vec()
+            .try_into()
+            .unwrap()
+    }
+
+    fn is_final_circuit_public_inputs_commitment_valid(
+        &self,
+        public_inputs: &FinalCircuitPublicInputs,
+    ) -> bool {
+        self.public_inputs.len() == 8
+            && self.get_final_circuit_public_inputs_commitment() == public_inputs.commitment()
+    }
+}
+
+/// Whether the proofs of the circuit publish the public inputs of the final
+/// circuit rather than their commitment, as `FinalCircuitProofTargetExt` expects.
+pub fn has_uncommitted_final_public_inputs(common: &CommonCircuitData<GoldilocksField, 2>) -> bool {
+    [FINAL_PUBLIC_INPUTS_LEN, FINAL_ANCHORED_PUBLIC_INPUTS_LEN].contains(&common.num_public_inputs)
+}
+
+pub type FinalCircuitProofTarget = ProofWithPublicInputsTarget<2>;
+
+// Only for proofs built without `commit_public_inputs`
+pub trait FinalCircuitProofTargetExt {
+    fn get_final_circuit_state_root(&self) -> [BoolTarget; ETH_SHA256_BIT_SIZE];
+
+    fn get_final_circuit_withdrawal_credentials_commitment(&self) -> HashOutTarget;
+
+    fn get_final_circuit_balance_sum(&self) -> BigUintTarget;
+}
+
+impl FinalCircuitProofTargetExt for FinalCircuitProofTarget {
+    fn get_final_circuit_state_root(&self) -> [BoolTarget; ETH_SHA256_BIT_SIZE] {
+        self.public_inputs
+            [FINAL_STATE_ROOT_PUB_INDEX..FINAL_STATE_ROOT_PUB_INDEX + ETH_SHA256_BIT_SIZE]
+            .iter()
+            .cloned()
+            .map(|x| BoolTarget::new_unsafe(x))
+            .collect_vec()
+            .try_into()
+            .unwrap()
+    }
+
+    fn get_final_circuit_withdrawal_credentials_commitment(&self) -> HashOutTarget {
+        HashOutTarget {
+            elements: self.public_inputs[FINAL_WITHDRAWAL_CREDENTIALS_COMMITMENT_PUB_INDEX
+                ..FINAL_WITHDRAWAL_CREDENTIALS_COMMITMENT_PUB_INDEX + POSEIDON_HASH_SIZE]
+                .try_into()
+                .unwrap(),
+        }
+    }
+
+    fn get_final_circuit_balance_sum(&self) -> BigUintTarget {
+        self.biguint_at(FINAL_BALANCE_SUM_PUB_INDEX)
     }
 }
 
@@ -91,6 +435,19 @@ const D: usize = 2;
 type C = PoseidonGoldilocksConfig;
 type F = <C as GenericConfig<D>>::F;
 
+/// How the final circuit is built for the generated balance verification and
+/// commitment mapper circuits.
+#[derive(Clone, Copy, Debug)]
+pub struct FinalCircuitConfig {
+    // The depth of both validator trees
+    pub tree_depth: usize,
+    pub commitment_mapper_levels: usize,
+    pub commitment_mapper_cyclic: bool,
+    pub fork: Fork,
+    pub anchor_to_block_root: bool,
+    pub commit_public_inputs: bool,
+}
+
 pub fn build_final_circuit(
     balance_data: &CircuitData<
         plonky2::field::goldilocks_field::GoldilocksField,
@@ -102,6 +459,8 @@ pub fn build_final_circuit(
         PoseidonGoldilocksConfig,
         2,
     >,
+    preset: &NetworkPreset,
+    config: &FinalCircuitConfig,
 ) -> (
     FinalCircuitTargets,
     plonky2::plonk::circuit_data::CircuitData<
@@ -110,6 +469,15 @@ pub fn build_final_circuit(
         2,
     >,
 ) {
+    let FinalCircuitConfig {
+        tree_depth,
+        commitment_mapper_levels,
+        commitment_mapper_cyclic,
+        fork,
+        anchor_to_block_root,
+        commit_public_inputs,
+    } = *config;
+
     let final_config = CircuitConfig::standard_recursion_config();
 
     let mut builder = CircuitBuilder::<F, D>::new(final_config);
@@ -119,9 +487,12 @@ pub fn build_final_circuit(
         balance_verifier_circuit_target,
         balance_root_hash,
         balance_sum,
-        withdrawal_credentials,
+        withdrawal_credentials_commitment,
         current_epoch,
         balances_validator_poseidon_root,
+        slashed_balance_sum,
+        counted_validators,
+        effective_balance_sum,
     ) = setup_balance_targets(&mut builder, balance_data);
 
     let (
@@ -131,11 +502,40 @@ pub fn build_final_circuit(
         commitment_mapper_sha256_root,
     ) = setup_commitment_mapper_targets(&mut builder, commitment_data);
 
+    if commitment_mapper_cyclic {
+        verify_commitment_mapper_cyclic_proof_public_inputs(
+            &mut builder,
+            &commitment_mapper_proof_targets,
+            &commitment_mapper_verifier_circuit_target,
+            &commitment_data.common,
+            commitment_mapper_levels,
+        );
+    }
+
     builder.connect_hashes(
         commitment_mapper_poseidon_root,
         balances_validator_poseidon_root,
     );
 
+    // The trees of the proofs may be shallower than the registry, in which case
+    // every validator past them is zero
+    let registry_depth = preset.validator_registry_depth();
+
+    let commitment_mapper_sha256_root = extend_merkle_root(
+        &mut builder,
+        &commitment_mapper_sha256_root,
+        tree_depth,
+        registry_depth,
+    );
+
+    // A balances chunk holds the balances of 4 validators
+    let balance_root_hash = extend_merkle_root(
+        &mut builder,
+        &balance_root_hash,
+        tree_depth - 2,
+        registry_depth - 2,
+    );
+
     let state_root = create_bool_target_array(&mut builder);
 
     let validator_size_bits = create_bool_target_array(&mut builder);
@@ -153,9 +553,6 @@ pub fn build_final_circuit(
         );
     }
 
-    let validators_merkle_branch =
-        create_and_connect_merkle_branch(&mut builder, 43, &validators_hasher.digest, &state_root);
-
     let balances_hasher = make_circuits(&mut builder, (2 * ETH_SHA256_BIT_SIZE) as u64);
 
     for i in 0..ETH_SHA256_BIT_SIZE {
@@ -169,29 +566,70 @@ pub fn build_final_circuit(
         );
     }
 
-    let balance_merkle_branch =
-        create_and_connect_merkle_branch(&mut builder, 44, &balances_hasher.digest, &state_root);
+    let slot = builder.add_virtual_biguint_target_safe(2);
 
-    let slot = builder.add_virtual_biguint_target(2);
-
-    verify_slot_is_in_range(&mut builder, &slot, &current_epoch);
+    verify_slot_is_in_range(&mut builder, &slot, &current_epoch, preset.slots_per_epoch);
 
     let slot_bits = ssz_num_to_bits(&mut builder, &slot, 64);
 
-    let slot_merkle_branch =
-        create_and_connect_merkle_branch(&mut builder, 34, &slot_bits, &state_root);
+    let state_root_multiproof = create_and_connect_merkle_multiproof(
+        &mut builder,
+        &[
+            fork.validators_gindex(),
+            fork.balances_gindex(),
+            fork.slot_gindex(),
+        ],
+        &[
+            &validators_hasher.digest,
+            &balances_hasher.digest,
+            &slot_bits,
+        ],
+        &state_root,
+    );
+
+    let block_header = if anchor_to_block_root {
+        let block_header_hash_tree_root = hash_tree_root_beacon_block_header(&mut builder);
+        let block_header = block_header_hash_tree_root.header;
 
-    builder.register_public_inputs(&state_root.iter().map(|x| x.target).collect::<Vec<Target>>());
+        for i in 0..ETH_SHA256_BIT_SIZE {
+            builder.connect(block_header.state_root[i].target, state_root[i].target);
+        }
 
-    builder.register_public_inputs(
-        &withdrawal_credentials
-            .limbs
-            .iter()
-            .map(|x| x.0)
-            .collect_vec(),
-    );
+        builder.connect_biguint(&block_header.slot, &slot);
+
+        Some((block_header, block_header_hash_tree_root.hash_tree_root))
+    } else {
+        None
+    };
 
-    builder.register_public_inputs(&balance_sum.limbs.iter().map(|x| x.0).collect_vec());
+    let public_inputs = FinalCircuitPublicInputsTargets {
+        state_root,
+        withdrawal_credentials_commitment,
+        balance_sum: balance_sum.clone(),
+        slashed_balance_sum,
+        counted_validators,
+        effective_balance_sum,
+        lifecycle_sums: [
+            balance_proof_targets.get_range_pending_total_value(),
+            balance_proof_targets.get_range_active_total_value(),
+            balance_proof_targets.get_range_exited_total_value(),
+            balance_proof_targets.get_range_withdrawable_total_value(),
+        ],
+        block_root: block_header.as_ref().map(|(_, block_root)| *block_root),
+        block_slot: block_header
+            .as_ref()
+            .map(|(block_header, _)| block_header.slot.clone()),
+    };
+
+    if commit_public_inputs {
+        let commitment = final_circuit_public_inputs_commitment(&mut builder, &public_inputs);
+
+        builder.register_public_inputs(&commitment.map(|x| x.0));
+    } else {
+        public_inputs.register(&mut builder);
+    }
+
+    let block_header = block_header.map(|(block_header, _)| block_header);
 
     let data = builder.build::<C>();
 
@@ -205,14 +643,12 @@ pub fn build_final_circuit(
                 proof: commitment_mapper_proof_targets,
                 verifier_circuit_target: commitment_mapper_verifier_circuit_target,
             },
-            validators_branch: validators_merkle_branch.branch.try_into().unwrap(),
             state_root,
-            balance_branch: balance_merkle_branch.branch.try_into().unwrap(),
+            state_root_multiproof: state_root_multiproof.proof,
             balance_sum,
             slot,
-            slot_branch: slot_merkle_branch.branch.try_into().unwrap(),
-            withdrawal_credentials: withdrawal_credentials.try_into().unwrap(),
             validator_size_bits,
+            block_header,
         },
         data,
     )
@@ -226,26 +662,35 @@ fn setup_balance_targets(
     VerifierCircuitTarget,
     [BoolTarget; ETH_SHA256_BIT_SIZE],
     BigUintTarget,
-    BigUintTarget,
+    HashOutTarget,
     BigUintTarget,
     HashOutTarget,
+    BigUintTarget,
+    Target,
+    BigUintTarget,
 ) {
     let (proof_targets, verifier_circuit_target) = setup_proof_targets(data, builder);
 
     let root_hash = proof_targets.get_range_balances_root();
     let sum = proof_targets.get_range_total_value();
-    let withdrawal_credentials = proof_targets.get_withdrawal_credentials();
+    let withdrawal_credentials_commitment = proof_targets.get_withdrawal_credentials_commitment();
     let current_epoch = proof_targets.get_current_epoch();
     let poseidon_hash = proof_targets.get_range_validator_commitment();
+    let slashed_sum = proof_targets.get_range_slashed_total_value();
+    let counted_validators = proof_targets.get_range_counted_validators();
+    let effective_balance_sum = proof_targets.get_range_effective_balance_total_value();
 
     (
         proof_targets,
         verifier_circuit_target,
         root_hash,
         sum,
-        withdrawal_credentials,
+        withdrawal_credentials_commitment,
         current_epoch,
         poseidon_hash,
+        slashed_sum,
+        counted_validators,
+        effective_balance_sum,
     )
 }
 
@@ -275,8 +720,9 @@ fn verify_slot_is_in_range(
     builder: &mut CircuitBuilder<GoldilocksField, 2>,
     slot: &BigUintTarget,
     current_epoch: &BigUintTarget,
+    slots_per_epoch: u64,
 ) -> () {
-    let slots_per_epoch = builder.constant_biguint(&BigUint::from_u32(32).unwrap());
+    let slots_per_epoch = builder.constant_biguint(&BigUint::from_u64(slots_per_epoch).unwrap());
 
     let slot_epoch = builder.div_biguint(slot, &slots_per_epoch);
 
@@ -291,11 +737,7 @@ fn setup_proof_targets(
     >,
     builder: &mut CircuitBuilder<GoldilocksField, 2>,
 ) -> (ProofWithPublicInputsTarget<2>, VerifierCircuitTarget) {
-    let verifier_circuit_target = VerifierCircuitTarget {
-        constants_sigmas_cap: builder
-            .add_virtual_cap(circuit_data.common.config.fri_config.cap_height),
-        circuit_digest: builder.add_virtual_hash(),
-    };
+    let verifier_circuit_target = builder.constant_verifier_data(&circuit_data.verifier_only);
 
     let proof_targets: ProofWithPublicInputsTarget<2> =
         builder.add_virtual_proof_with_pis(&circuit_data.common);
@@ -309,29 +751,28 @@ fn setup_proof_targets(
     (proof_targets, verifier_circuit_target)
 }
 
-fn create_and_connect_merkle_branch(
+fn create_and_connect_merkle_multiproof(
     builder: &mut CircuitBuilder<GoldilocksField, 2>,
-    index: u32,
-    leaf_targets: &[BoolTarget],
+    gindices: &[u64],
+    leaves_targets: &[&[BoolTarget]],
     root_targets: &[BoolTarget; ETH_SHA256_BIT_SIZE],
-) -> IsValidMerkleBranchTargets {
-    let merkle_branch = is_valid_merkle_branch(builder, 5);
-    let index = builder.constant(GoldilocksField::from_canonical_u32(index));
+) -> IsValidMerkleMultiproofTargets {
+    let merkle_multiproof = is_valid_merkle_multiproof(builder, gindices);
 
-    builder.connect(merkle_branch.index, index);
-
-    for i in 0..ETH_SHA256_BIT_SIZE {
-        builder.connect(merkle_branch.leaf[i].target, leaf_targets[i].target);
+    for (leaf, leaf_targets) in merkle_multiproof.leaves.iter().zip(leaves_targets) {
+        for i in 0..ETH_SHA256_BIT_SIZE {
+            builder.connect(leaf[i].target, leaf_targets[i].target);
+        }
     }
 
     for i in 0..ETH_SHA256_BIT_SIZE {
-        builder.connect(merkle_branch.root[i].target, root_targets[i].target);
+        builder.connect(merkle_multiproof.root[i].target, root_targets[i].target);
     }
 
-    merkle_branch
+    merkle_multiproof
 }
 
-fn create_final_config() -> CircuitConfig {
+pub fn create_final_config() -> CircuitConfig {
     let standard_recursion_config = CircuitConfig::standard_recursion_config();
 
     CircuitConfig {
@@ -377,7 +818,7 @@ mod test_verify_slot_is_in_range {
         let slot_target = builder.add_virtual_biguint_target(2);
         let current_epoch = builder.add_virtual_biguint_target(2);
 
-        verify_slot_is_in_range(&mut builder, &slot_target, &current_epoch);
+        verify_slot_is_in_range(&mut builder, &slot_target, &current_epoch, 32);
 
         pw.set_biguint_target(&slot_target, &BigUint::from_u64(6953401).unwrap());
         pw.set_biguint_target(&current_epoch, &BigUint::from_u64(217293).unwrap());
@@ -402,7 +843,7 @@ mod test_verify_slot_is_in_range {
         let slot_target = builder.add_virtual_biguint_target(2);
         let current_epoch = builder.add_virtual_biguint_target(2);
 
-        verify_slot_is_in_range(&mut builder, &slot_target, &current_epoch);
+        verify_slot_is_in_range(&mut builder, &slot_target, &current_epoch, 32);
 
         pw.set_biguint_target(&slot_target, &BigUint::from_u64(7314752).unwrap());
         pw.set_biguint_target(&current_epoch, &BigUint::from_u64(228586).unwrap());
@@ -426,7 +867,7 @@ mod test_verify_slot_is_in_range {
         let slot_target = builder.add_virtual_biguint_target(2);
         let current_epoch = builder.add_virtual_biguint_target(2);
 
-        verify_slot_is_in_range(&mut builder, &slot_target, &current_epoch);
+        verify_slot_is_in_range(&mut builder, &slot_target, &current_epoch, 32);
 
         pw.set_biguint_target(&slot_target, &BigUint::from_u64(7314751).unwrap());
         pw.set_biguint_target(&current_epoch, &BigUint::from_u64(228585).unwrap());
@@ -437,6 +878,30 @@ mod test_verify_slot_is_in_range {
         data.verify(proof)
     }
 
+    #[test]
+    fn test_verify_slot_is_in_range_gnosis() -> std::result::Result<(), anyhow::Error> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = GoldilocksField;
+
+        let mut pw = PartialWitness::new();
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+
+        let slot_target = builder.add_virtual_biguint_target(2);
+        let current_epoch = builder.add_virtual_biguint_target(2);
+
+        verify_slot_is_in_range(&mut builder, &slot_target, &current_epoch, 16);
+
+        pw.set_biguint_target(&slot_target, &BigUint::from_u64(6953401).unwrap());
+        pw.set_biguint_target(&current_epoch, &BigUint::from_u64(434587).unwrap());
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        data.verify(proof)
+    }
+
     #[test]
     #[should_panic]
     fn test_verify_slot_is_not_in_range() -> () {
@@ -451,7 +916,7 @@ mod test_verify_slot_is_in_range {
         let slot_target = builder.add_virtual_biguint_target(2);
         let current_epoch = builder.add_virtual_biguint_target(2);
 
-        verify_slot_is_in_range(&mut builder, &slot_target, &current_epoch);
+        verify_slot_is_in_range(&mut builder, &slot_target, &current_epoch, 32);
 
         pw.set_biguint_target(&slot_target, &BigUint::from_u64(7314751).unwrap());
         pw.set_biguint_target(&current_epoch, &BigUint::from_u64(228586).unwrap());
@@ -462,3 +927,207 @@ mod test_verify_slot_is_in_range {
         data.verify(proof).unwrap();
     }
 }
+
+#[cfg(test)]
+mod test_final_circuit_public_inputs_commitment {
+    use num::BigUint;
+    use plonky2::{
+        field::{goldilocks_field::GoldilocksField, types::Field},
+        hash::hash_types::HashOut,
+        iop::witness::{PartialWitness, WitnessWrite},
+        plonk::{
+            circuit_builder::CircuitBuilder, circuit_data::CircuitConfig,
+            config::PoseidonGoldilocksConfig,
+        },
+    };
+
+    use crate::{
+        biguint::{CircuitBuilderBiguint, WitnessBigUint},
+        build_final_circuit::{
+            final_circuit_public_inputs_commitment, FinalCircuitProofExt, FinalCircuitPublicInputs,
+            FinalCircuitPublicInputsTargets,
+        },
+        utils::create_bool_target_array,
+    };
+
+    fn bytes_to_bits(bytes: &[u8]) -> Vec<bool> {
+        bytes
+            .iter()
+            .flat_map(|byte| (0..8).rev().map(move |i| (byte >> i) & 1 == 1))
+            .collect()
+    }
+
+    #[test]
+    fn test_final_circuit_public_inputs_commitment() -> std::result::Result<(), anyhow::Error> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = GoldilocksField;
+
+        let values = FinalCircuitPublicInputs {
+            state_root: [0x5a; 32],
+            withdrawal_credentials_commitment: [
+                12105470243548446339,
+                1453437346146451577,
+                7395476349102815284,
+                16311846138318069203,
+            ],
+            balance_sum: 92421213812442,
+            slashed_balance_sum: 32000000000,
+            counted_validators: 2893,
+            effective_balance_sum: 92576000000000,
+            pending_balance_sum: 64000000000,
+            active_balance_sum: 92357213812442,
+            exited_balance_sum: 0,
+            withdrawable_balance_sum: 31998212123,
+            block_root: Some([0xc3; 32]),
+            block_slot: Some(6953401),
+        };
+
+        let mut pw = PartialWitness::new();
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+
+        let targets = FinalCircuitPublicInputsTargets {
+            state_root: create_bool_target_array(&mut builder),
+            withdrawal_credentials_commitment: builder.add_virtual_hash(),
+            balance_sum: builder.add_virtual_biguint_target_safe(2),
+            slashed_balance_sum: builder.add_virtual_biguint_target_safe(2),
+            counted_validators: builder.add_virtual_target(),
+            effective_balance_sum: builder.add_virtual_biguint_target_safe(2),
+            lifecycle_sums: [(); 4].map(|_| builder.add_virtual_biguint_target_safe(2)),
+            block_root: Some(create_bool_target_array(&mut builder)),
+            block_slot: Some(builder.add_virtual_biguint_target_safe(2)),
+        };
+
+        let commitment = final_circuit_public_inputs_commitment(&mut builder, &targets);
+
+        builder.register_public_inputs(&commitment.map(|x| x.0));
+
+        for (target, bit) in targets
+            .state_root
+            .iter()
+            .zip(bytes_to_bits(&values.state_root))
+        {
+            pw.set_bool_target(*target, bit);
+        }
+
+        for (target, bit) in targets
+            .block_root
+            .unwrap()
+            .iter()
+            .zip(bytes_to_bits(&values.block_root.unwrap()))
+        {
+            pw.set_bool_target(*target, bit);
+        }
+
+        pw.set_hash_target(
+            targets.withdrawal_credentials_commitment,
+            HashOut {
+                elements: values
+                    .withdrawal_credentials_commitment
+                    .map(F::from_canonical_u64),
+            },
+        );
+
+        pw.set_target(
+            targets.counted_validators,
+            F::from_canonical_u64(values.counted_validators),
+        );
+
+        for (target, value) in [
+            (&targets.balance_sum, values.balance_sum),
+            (&targets.slashed_balance_sum, values.slashed_balance_sum),
+            (&targets.effective_balance_sum, values.effective_balance_sum),
+            (&targets.lifecycle_sums[0], values.pending_balance_sum),
+            (&targets.lifecycle_sums[1], values.active_balance_sum),
+            (&targets.lifecycle_sums[2], values.exited_balance_sum),
+            (&targets.lifecycle_sums[3], values.withdrawable_balance_sum),
+            (
+                targets.block_slot.as_ref().unwrap(),
+                values.block_slot.unwrap(),
+            ),
+        ] {
+            pw.set_biguint_target(target, &BigUint::from(value));
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        assert!(proof.is_final_circuit_public_inputs_commitment_valid(&values));
+
+        data.verify(proof)
+    }
+}
+
+#[cfg(test)]
+mod test_target_to_u64_biguint {
+    use num::BigUint;
+    use plonky2::{
+        field::{goldilocks_field::GoldilocksField, types::Field},
+        iop::witness::PartialWitness,
+        plonk::{
+            circuit_builder::CircuitBuilder, circuit_data::CircuitConfig,
+            config::PoseidonGoldilocksConfig,
+        },
+    };
+
+    use crate::{
+        biguint::{CircuitBuilderBiguint, WitnessBigUint},
+        build_final_circuit::{assert_is_canonical_u64, target_to_u64_biguint},
+    };
+
+    #[test]
+    fn test_target_to_u64_biguint() -> std::result::Result<(), anyhow::Error> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = GoldilocksField;
+
+        let pw = PartialWitness::new();
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+
+        // p - 1 has a high limb of all ones
+        let target = builder.constant(F::NEG_ONE);
+        let value = target_to_u64_biguint(&mut builder, target);
+
+        let expected_value = builder.constant_biguint(&BigUint::from(0xFFFFFFFF00000000u64));
+        builder.connect_biguint(&value, &expected_value);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        data.verify(proof)
+    }
+
+    #[test]
+    #[should_panic(expected = "set twice with different values")]
+    fn test_non_canonical_u64_is_rejected() -> () {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = GoldilocksField;
+
+        let mut pw = PartialWitness::new();
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+
+        let value = builder.add_virtual_biguint_target_safe(2);
+
+        let target = builder.mul_const_add(
+            F::from_canonical_u64(1 << 32),
+            value.limbs[1].0,
+            value.limbs[0].0,
+        );
+        let zero = builder.zero();
+        builder.connect(target, zero);
+
+        assert_is_canonical_u64(&mut builder, &value);
+
+        // p encodes zero as well
+        pw.set_biguint_target(&value, &BigUint::from(0xFFFFFFFF00000001u64));
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw).unwrap();
+
+        data.verify(proof).unwrap();
+    }
+}
@@ -0,0 +1,124 @@
+use plonky2::{
+    field::goldilocks_field::GoldilocksField,
+    plonk::{
+        circuit_builder::CircuitBuilder,
+        circuit_data::{CircuitConfig, CircuitData, VerifierCircuitTarget},
+        config::{GenericConfig, PoseidonBN128GoldilocksConfig, PoseidonGoldilocksConfig},
+        proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget},
+    },
+};
+
+use crate::build_final_circuit::create_final_config;
+
+pub struct FinalWrapperCircuitTargets {
+    pub proof: ProofWithPublicInputsTarget<2>,
+    pub verifier_circuit_target: VerifierCircuitTarget,
+}
+
+pub type FinalWrapperProof =
+    ProofWithPublicInputs<GoldilocksField, PoseidonBN128GoldilocksConfig, 2>;
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+
+/// Shrinks the final proof (or a proof of a layer on top of it) by verifying it
+/// in a circuit with the `create_final_config` configuration. The public inputs
+/// are forwarded unchanged.
+pub fn build_final_compression_circuit(
+    inner_circuit_data: &CircuitData<F, C, D>,
+) -> (FinalWrapperCircuitTargets, CircuitData<F, C, D>) {
+    build_wrapper_circuit::<C>(inner_circuit_data, create_final_config())
+}
+
+/// The last wrapping step. It verifies the compressed proof and is itself proven
+/// with Poseidon over BN254 as the hasher, so its proof can be verified inside a
+/// BN254 SNARK (e.g. a Groth16 wrapper).
+pub fn build_final_wrapper_circuit(
+    compression_circuit_data: &CircuitData<F, C, D>,
+) -> (
+    FinalWrapperCircuitTargets,
+    CircuitData<F, PoseidonBN128GoldilocksConfig, D>,
+) {
+    build_wrapper_circuit::<PoseidonBN128GoldilocksConfig>(
+        compression_circuit_data,
+        create_final_config(),
+    )
+}
+
+fn build_wrapper_circuit<OuterC: GenericConfig<D, F = F>>(
+    inner_circuit_data: &CircuitData<F, C, D>,
+    config: CircuitConfig,
+) -> (FinalWrapperCircuitTargets, CircuitData<F, OuterC, D>) {
+    let mut builder = CircuitBuilder::<F, D>::new(config);
+
+    let verifier_circuit_target = builder.constant_verifier_data(&inner_circuit_data.verifier_only);
+
+    let proof: ProofWithPublicInputsTarget<2> =
+        builder.add_virtual_proof_with_pis(&inner_circuit_data.common);
+
+    builder.verify_proof::<C>(&proof, &verifier_circuit_target, &inner_circuit_data.common);
+
+    builder.register_public_inputs(&proof.public_inputs);
+
+    let data = builder.build::<OuterC>();
+
+    (
+        FinalWrapperCircuitTargets {
+            proof,
+            verifier_circuit_target,
+        },
+        data,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use plonky2::{
+        field::types::Field,
+        iop::witness::{PartialWitness, WitnessWrite},
+        plonk::{circuit_builder::CircuitBuilder, circuit_data::CircuitConfig},
+    };
+
+    use super::{build_final_compression_circuit, build_final_wrapper_circuit, C, D, F};
+
+    #[test]
+    fn test_public_inputs_are_forwarded() -> Result<()> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+
+        let public_inputs = builder.add_virtual_targets(4);
+        builder.register_public_inputs(&public_inputs);
+
+        let inner_data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+
+        for (i, target) in public_inputs.iter().enumerate() {
+            pw.set_target(*target, F::from_canonical_u64(1 << (i * 16)));
+        }
+
+        let inner_proof = inner_data.prove(pw)?;
+
+        let (compression_targets, compression_data) = build_final_compression_circuit(&inner_data);
+
+        let mut pw = PartialWitness::new();
+        pw.set_proof_with_pis_target(&compression_targets.proof, &inner_proof);
+
+        let compression_proof = compression_data.prove(pw)?;
+
+        assert_eq!(compression_proof.public_inputs, inner_proof.public_inputs);
+
+        let (wrapper_targets, wrapper_data) = build_final_wrapper_circuit(&compression_data);
+
+        let mut pw = PartialWitness::new();
+        pw.set_proof_with_pis_target(&wrapper_targets.proof, &compression_proof);
+
+        let wrapper_proof = wrapper_data.prove(pw)?;
+
+        assert_eq!(wrapper_proof.public_inputs, inner_proof.public_inputs);
+
+        compression_data.verify(compression_proof)?;
+        wrapper_data.verify(wrapper_proof)
+    }
+}
@@ -0,0 +1,448 @@
+use itertools::Itertools;
+use plonky2::{
+    field::{goldilocks_field::GoldilocksField, types::Field},
+    iop::target::{BoolTarget, Target},
+    plonk::{
+        circuit_builder::CircuitBuilder,
+        circuit_data::{CircuitConfig, CircuitData, VerifierCircuitTarget},
+        config::{GenericConfig, PoseidonGoldilocksConfig},
+        proof::ProofWithPublicInputsTarget,
+    },
+};
+
+use crate::{
+    build_final_circuit::{
+        has_uncommitted_final_public_inputs, FINAL_ANCHORED_PUBLIC_INPUTS_LEN,
+        FINAL_STATE_ROOT_PUB_INDEX,
+    },
+    fork::Fork,
+    is_valid_merkle_branch::is_valid_merkle_branch,
+    network_preset::NetworkPreset,
+    utils::{create_bool_target_array, ETH_SHA256_BIT_SIZE},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HistoricalStateRootSource {
+    // The older state is at most SLOTS_PER_HISTORICAL_ROOT slots behind
+    StateRoots,
+    // The older state is in a period that is already summarized
+    HistoricalSummaries,
+}
+
+pub struct HistoricalStateRootCircuitTargets {
+    pub proof: ProofWithPublicInputsTarget<2>,
+    pub verifier_circuit_target: VerifierCircuitTarget,
+    pub state_root: [BoolTarget; ETH_SHA256_BIT_SIZE],
+    pub branch: Vec<[BoolTarget; ETH_SHA256_BIT_SIZE]>,
+    pub state_roots_index: Target,
+    pub historical_summary_index: Option<Target>,
+}
+
+/// Proves that the state root published by the inner proof is a past state
+/// root of a newer state. The public inputs are those of the inner proof with
+/// the state root replaced by the newer one, so the layer can be chained.
+/// Proofs anchored to a block root are rejected.
+pub fn build_historical_state_root_circuit(
+    inner_circuit_data: &CircuitData<
+        plonky2::field::goldilocks_field::GoldilocksField,
+        PoseidonGoldilocksConfig,
+        2,
+    >,
+    source: HistoricalStateRootSource,
+    preset: &NetworkPreset,
+    fork: Fork,
+) -> (
+    HistoricalStateRootCircuitTargets,
+    plonky2::plonk::circuit_data::CircuitData<
+        plonky2::field::goldilocks_field::GoldilocksField,
+        PoseidonGoldilocksConfig,
+        2,
+    >,
+) {
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    assert!(
+        has_uncommitted_final_public_inputs(&inner_circuit_data.common),
+        "The inner proof must be built without `commit_public_inputs`"
+    );
+    // The block root and slot of an anchored proof would not match the newer state root
+    assert_ne!(
+        inner_circuit_data.common.num_public_inputs, FINAL_ANCHORED_PUBLIC_INPUTS_LEN,
+        "The inner proof must be built without `anchor_to_block_root`"
+    );
+
+    let standard_recursion_config = CircuitConfig::standard_recursion_config();
+
+    let mut builder = CircuitBuilder::<F, D>::new(standard_recursion_config);
+
+    let verifier_circuit_target = builder.constant_verifier_data(&inner_circuit_data.verifier_only);
+
+    let proof: ProofWithPublicInputsTarget<2> =
+        builder.add_virtual_proof_with_pis(&inner_circuit_data.common);
+
+    builder.verify_proof::<C>(&proof, &verifier_circuit_target, &inner_circuit_data.common);
+
+    let beacon_state_depth = fork.beacon_state_depth();
+    let state_roots_depth = preset.state_roots_depth();
+    let historical_summaries_depth = preset.historical_summaries_depth();
+
+    let state_roots_index = builder.add_virtual_target();
+    builder.range_check(state_roots_index, state_roots_depth);
+
+    let (depth, gindex, historical_summary_index) = match source {
+        HistoricalStateRootSource::StateRoots => {
+            let prefix = builder.constant(GoldilocksField::from_canonical_u64(
+                fork.state_roots_gindex() << state_roots_depth,
+            ));
+
+            (
+                beacon_state_depth + state_roots_depth,
+                builder.add(prefix, state_roots_index),
+                None,
+            )
+        }
+        HistoricalStateRootSource::HistoricalSummaries => {
+            let historical_summary_index = builder.add_virtual_target();
+            builder.range_check(historical_summary_index, historical_summaries_depth);
+
+            // state_roots -> state_summary_root -> list data root -> historical_summaries
+            let prefix = builder.constant(GoldilocksField::from_canonical_u64(
+                (((fork.historical_summaries_gindex() * 2) << historical_summaries_depth) * 2 + 1)
+                    << state_roots_depth,
+            ));
+
+            let gindex = builder.mul_const_add(
+                GoldilocksField::from_canonical_u64(1 << (state_roots_depth + 1)),
+                historical_summary_index,
+                prefix,
+            );
+
+            (
+                beacon_state_depth + 1 + historical_summaries_depth + 1 + state_roots_depth,
+                builder.add(gindex, state_roots_index),
+                Some(historical_summary_index),
+            )
+        }
+    };
+
+    let merkle_branch = is_valid_merkle_branch(&mut builder, depth);
+
+    builder.connect(merkle_branch.index, gindex);
+
+    for i in 0..ETH_SHA256_BIT_SIZE {
+        builder.connect(
+            merkle_branch.leaf[i].target,
+            proof.public_inputs[FINAL_STATE_ROOT_PUB_INDEX + i],
+        );
+    }
+
+    let state_root = create_bool_target_array(&mut builder);
+
+    for i in 0..ETH_SHA256_BIT_SIZE {
+        builder.connect(merkle_branch.root[i].target, state_root[i].target);
+    }
+
+    builder.register_public_inputs(&state_root.iter().map(|x| x.target).collect_vec());
+
+    // The public inputs keep the layout of the final circuit
+    builder.register_public_inputs(
+        &proof.public_inputs[FINAL_STATE_ROOT_PUB_INDEX + ETH_SHA256_BIT_SIZE..],
+    );
+
+    let data = builder.build::<C>();
+
+    (
+        HistoricalStateRootCircuitTargets {
+            proof,
+            verifier_circuit_target,
+            state_root,
+            branch: merkle_branch.branch,
+            state_roots_index,
+            historical_summary_index,
+        },
+        data,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use plonky2::{
+        field::{goldilocks_field::GoldilocksField, types::Field},
+        iop::{
+            target::Target,
+            witness::{PartialWitness, WitnessWrite},
+        },
+        plonk::{
+            circuit_builder::CircuitBuilder,
+            circuit_data::{CircuitConfig, CircuitData},
+            config::PoseidonGoldilocksConfig,
+            proof::ProofWithPublicInputs,
+        },
+    };
+    use sha2::{Digest, Sha256};
+
+    use crate::{
+        build_final_circuit::{
+            FINAL_ANCHORED_PUBLIC_INPUTS_LEN, FINAL_PUBLIC_INPUTS_COMMITMENT_LEN,
+            FINAL_PUBLIC_INPUTS_LEN, FINAL_STATE_ROOT_PUB_INDEX,
+        },
+        fork::Fork,
+        network_preset::NetworkPreset,
+        sha256::array_to_bits,
+        utils::ETH_SHA256_BIT_SIZE,
+    };
+
+    use super::{build_historical_state_root_circuit, HistoricalStateRootSource};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = GoldilocksField;
+
+    type InnerCircuit = (Vec<Target>, CircuitData<F, C, D>);
+
+    const STATE_ROOTS_INDEX: usize = 2;
+    const HISTORICAL_SUMMARY_INDEX: usize = 1;
+
+    fn preset() -> NetworkPreset {
+        NetworkPreset {
+            slots_per_historical_root: 4,
+            historical_roots_limit: 4,
+            ..NetworkPreset::minimal()
+        }
+    }
+
+    fn sha256(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(left);
+        hasher.update(right);
+        hasher.finalize().as_slice().try_into().unwrap()
+    }
+
+    fn chunks(len: usize, seed: u8) -> Vec<[u8; 32]> {
+        (0..len).map(|i| [seed.wrapping_add(i as u8); 32]).collect()
+    }
+
+    /// Merkleizes a power of two number of chunks.
+    fn merkleize(chunks: &[[u8; 32]]) -> [u8; 32] {
+        if chunks.len() == 1 {
+            return chunks[0];
+        }
+
+        let (left, right) = chunks.split_at(chunks.len() / 2);
+
+        sha256(&merkleize(left), &merkleize(right))
+    }
+
+    /// The siblings on the path from the chunk at `index` to the root, from the
+    /// bottom up.
+    fn merkle_branch(chunks: &[[u8; 32]], index: usize) -> Vec<[u8; 32]> {
+        if chunks.len() == 1 {
+            return vec![];
+        }
+
+        let half = chunks.len() / 2;
+        let (left, right) = chunks.split_at(half);
+
+        let (mut branch, sibling) = if index < half {
+            (merkle_branch(left, index), merkleize(right))
+        } else {
+            (merkle_branch(right, index - half), merkleize(left))
+        };
+
+        branch.push(sibling);
+        branch
+    }
+
+    /// Returns the state root at `STATE_ROOTS_INDEX`, the newer state root and
+    /// the branch between them.
+    fn state_tree(
+        source: HistoricalStateRootSource,
+        fork: Fork,
+    ) -> ([u8; 32], [u8; 32], Vec<[u8; 32]>) {
+        let preset = preset();
+
+        let state_roots = chunks(preset.slots_per_historical_root as usize, 10);
+        let mut branch = merkle_branch(&state_roots, STATE_ROOTS_INDEX);
+
+        let mut fields = chunks(1 << fork.beacon_state_depth(), 100);
+
+        let (field_index, field_root) = match source {
+            HistoricalStateRootSource::StateRoots => (
+                fork.state_roots_gindex() as usize - fields.len(),
+                merkleize(&state_roots),
+            ),
+            HistoricalStateRootSource::HistoricalSummaries => {
+                let block_summary_root = [1u8; 32];
+                branch.push(block_summary_root);
+
+                let mut summaries = chunks(preset.historical_roots_limit as usize, 50);
+                summaries[HISTORICAL_SUMMARY_INDEX] =
+                    sha256(&block_summary_root, &merkleize(&state_roots));
+                branch.extend(merkle_branch(&summaries, HISTORICAL_SUMMARY_INDEX));
+
+                let mut length = [0u8; 32];
+                length[0] = summaries.len() as u8;
+                branch.push(length);
+
+                (
+                    fork.historical_summaries_gindex() as usize - fields.len(),
+                    sha256(&merkleize(&summaries), &length),
+                )
+            }
+        };
+
+        fields[field_index] = field_root;
+        branch.extend(merkle_branch(&fields, field_index));
+
+        (state_roots[STATE_ROOTS_INDEX], merkleize(&fields), branch)
+    }
+
+    /// A circuit with `len` public inputs, all of them taken from the witness.
+    fn build_inner_circuit(len: usize) -> InnerCircuit {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+
+        let public_inputs = builder.add_virtual_targets(len);
+        builder.register_public_inputs(&public_inputs);
+
+        (public_inputs, builder.build::<C>())
+    }
+
+    fn prove_inner(
+        (public_inputs, data): &InnerCircuit,
+        state_root: &[u8; 32],
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        let mut values = (0..public_inputs.len() as u64).collect::<Vec<_>>();
+
+        for (i, bit) in array_to_bits(state_root).into_iter().enumerate() {
+            values[FINAL_STATE_ROOT_PUB_INDEX + i] = bit as u64;
+        }
+
+        let mut pw = PartialWitness::new();
+
+        for (target, value) in public_inputs.iter().zip(values) {
+            pw.set_target(*target, F::from_canonical_u64(value));
+        }
+
+        data.prove(pw)
+    }
+
+    fn prove_historical_state_root(
+        source: HistoricalStateRootSource,
+        state_roots_index: usize,
+    ) -> Result<()> {
+        let fork = Fork::Deneb;
+
+        let (old_state_root, state_root, branch) = state_tree(source, fork);
+
+        let inner_circuit = build_inner_circuit(FINAL_PUBLIC_INPUTS_LEN);
+        let inner_proof = prove_inner(&inner_circuit, &old_state_root)?;
+
+        let (targets, data) =
+            build_historical_state_root_circuit(&inner_circuit.1, source, &preset(), fork);
+
+        let mut pw = PartialWitness::new();
+
+        pw.set_proof_with_pis_target(&targets.proof, &inner_proof);
+
+        for (target, bit) in targets.state_root.iter().zip(array_to_bits(&state_root)) {
+            pw.set_bool_target(*target, bit);
+        }
+
+        assert_eq!(targets.branch.len(), branch.len());
+
+        for (branch_targets, sibling) in targets.branch.iter().zip(&branch) {
+            for (target, bit) in branch_targets.iter().zip(array_to_bits(sibling)) {
+                pw.set_bool_target(*target, bit);
+            }
+        }
+
+        pw.set_target(
+            targets.state_roots_index,
+            F::from_canonical_usize(state_roots_index),
+        );
+
+        if let Some(historical_summary_index) = targets.historical_summary_index {
+            pw.set_target(
+                historical_summary_index,
+                F::from_canonical_usize(HISTORICAL_SUMMARY_INDEX),
+            );
+        }
+
+        let proof = data.prove(pw)?;
+
+        let expected_state_root = array_to_bits(&state_root)
+            .into_iter()
+            .map(|bit| F::from_bool(bit))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            proof.public_inputs[..ETH_SHA256_BIT_SIZE],
+            expected_state_root
+        );
+        assert_eq!(
+            proof.public_inputs[ETH_SHA256_BIT_SIZE..],
+            inner_proof.public_inputs[FINAL_STATE_ROOT_PUB_INDEX + ETH_SHA256_BIT_SIZE..]
+        );
+
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_state_root_from_state_roots() -> Result<()> {
+        prove_historical_state_root(HistoricalStateRootSource::StateRoots, STATE_ROOTS_INDEX)
+    }
+
+    #[test]
+    fn test_state_root_from_historical_summaries() -> Result<()> {
+        prove_historical_state_root(
+            HistoricalStateRootSource::HistoricalSummaries,
+            STATE_ROOTS_INDEX,
+        )
+    }
+
+    #[test]
+    #[should_panic(expected = "set twice with different values")]
+    fn test_wrong_state_roots_index_is_rejected() {
+        prove_historical_state_root(HistoricalStateRootSource::StateRoots, STATE_ROOTS_INDEX + 1)
+            .unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "set twice with different values")]
+    fn test_wrong_state_roots_index_in_summary_is_rejected() {
+        prove_historical_state_root(
+            HistoricalStateRootSource::HistoricalSummaries,
+            STATE_ROOTS_INDEX + 1,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "The inner proof must be built without `commit_public_inputs`")]
+    fn test_committed_inner_proof_is_rejected() {
+        let (_, inner_circuit_data) = build_inner_circuit(FINAL_PUBLIC_INPUTS_COMMITMENT_LEN);
+
+        build_historical_state_root_circuit(
+            &inner_circuit_data,
+            HistoricalStateRootSource::StateRoots,
+            &preset(),
+            Fork::Deneb,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "The inner proof must be built without `anchor_to_block_root`")]
+    fn test_anchored_inner_proof_is_rejected() {
+        let (_, inner_circuit_data) = build_inner_circuit(FINAL_ANCHORED_PUBLIC_INPUTS_LEN);
+
+        build_historical_state_root_circuit(
+            &inner_circuit_data,
+            HistoricalStateRootSource::StateRoots,
+            &preset(),
+            Fork::Deneb,
+        );
+    }
+}
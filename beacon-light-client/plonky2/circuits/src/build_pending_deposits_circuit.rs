@@ -0,0 +1,166 @@
+use itertools::Itertools;
+use num::BigUint;
+use plonky2::{
+    field::{goldilocks_field::GoldilocksField, types::Field64},
+    hash::hash_types::HashOutTarget,
+    iop::target::{BoolTarget, Target},
+    plonk::{
+        circuit_builder::CircuitBuilder,
+        circuit_data::{CircuitConfig, CircuitData},
+        config::{GenericConfig, PoseidonGoldilocksConfig},
+        proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget},
+    },
+};
+
+use crate::{
+    biguint::BigUintTarget,
+    pending_deposits_circuit::{pending_deposits_verification, PendingDepositsVerificationTargets},
+    utils::{ProofBiguintExt, ProofTargetBiguintExt, ETH_SHA256_BIT_SIZE, POSEIDON_HASH_SIZE},
+    withdrawal_credentials::WithdrawalCredentialsMatching,
+};
+
+pub const PENDING_DEPOSITS_TOTAL_VALUE_PUB_INDEX: usize = 0;
+pub const PENDING_DEPOSITS_ROOT_PUB_INDEX: usize = 2;
+pub const PENDING_DEPOSITS_WITHDRAWAL_CREDENTIALS_COMMITMENT_PUB_INDEX: usize = 258;
+pub const PENDING_DEPOSITS_COUNT_PUB_INDEX: usize = 262;
+
+/// The number of pending deposits in a first level proof.
+pub const PENDING_DEPOSITS_PER_CIRCUIT: usize = 8;
+
+type PendingDepositsProof = ProofWithPublicInputs<GoldilocksField, PoseidonGoldilocksConfig, 2>;
+
+pub trait PendingDepositsProofExt {
+    fn get_pending_deposits_total_value(&self) -> BigUint;
+
+    fn get_pending_deposits_root(&self) -> [u64; ETH_SHA256_BIT_SIZE];
+
+    fn get_pending_deposits_withdrawal_credentials_commitment(&self) -> [u64; POSEIDON_HASH_SIZE];
+
+    fn get_pending_deposits_count(&self) -> u64;
+}
+
+impl PendingDepositsProofExt for PendingDepositsProof {
+    fn get_pending_deposits_total_value(&self) -> BigUint {
+        self.biguint_at(PENDING_DEPOSITS_TOTAL_VALUE_PUB_INDEX)
+    }
+
+    fn get_pending_deposits_root(&self) -> [u64; ETH_SHA256_BIT_SIZE] {
+        self.public_inputs
+            [PENDING_DEPOSITS_ROOT_PUB_INDEX..PENDING_DEPOSITS_ROOT_PUB_INDEX + ETH_SHA256_BIT_SIZE]
+            .iter()
+            .map(|x| (x.0 % GoldilocksField::ORDER) as u64)
+            .collect_vec()
+            .try_into()
+            .unwrap()
+    }
+
+    fn get_pending_deposits_withdrawal_credentials_commitment(&self) -> [u64; POSEIDON_HASH_SIZE] {
+        self.public_inputs[PENDING_DEPOSITS_WITHDRAWAL_CREDENTIALS_COMMITMENT_PUB_INDEX
+            ..PENDING_DEPOSITS_WITHDRAWAL_CREDENTIALS_COMMITMENT_PUB_INDEX + POSEIDON_HASH_SIZE]
+            .iter()
+            .map(|x| (x.0 % GoldilocksField::ORDER) as u64)
+            .collect_vec()
+            .try_into()
+            .unwrap()
+    }
+
+    fn get_pending_deposits_count(&self) -> u64 {
+        self.public_inputs[PENDING_DEPOSITS_COUNT_PUB_INDEX].0 % GoldilocksField::ORDER
+    }
+}
+
+type PendingDepositsProofTargets = ProofWithPublicInputsTarget<2>;
+
+pub trait PendingDepositsProofTargetsExt {
+    fn get_pending_deposits_total_value(&self) -> BigUintTarget;
+
+    fn get_pending_deposits_root(&self) -> [BoolTarget; ETH_SHA256_BIT_SIZE];
+
+    fn get_pending_deposits_withdrawal_credentials_commitment(&self) -> HashOutTarget;
+
+    fn get_pending_deposits_count(&self) -> Target;
+}
+
+impl PendingDepositsProofTargetsExt for PendingDepositsProofTargets {
+    fn get_pending_deposits_total_value(&self) -> BigUintTarget {
+        self.biguint_at(PENDING_DEPOSITS_TOTAL_VALUE_PUB_INDEX)
+    }
+
+    fn get_pending_deposits_root(&self) -> [BoolTarget; ETH_SHA256_BIT_SIZE] {
+        self.public_inputs
+            [PENDING_DEPOSITS_ROOT_PUB_INDEX..PENDING_DEPOSITS_ROOT_PUB_INDEX + ETH_SHA256_BIT_SIZE]
+            .iter()
+            .cloned()
+            .map(|x| BoolTarget::new_unsafe(x))
+            .collect_vec()
+            .try_into()
+            .unwrap()
+    }
+
+    fn get_pending_deposits_withdrawal_credentials_commitment(&self) -> HashOutTarget {
+        HashOutTarget {
+            elements: self.public_inputs
+                [PENDING_DEPOSITS_WITHDRAWAL_CREDENTIALS_COMMITMENT_PUB_INDEX
+                    ..PENDING_DEPOSITS_WITHDRAWAL_CREDENTIALS_COMMITMENT_PUB_INDEX
+                        + POSEIDON_HASH_SIZE]
+                .try_into()
+                .unwrap(),
+        }
+    }
+
+    fn get_pending_deposits_count(&self) -> Target {
+        self.public_inputs[PENDING_DEPOSITS_COUNT_PUB_INDEX]
+    }
+}
+
+pub fn build_pending_deposits_circuit(
+    deposits_len: usize,
+    withdrawal_credentials_len: usize,
+    matching: &WithdrawalCredentialsMatching,
+) -> (
+    PendingDepositsVerificationTargets,
+    CircuitData<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+) {
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    let standard_recursion_config = CircuitConfig::standard_recursion_config();
+
+    let mut builder = CircuitBuilder::<F, D>::new(standard_recursion_config);
+
+    let pending_deposits_verification_targets = pending_deposits_verification(
+        &mut builder,
+        deposits_len,
+        withdrawal_credentials_len,
+        matching,
+    );
+
+    set_public_variables(
+        &mut builder,
+        &pending_deposits_verification_targets.range_total_value,
+        pending_deposits_verification_targets.range_deposits_root,
+        pending_deposits_verification_targets.withdrawal_credentials_commitment,
+        pending_deposits_verification_targets.range_deposits_count,
+    );
+
+    let data = builder.build::<C>();
+
+    (pending_deposits_verification_targets, data)
+}
+
+pub fn set_public_variables(
+    builder: &mut CircuitBuilder<GoldilocksField, 2>,
+    range_total_value: &BigUintTarget,
+    range_deposits_root: [BoolTarget; ETH_SHA256_BIT_SIZE],
+    withdrawal_credentials_commitment: HashOutTarget,
+    range_deposits_count: Target,
+) {
+    builder.register_public_inputs(&range_total_value.limbs.iter().map(|x| x.0).collect_vec());
+
+    builder.register_public_inputs(&range_deposits_root.map(|x| x.target));
+
+    builder.register_public_inputs(&withdrawal_credentials_commitment.elements);
+
+    builder.register_public_input(range_deposits_count);
+}
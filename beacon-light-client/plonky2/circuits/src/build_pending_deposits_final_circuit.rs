@@ -0,0 +1,352 @@
+use itertools::Itertools;
+use num::BigUint;
+use plonky2::{
+    field::{goldilocks_field::GoldilocksField, types::Field},
+    iop::target::BoolTarget,
+    plonk::{
+        circuit_builder::CircuitBuilder,
+        circuit_data::{CircuitConfig, CircuitData, VerifierCircuitTarget},
+        config::{GenericConfig, PoseidonGoldilocksConfig},
+        proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget},
+    },
+};
+use plonky2_u32::gadgets::arithmetic_u32::U32Target;
+
+use crate::{
+    biguint::{BigUintTarget, CircuitBuilderBiguint},
+    build_final_circuit::{has_uncommitted_final_public_inputs, FinalCircuitProofTargetExt},
+    build_pending_deposits_circuit::PendingDepositsProofTargetsExt,
+    fork::Fork,
+    is_valid_merkle_branch::is_valid_merkle_branch,
+    ssz::mix_in_length,
+    utils::{ProofBiguintExt, ETH_SHA256_BIT_SIZE},
+};
+
+pub struct PendingDepositsFinalCircuitTargets {
+    pub final_proof: ProofWithPublicInputsTarget<2>,
+    pub final_verifier_circuit_target: VerifierCircuitTarget,
+    pub pending_deposits_proof: ProofWithPublicInputsTarget<2>,
+    pub pending_deposits_verifier_circuit_target: VerifierCircuitTarget,
+    pub pending_deposits_branch: Vec<[BoolTarget; ETH_SHA256_BIT_SIZE]>,
+}
+
+pub type PendingDepositsFinalCircuitProof =
+    ProofWithPublicInputs<GoldilocksField, PoseidonGoldilocksConfig, 2>;
+
+// The pending deposits sums are appended after the final circuit public inputs
+pub trait PendingDepositsFinalCircuitProofExt {
+    fn get_pending_deposits_final_circuit_pending_deposits_sum(&self) -> BigUint;
+
+    fn get_pending_deposits_final_circuit_total_sum(&self) -> BigUint;
+}
+
+impl PendingDepositsFinalCircuitProofExt for PendingDepositsFinalCircuitProof {
+    fn get_pending_deposits_final_circuit_pending_deposits_sum(&self) -> BigUint {
+        self.biguint_at(self.public_inputs.len() - 4)
+    }
+
+    fn get_pending_deposits_final_circuit_total_sum(&self) -> BigUint {
+        self.biguint_at(self.public_inputs.len() - 2)
+    }
+}
+
+/// Adds the pending deposits to the balance proven by the final circuit. The
+/// pending deposits list is proven against the state root of the final proof
+/// and only deposits to the committed withdrawal credentials are summed up.
+pub fn build_pending_deposits_final_circuit(
+    final_circuit_data: &CircuitData<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+    pending_deposits_data: &CircuitData<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+    fork: Fork,
+) -> (
+    PendingDepositsFinalCircuitTargets,
+    CircuitData<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+) {
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    let pending_deposits_gindex = fork
+        .pending_deposits_gindex()
+        .expect("The fork has no pending deposits in the BeaconState");
+
+    assert!(
+        has_uncommitted_final_public_inputs(&final_circuit_data.common),
+        "The final proof must be built without `commit_public_inputs`"
+    );
+
+    let standard_recursion_config = CircuitConfig::standard_recursion_config();
+
+    let mut builder = CircuitBuilder::<F, D>::new(standard_recursion_config);
+
+    let final_verifier_circuit_target =
+        builder.constant_verifier_data(&final_circuit_data.verifier_only);
+
+    let final_proof: ProofWithPublicInputsTarget<2> =
+        builder.add_virtual_proof_with_pis(&final_circuit_data.common);
+
+    builder.verify_proof::<C>(
+        &final_proof,
+        &final_verifier_circuit_target,
+        &final_circuit_data.common,
+    );
+
+    let pending_deposits_verifier_circuit_target =
+        builder.constant_verifier_data(&pending_deposits_data.verifier_only);
+
+    let pending_deposits_proof: ProofWithPublicInputsTarget<2> =
+        builder.add_virtual_proof_with_pis(&pending_deposits_data.common);
+
+    builder.verify_proof::<C>(
+        &pending_deposits_proof,
+        &pending_deposits_verifier_circuit_target,
+        &pending_deposits_data.common,
+    );
+
+    builder.connect_hashes(
+        final_proof.get_final_circuit_withdrawal_credentials_commitment(),
+        pending_deposits_proof.get_pending_deposits_withdrawal_credentials_commitment(),
+    );
+
+    // The count is a sum of bits over at most PENDING_DEPOSITS_LIMIT deposits
+    let pending_deposits_count = BigUintTarget {
+        limbs: vec![
+            U32Target(pending_deposits_proof.get_pending_deposits_count()),
+            U32Target(builder.zero()),
+        ],
+    };
+
+    let pending_deposits_root = mix_in_length(
+        &mut builder,
+        &pending_deposits_proof.get_pending_deposits_root(),
+        &pending_deposits_count,
+    );
+
+    let merkle_branch = is_valid_merkle_branch(&mut builder, fork.beacon_state_depth());
+
+    let index = builder.constant(GoldilocksField::from_canonical_u64(pending_deposits_gindex));
+    builder.connect(merkle_branch.index, index);
+
+    let state_root = final_proof.get_final_circuit_state_root();
+
+    for i in 0..ETH_SHA256_BIT_SIZE {
+        builder.connect(
+            merkle_branch.leaf[i].target,
+            pending_deposits_root[i].target,
+        );
+        builder.connect(merkle_branch.root[i].target, state_root[i].target);
+    }
+
+    let balance_sum = final_proof.get_final_circuit_balance_sum();
+
+    let pending_deposits_sum = pending_deposits_proof.get_pending_deposits_total_value();
+
+    let total_sum = builder.add_biguint_checked(&balance_sum, &pending_deposits_sum);
+
+    builder.register_public_inputs(&final_proof.public_inputs);
+
+    builder.register_public_inputs(&pending_deposits_sum.limbs.iter().map(|x| x.0).collect_vec());
+
+    builder.register_public_inputs(&total_sum.limbs.iter().map(|x| x.0).collect_vec());
+
+    let data = builder.build::<C>();
+
+    (
+        PendingDepositsFinalCircuitTargets {
+            final_proof,
+            final_verifier_circuit_target,
+            pending_deposits_proof,
+            pending_deposits_verifier_circuit_target,
+            pending_deposits_branch: merkle_branch.branch,
+        },
+        data,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use num::BigUint;
+    use plonky2::{
+        field::{goldilocks_field::GoldilocksField, types::Field},
+        iop::{
+            target::Target,
+            witness::{PartialWitness, WitnessWrite},
+        },
+        plonk::{
+            circuit_builder::CircuitBuilder,
+            circuit_data::{CircuitConfig, CircuitData},
+            config::PoseidonGoldilocksConfig,
+            proof::ProofWithPublicInputs,
+        },
+    };
+    use sha2::{Digest, Sha256};
+
+    use crate::{
+        build_final_circuit::{
+            FINAL_BALANCE_SUM_PUB_INDEX, FINAL_PUBLIC_INPUTS_COMMITMENT_LEN,
+            FINAL_PUBLIC_INPUTS_LEN, FINAL_STATE_ROOT_PUB_INDEX,
+            FINAL_WITHDRAWAL_CREDENTIALS_COMMITMENT_PUB_INDEX,
+        },
+        build_pending_deposits_circuit::{
+            PENDING_DEPOSITS_COUNT_PUB_INDEX, PENDING_DEPOSITS_ROOT_PUB_INDEX,
+            PENDING_DEPOSITS_TOTAL_VALUE_PUB_INDEX,
+            PENDING_DEPOSITS_WITHDRAWAL_CREDENTIALS_COMMITMENT_PUB_INDEX,
+        },
+        fork::Fork,
+        sha256::array_to_bits,
+        utils::POSEIDON_HASH_SIZE,
+    };
+
+    use super::{build_pending_deposits_final_circuit, PendingDepositsFinalCircuitProofExt};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = GoldilocksField;
+
+    type MockCircuit = (Vec<Target>, CircuitData<F, C, D>);
+
+    /// A circuit with `len` public inputs, all of them taken from the witness.
+    fn build_mock_circuit(len: usize) -> MockCircuit {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+
+        let public_inputs = builder.add_virtual_targets(len);
+        builder.register_public_inputs(&public_inputs);
+
+        (public_inputs, builder.build::<C>())
+    }
+
+    fn prove_mock(
+        (public_inputs, data): &MockCircuit,
+        values: &[u64],
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        let mut pw = PartialWitness::new();
+
+        for (target, value) in public_inputs.iter().zip(values) {
+            pw.set_target(*target, F::from_canonical_u64(*value));
+        }
+
+        data.prove(pw)
+    }
+
+    fn sha256(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(left);
+        hasher.update(right);
+        hasher.finalize().as_slice().try_into().unwrap()
+    }
+
+    fn set_bits(values: &mut [u64], index: usize, bytes: &[u8; 32]) {
+        for (i, bit) in array_to_bits(bytes).into_iter().enumerate() {
+            values[index + i] = bit as u64;
+        }
+    }
+
+    fn set_u64(values: &mut [u64], index: usize, value: u64) {
+        values[index] = value & 0xFFFFFFFF;
+        values[index + 1] = value >> 32;
+    }
+
+    #[test]
+    fn test_pending_deposits_are_added_to_the_balance_sum() -> Result<()> {
+        let fork = Fork::Electra;
+
+        let balance_sum = 0xFFFFFFFFu64;
+        let pending_deposits_sum = 37000000000u64;
+        let pending_deposits_count = 11u64;
+        let pending_deposits_root = [5u8; 32];
+
+        let mut length_chunk = [0u8; 32];
+        length_chunk[..8].copy_from_slice(&pending_deposits_count.to_le_bytes());
+
+        let branch = (0..fork.beacon_state_depth())
+            .map(|i| [i as u8 + 1; 32])
+            .collect::<Vec<_>>();
+        let gindex = fork.pending_deposits_gindex().unwrap();
+
+        let mut state_root = sha256(&pending_deposits_root, &length_chunk);
+
+        for (i, sibling) in branch.iter().enumerate() {
+            state_root = if (gindex >> i) & 1 == 1 {
+                sha256(sibling, &state_root)
+            } else {
+                sha256(&state_root, sibling)
+            };
+        }
+
+        let final_circuit = build_mock_circuit(FINAL_PUBLIC_INPUTS_LEN);
+        let mut final_values = vec![0; FINAL_PUBLIC_INPUTS_LEN];
+        set_bits(&mut final_values, FINAL_STATE_ROOT_PUB_INDEX, &state_root);
+        set_u64(&mut final_values, FINAL_BALANCE_SUM_PUB_INDEX, balance_sum);
+
+        let pending_deposits_circuit = build_mock_circuit(PENDING_DEPOSITS_COUNT_PUB_INDEX + 1);
+        let mut pending_deposits_values = vec![0; PENDING_DEPOSITS_COUNT_PUB_INDEX + 1];
+        set_bits(
+            &mut pending_deposits_values,
+            PENDING_DEPOSITS_ROOT_PUB_INDEX,
+            &pending_deposits_root,
+        );
+        set_u64(
+            &mut pending_deposits_values,
+            PENDING_DEPOSITS_TOTAL_VALUE_PUB_INDEX,
+            pending_deposits_sum,
+        );
+        pending_deposits_values[PENDING_DEPOSITS_COUNT_PUB_INDEX] = pending_deposits_count;
+
+        for i in 0..POSEIDON_HASH_SIZE {
+            final_values[FINAL_WITHDRAWAL_CREDENTIALS_COMMITMENT_PUB_INDEX + i] = 100 + i as u64;
+            pending_deposits_values
+                [PENDING_DEPOSITS_WITHDRAWAL_CREDENTIALS_COMMITMENT_PUB_INDEX + i] = 100 + i as u64;
+        }
+
+        let final_proof = prove_mock(&final_circuit, &final_values)?;
+        let pending_deposits_proof =
+            prove_mock(&pending_deposits_circuit, &pending_deposits_values)?;
+
+        let (targets, data) = build_pending_deposits_final_circuit(
+            &final_circuit.1,
+            &pending_deposits_circuit.1,
+            fork,
+        );
+
+        let mut pw = PartialWitness::new();
+
+        pw.set_proof_with_pis_target(&targets.final_proof, &final_proof);
+        pw.set_proof_with_pis_target(&targets.pending_deposits_proof, &pending_deposits_proof);
+
+        for (branch_targets, sibling) in targets.pending_deposits_branch.iter().zip(&branch) {
+            for (target, bit) in branch_targets.iter().zip(array_to_bits(sibling)) {
+                pw.set_bool_target(*target, bit);
+            }
+        }
+
+        let proof = data.prove(pw)?;
+
+        assert_eq!(
+            proof.public_inputs[..FINAL_PUBLIC_INPUTS_LEN],
+            final_proof.public_inputs
+        );
+        assert_eq!(
+            proof.get_pending_deposits_final_circuit_pending_deposits_sum(),
+            BigUint::from(pending_deposits_sum)
+        );
+        assert_eq!(
+            proof.get_pending_deposits_final_circuit_total_sum(),
+            BigUint::from(balance_sum + pending_deposits_sum)
+        );
+
+        data.verify(proof)
+    }
+
+    #[test]
+    #[should_panic(expected = "The final proof must be built without `commit_public_inputs`")]
+    fn test_committed_final_proof_is_rejected() {
+        let final_circuit = build_mock_circuit(FINAL_PUBLIC_INPUTS_COMMITMENT_LEN);
+        let pending_deposits_circuit = build_mock_circuit(PENDING_DEPOSITS_COUNT_PUB_INDEX + 1);
+
+        build_pending_deposits_final_circuit(
+            &final_circuit.1,
+            &pending_deposits_circuit.1,
+            Fork::Electra,
+        );
+    }
+}
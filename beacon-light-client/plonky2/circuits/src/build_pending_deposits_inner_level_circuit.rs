@@ -0,0 +1,264 @@
+use plonky2::{
+    field::goldilocks_field::GoldilocksField,
+    plonk::{
+        circuit_builder::CircuitBuilder,
+        circuit_data::{CircuitConfig, CircuitData, VerifierCircuitTarget},
+        config::{GenericConfig, PoseidonGoldilocksConfig},
+        proof::ProofWithPublicInputsTarget,
+    },
+    util::serialization::{Buffer, IoResult, Read, Write},
+};
+
+use crate::{
+    biguint::CircuitBuilderBiguint,
+    build_pending_deposits_circuit::{set_public_variables, PendingDepositsProofTargetsExt},
+    sha256::make_circuits,
+    targets_serialization::{ReadTargets, WriteTargets},
+    utils::ETH_SHA256_BIT_SIZE,
+};
+
+pub struct PendingDepositsInnerCircuitTargets {
+    pub proof1: ProofWithPublicInputsTarget<2>,
+    pub proof2: ProofWithPublicInputsTarget<2>,
+    pub verifier_circuit_target: VerifierCircuitTarget,
+}
+
+impl ReadTargets for PendingDepositsInnerCircuitTargets {
+    fn read_targets(data: &mut Buffer) -> IoResult<Self> {
+        Ok(PendingDepositsInnerCircuitTargets {
+            proof1: data.read_target_proof_with_public_inputs()?,
+            proof2: data.read_target_proof_with_public_inputs()?,
+            verifier_circuit_target: data.read_target_verifier_circuit()?,
+        })
+    }
+}
+
+impl WriteTargets for PendingDepositsInnerCircuitTargets {
+    fn write_targets(&self) -> IoResult<Vec<u8>> {
+        let mut data = Vec::<u8>::new();
+
+        data.write_target_proof_with_public_inputs(&self.proof1)?;
+        data.write_target_proof_with_public_inputs(&self.proof2)?;
+        data.write_target_verifier_circuit(&self.verifier_circuit_target)?;
+
+        Ok(data)
+    }
+}
+
+pub fn build_pending_deposits_inner_level_circuit(
+    inner_circuit_data: &CircuitData<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+) -> (
+    PendingDepositsInnerCircuitTargets,
+    CircuitData<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+) {
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    let standard_recursion_config = CircuitConfig::standard_recursion_config();
+
+    let mut builder = CircuitBuilder::<F, D>::new(standard_recursion_config);
+
+    let verifier_circuit_target = builder.constant_verifier_data(&inner_circuit_data.verifier_only);
+
+    let pt1: ProofWithPublicInputsTarget<2> =
+        builder.add_virtual_proof_with_pis(&inner_circuit_data.common);
+    let pt2: ProofWithPublicInputsTarget<2> =
+        builder.add_virtual_proof_with_pis(&inner_circuit_data.common);
+
+    builder.verify_proof::<C>(&pt1, &verifier_circuit_target, &inner_circuit_data.common);
+
+    builder.verify_proof::<C>(&pt2, &verifier_circuit_target, &inner_circuit_data.common);
+
+    let deposits_root1 = pt1.get_pending_deposits_root();
+
+    let deposits_root2 = pt2.get_pending_deposits_root();
+
+    let hasher = make_circuits(&mut builder, (2 * ETH_SHA256_BIT_SIZE) as u64);
+
+    for i in 0..ETH_SHA256_BIT_SIZE {
+        builder.connect(hasher.message[i].target, deposits_root1[i].target);
+        builder.connect(
+            hasher.message[i + ETH_SHA256_BIT_SIZE].target,
+            deposits_root2[i].target,
+        );
+    }
+
+    let sum1 = pt1.get_pending_deposits_total_value();
+
+    let sum2 = pt2.get_pending_deposits_total_value();
+
+    let sum = builder.add_biguint_checked(&sum1, &sum2);
+
+    let count1 = pt1.get_pending_deposits_count();
+
+    let count2 = pt2.get_pending_deposits_count();
+
+    let count = builder.add(count1, count2);
+
+    let withdrawal_credentials_commitment1 =
+        pt1.get_pending_deposits_withdrawal_credentials_commitment();
+    let withdrawal_credentials_commitment2 =
+        pt2.get_pending_deposits_withdrawal_credentials_commitment();
+
+    builder.connect_hashes(
+        withdrawal_credentials_commitment1,
+        withdrawal_credentials_commitment2,
+    );
+
+    set_public_variables(
+        &mut builder,
+        &sum,
+        hasher.digest.try_into().unwrap(),
+        withdrawal_credentials_commitment1,
+        count,
+    );
+
+    let data = builder.build::<C>();
+
+    (
+        PendingDepositsInnerCircuitTargets {
+            proof1: pt1,
+            proof2: pt2,
+            verifier_circuit_target,
+        },
+        data,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use num::BigUint;
+    use plonky2::{
+        field::{goldilocks_field::GoldilocksField, types::Field},
+        iop::{
+            target::Target,
+            witness::{PartialWitness, WitnessWrite},
+        },
+        plonk::{
+            circuit_builder::CircuitBuilder,
+            circuit_data::{CircuitConfig, CircuitData},
+            config::PoseidonGoldilocksConfig,
+            proof::ProofWithPublicInputs,
+        },
+    };
+    use sha2::{Digest, Sha256};
+
+    use crate::{
+        build_pending_deposits_circuit::{
+            PendingDepositsProofExt, PENDING_DEPOSITS_COUNT_PUB_INDEX,
+            PENDING_DEPOSITS_ROOT_PUB_INDEX, PENDING_DEPOSITS_TOTAL_VALUE_PUB_INDEX,
+            PENDING_DEPOSITS_WITHDRAWAL_CREDENTIALS_COMMITMENT_PUB_INDEX,
+        },
+        sha256::array_to_bits,
+        utils::POSEIDON_HASH_SIZE,
+    };
+
+    use super::build_pending_deposits_inner_level_circuit;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = GoldilocksField;
+
+    type ChildCircuit = (Vec<Target>, CircuitData<F, C, D>);
+
+    /// A circuit with the public inputs of a pending deposits proof, all of them
+    /// taken from the witness.
+    fn build_child_circuit() -> ChildCircuit {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+
+        let public_inputs = builder.add_virtual_targets(PENDING_DEPOSITS_COUNT_PUB_INDEX + 1);
+        builder.register_public_inputs(&public_inputs);
+
+        (public_inputs, builder.build::<C>())
+    }
+
+    fn prove_child(
+        (public_inputs, data): &ChildCircuit,
+        root: &[u8; 32],
+        total_value: u64,
+        count: u64,
+        withdrawal_credentials_commitment: u64,
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        let mut values = vec![0; public_inputs.len()];
+
+        values[PENDING_DEPOSITS_TOTAL_VALUE_PUB_INDEX] = total_value & 0xFFFFFFFF;
+        values[PENDING_DEPOSITS_TOTAL_VALUE_PUB_INDEX + 1] = total_value >> 32;
+
+        for (i, bit) in array_to_bits(root).into_iter().enumerate() {
+            values[PENDING_DEPOSITS_ROOT_PUB_INDEX + i] = bit as u64;
+        }
+
+        for i in 0..POSEIDON_HASH_SIZE {
+            values[PENDING_DEPOSITS_WITHDRAWAL_CREDENTIALS_COMMITMENT_PUB_INDEX + i] =
+                withdrawal_credentials_commitment + i as u64;
+        }
+
+        values[PENDING_DEPOSITS_COUNT_PUB_INDEX] = count;
+
+        let mut pw = PartialWitness::new();
+
+        for (target, value) in public_inputs.iter().zip(values) {
+            pw.set_target(*target, F::from_canonical_u64(value));
+        }
+
+        data.prove(pw)
+    }
+
+    fn prove_inner_level(
+        child: &ChildCircuit,
+        children: [&ProofWithPublicInputs<F, C, D>; 2],
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        let (targets, data) = build_pending_deposits_inner_level_circuit(&child.1);
+
+        let mut pw = PartialWitness::new();
+
+        pw.set_proof_with_pis_target(&targets.proof1, children[0]);
+        pw.set_proof_with_pis_target(&targets.proof2, children[1]);
+
+        data.prove(pw)
+    }
+
+    #[test]
+    fn test_inner_level_sums_children() -> Result<()> {
+        let child = build_child_circuit();
+
+        let left = prove_child(&child, &[1u8; 32], 32000000000, 8, 100)?;
+        let right = prove_child(&child, &[2u8; 32], 5000000000, 3, 100)?;
+
+        let proof = prove_inner_level(&child, [&left, &right])?;
+
+        let mut hasher = Sha256::new();
+        hasher.update([1u8; 32]);
+        hasher.update([2u8; 32]);
+        let expected_root = array_to_bits(hasher.finalize().as_slice())
+            .into_iter()
+            .map(|bit| bit as u64)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            proof.get_pending_deposits_total_value(),
+            BigUint::from(37000000000u64)
+        );
+        assert_eq!(proof.get_pending_deposits_root().to_vec(), expected_root);
+        assert_eq!(proof.get_pending_deposits_count(), 11);
+        assert_eq!(
+            proof.get_pending_deposits_withdrawal_credentials_commitment(),
+            [100, 101, 102, 103]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "set twice with different values")]
+    fn test_children_with_different_credentials_are_rejected() {
+        let child = build_child_circuit();
+
+        let left = prove_child(&child, &[1u8; 32], 32000000000, 8, 100).unwrap();
+        let right = prove_child(&child, &[2u8; 32], 5000000000, 3, 200).unwrap();
+
+        prove_inner_level(&child, [&left, &right]).unwrap();
+    }
+}
@@ -3,7 +3,7 @@ use num::BigUint;
 use plonky2::{
     field::{goldilocks_field::GoldilocksField, types::Field64},
     hash::hash_types::HashOutTarget,
-    iop::target::BoolTarget,
+    iop::target::{BoolTarget, Target},
     plonk::{
         circuit_builder::CircuitBuilder,
         circuit_data::CircuitConfig,
@@ -11,22 +11,29 @@ use plonky2::{
         proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget},
     },
 };
-use plonky2_u32::gadgets::arithmetic_u32::U32Target;
 
 use crate::{
     biguint::BigUintTarget,
-    utils::{ETH_SHA256_BIT_SIZE, POSEIDON_HASH_SIZE},
+    utils::{ProofBiguintExt, ProofTargetBiguintExt, ETH_SHA256_BIT_SIZE, POSEIDON_HASH_SIZE},
     validator_balance_circuit::{
         validator_balance_verification, ValidatorBalanceVerificationTargets,
     },
+    withdrawal_credentials::WithdrawalCredentialsMatching,
 };
 
 pub const RANGE_TOTAL_VALUE_PUB_INDEX: usize = 0;
 pub const RANGE_BALANCES_ROOT_PUB_INDEX: usize = 2;
-pub const WITHDRAWAL_CREDENTIALS_PUB_INDEX: usize = 258;
-pub const WITHDRAWAL_CREDENTIALS_SIZE: usize = 8;
-pub const RANGE_VALIDATOR_COMMITMENT_PUB_INDEX: usize = 266;
-pub const CURRENT_EPOCH_PUB_INDEX: usize = 270;
+pub const WITHDRAWAL_CREDENTIALS_COMMITMENT_PUB_INDEX: usize = 258;
+pub const RANGE_VALIDATOR_COMMITMENT_PUB_INDEX: usize = 262;
+pub const CURRENT_EPOCH_PUB_INDEX: usize = 266;
+pub const RANGE_SLASHED_TOTAL_VALUE_PUB_INDEX: usize = 268;
+pub const RANGE_COUNTED_VALIDATORS_PUB_INDEX: usize = 270;
+pub const RANGE_EFFECTIVE_BALANCE_TOTAL_VALUE_PUB_INDEX: usize = 271;
+pub const RANGE_PENDING_TOTAL_VALUE_PUB_INDEX: usize = 273;
+pub const RANGE_ACTIVE_TOTAL_VALUE_PUB_INDEX: usize = 275;
+pub const RANGE_EXITED_TOTAL_VALUE_PUB_INDEX: usize = 277;
+pub const RANGE_WITHDRAWABLE_TOTAL_VALUE_PUB_INDEX: usize = 279;
+pub const RANGE_VALID_UNTIL_EPOCH_PUB_INDEX: usize = 281;
 
 type ValidatorBalanceProof = ProofWithPublicInputs<GoldilocksField, PoseidonGoldilocksConfig, 2>;
 
@@ -35,21 +42,32 @@ pub trait ValidatorBalanceProofExt {
 
     fn get_range_balances_root(&self) -> [u64; ETH_SHA256_BIT_SIZE];
 
-    fn get_withdrawal_credentials(&self) -> BigUint;
+    fn get_withdrawal_credentials_commitment(&self) -> [u64; POSEIDON_HASH_SIZE];
 
     fn get_range_validator_commitment(&self) -> [u64; POSEIDON_HASH_SIZE];
 
     fn get_current_epoch(&self) -> BigUint;
+
+    fn get_range_slashed_total_value(&self) -> BigUint;
+
+    fn get_range_counted_validators(&self) -> u64;
+
+    fn get_range_effective_balance_total_value(&self) -> BigUint;
+
+    fn get_range_pending_total_value(&self) -> BigUint;
+
+    fn get_range_active_total_value(&self) -> BigUint;
+
+    fn get_range_exited_total_value(&self) -> BigUint;
+
+    fn get_range_withdrawable_total_value(&self) -> BigUint;
+
+    fn get_range_valid_until_epoch(&self) -> BigUint;
 }
 
 impl ValidatorBalanceProofExt for ValidatorBalanceProof {
     fn get_range_total_value(&self) -> BigUint {
-        BigUint::new(
-            self.public_inputs[RANGE_TOTAL_VALUE_PUB_INDEX..RANGE_TOTAL_VALUE_PUB_INDEX + 2]
-                .iter()
-                .map(|x| (x.0 % GoldilocksField::ORDER) as u32)
-                .collect(),
-        )
+        self.biguint_at(RANGE_TOTAL_VALUE_PUB_INDEX)
     }
 
     fn get_range_balances_root(&self) -> [u64; ETH_SHA256_BIT_SIZE] {
@@ -61,14 +79,14 @@ impl ValidatorBalanceProofExt for ValidatorBalanceProof {
             .unwrap()
     }
 
-    fn get_withdrawal_credentials(&self) -> BigUint {
-        BigUint::new(
-            self.public_inputs[WITHDRAWAL_CREDENTIALS_PUB_INDEX
-                ..WITHDRAWAL_CREDENTIALS_PUB_INDEX + WITHDRAWAL_CREDENTIALS_SIZE]
-                .iter()
-                .map(|x| (x.0 % GoldilocksField::ORDER) as u32)
-                .collect(),
-        )
+    fn get_withdrawal_credentials_commitment(&self) -> [u64; POSEIDON_HASH_SIZE] {
+        self.public_inputs[WITHDRAWAL_CREDENTIALS_COMMITMENT_PUB_INDEX
+            ..WITHDRAWAL_CREDENTIALS_COMMITMENT_PUB_INDEX + POSEIDON_HASH_SIZE]
+            .iter()
+            .map(|x| (x.0 % GoldilocksField::ORDER) as u64)
+            .collect_vec()
+            .try_into()
+            .unwrap()
     }
 
     fn get_range_validator_commitment(&self) -> [u64; POSEIDON_HASH_SIZE] {
@@ -82,12 +100,39 @@ impl ValidatorBalanceProofExt for ValidatorBalanceProof {
     }
 
     fn get_current_epoch(&self) -> BigUint {
-        BigUint::new(
-            self.public_inputs[CURRENT_EPOCH_PUB_INDEX..CURRENT_EPOCH_PUB_INDEX + 2]
-                .iter()
-                .map(|x| (x.0 % GoldilocksField::ORDER) as u32)
-                .collect(),
-        )
+        self.biguint_at(CURRENT_EPOCH_PUB_INDEX)
+    }
+
+    fn get_range_slashed_total_value(&self) -> BigUint {
+        self.biguint_at(RANGE_SLASHED_TOTAL_VALUE_PUB_INDEX)
+    }
+
+    fn get_range_counted_validators(&self) -> u64 {
+        self.public_inputs[RANGE_COUNTED_VALIDATORS_PUB_INDEX].0 % GoldilocksField::ORDER
+    }
+
+    fn get_range_effective_balance_total_value(&self) -> BigUint {
+        self.biguint_at(RANGE_EFFECTIVE_BALANCE_TOTAL_VALUE_PUB_INDEX)
+    }
+
+    fn get_range_pending_total_value(&self) -> BigUint {
+        self.biguint_at(RANGE_PENDING_TOTAL_VALUE_PUB_INDEX)
+    }
+
+    fn get_range_active_total_value(&self) -> BigUint {
+        self.biguint_at(RANGE_ACTIVE_TOTAL_VALUE_PUB_INDEX)
+    }
+
+    fn get_range_exited_total_value(&self) -> BigUint {
+        self.biguint_at(RANGE_EXITED_TOTAL_VALUE_PUB_INDEX)
+    }
+
+    fn get_range_withdrawable_total_value(&self) -> BigUint {
+        self.biguint_at(RANGE_WITHDRAWABLE_TOTAL_VALUE_PUB_INDEX)
+    }
+
+    fn get_range_valid_until_epoch(&self) -> BigUint {
+        self.biguint_at(RANGE_VALID_UNTIL_EPOCH_PUB_INDEX)
     }
 }
 
@@ -98,22 +143,32 @@ pub trait ValidatorBalanceProofTargetsExt {
 
     fn get_range_balances_root(&self) -> [BoolTarget; ETH_SHA256_BIT_SIZE];
 
-    fn get_withdrawal_credentials(&self) -> BigUintTarget;
+    fn get_withdrawal_credentials_commitment(&self) -> HashOutTarget;
 
     fn get_range_validator_commitment(&self) -> HashOutTarget;
 
     fn get_current_epoch(&self) -> BigUintTarget;
+
+    fn get_range_slashed_total_value(&self) -> BigUintTarget;
+
+    fn get_range_counted_validators(&self) -> Target;
+
+    fn get_range_effective_balance_total_value(&self) -> BigUintTarget;
+
+    fn get_range_pending_total_value(&self) -> BigUintTarget;
+
+    fn get_range_active_total_value(&self) -> BigUintTarget;
+
+    fn get_range_exited_total_value(&self) -> BigUintTarget;
+
+    fn get_range_withdrawable_total_value(&self) -> BigUintTarget;
+
+    fn get_range_valid_until_epoch(&self) -> BigUintTarget;
 }
 
 impl ValidatorBalanceProofTargetsExt for ValidatorBalanceProofTargets {
     fn get_range_total_value(&self) -> BigUintTarget {
-        BigUintTarget {
-            limbs: self.public_inputs[RANGE_TOTAL_VALUE_PUB_INDEX..RANGE_TOTAL_VALUE_PUB_INDEX + 2]
-                .iter()
-                .cloned()
-                .map(|x| U32Target(x))
-                .collect_vec(),
-        }
+        self.biguint_at(RANGE_TOTAL_VALUE_PUB_INDEX)
     }
 
     fn get_range_balances_root(&self) -> [BoolTarget; ETH_SHA256_BIT_SIZE] {
@@ -127,14 +182,12 @@ impl ValidatorBalanceProofTargetsExt for ValidatorBalanceProofTargets {
             .unwrap()
     }
 
-    fn get_withdrawal_credentials(&self) -> BigUintTarget {
-        BigUintTarget {
-            limbs: self.public_inputs[WITHDRAWAL_CREDENTIALS_PUB_INDEX
-                ..WITHDRAWAL_CREDENTIALS_PUB_INDEX + WITHDRAWAL_CREDENTIALS_SIZE]
-                .iter()
-                .cloned()
-                .map(|x| U32Target(x))
-                .collect_vec(),
+    fn get_withdrawal_credentials_commitment(&self) -> HashOutTarget {
+        HashOutTarget {
+            elements: self.public_inputs[WITHDRAWAL_CREDENTIALS_COMMITMENT_PUB_INDEX
+                ..WITHDRAWAL_CREDENTIALS_COMMITMENT_PUB_INDEX + POSEIDON_HASH_SIZE]
+                .try_into()
+                .unwrap(),
         }
     }
 
@@ -148,18 +201,47 @@ impl ValidatorBalanceProofTargetsExt for ValidatorBalanceProofTargets {
     }
 
     fn get_current_epoch(&self) -> BigUintTarget {
-        BigUintTarget {
-            limbs: self.public_inputs[CURRENT_EPOCH_PUB_INDEX..CURRENT_EPOCH_PUB_INDEX + 2]
-                .iter()
-                .cloned()
-                .map(|x| U32Target(x))
-                .collect_vec(),
-        }
+        self.biguint_at(CURRENT_EPOCH_PUB_INDEX)
+    }
+
+    fn get_range_slashed_total_value(&self) -> BigUintTarget {
+        self.biguint_at(RANGE_SLASHED_TOTAL_VALUE_PUB_INDEX)
+    }
+
+    fn get_range_counted_validators(&self) -> Target {
+        self.public_inputs[RANGE_COUNTED_VALIDATORS_PUB_INDEX]
+    }
+
+    fn get_range_effective_balance_total_value(&self) -> BigUintTarget {
+        self.biguint_at(RANGE_EFFECTIVE_BALANCE_TOTAL_VALUE_PUB_INDEX)
+    }
+
+    fn get_range_pending_total_value(&self) -> BigUintTarget {
+        self.biguint_at(RANGE_PENDING_TOTAL_VALUE_PUB_INDEX)
+    }
+
+    fn get_range_active_total_value(&self) -> BigUintTarget {
+        self.biguint_at(RANGE_ACTIVE_TOTAL_VALUE_PUB_INDEX)
+    }
+
+    fn get_range_exited_total_value(&self) -> BigUintTarget {
+        self.biguint_at(RANGE_EXITED_TOTAL_VALUE_PUB_INDEX)
+    }
+
+    fn get_range_withdrawable_total_value(&self) -> BigUintTarget {
+        self.biguint_at(RANGE_WITHDRAWABLE_TOTAL_VALUE_PUB_INDEX)
+    }
+
+    fn get_range_valid_until_epoch(&self) -> BigUintTarget {
+        self.biguint_at(RANGE_VALID_UNTIL_EPOCH_PUB_INDEX)
     }
 }
 
 pub fn build_validator_balance_circuit(
     validators_len: usize,
+    withdrawal_credentials_len: usize,
+    exclude_slashed: bool,
+    matching: &WithdrawalCredentialsMatching,
 ) -> (
     ValidatorBalanceVerificationTargets,
     plonky2::plonk::circuit_data::CircuitData<
@@ -176,44 +258,101 @@ pub fn build_validator_balance_circuit(
 
     let mut builder = CircuitBuilder::<F, D>::new(standard_recursion_config);
 
-    let validator_balance_verification_targets =
-        validator_balance_verification(&mut builder, validators_len);
-
-    set_public_variables(
+    let validator_balance_verification_targets = validator_balance_verification(
         &mut builder,
-        &validator_balance_verification_targets.range_total_value,
-        validator_balance_verification_targets.range_balances_root,
-        &validator_balance_verification_targets.withdrawal_credentials,
-        validator_balance_verification_targets.range_validator_commitment,
-        &validator_balance_verification_targets.current_epoch,
+        validators_len,
+        withdrawal_credentials_len,
+        exclude_slashed,
+        matching,
     );
 
+    ValidatorBalancePublicInputsTargets::from(&validator_balance_verification_targets)
+        .register(&mut builder);
+
     let data = builder.build::<C>();
 
     (validator_balance_verification_targets, data)
 }
 
-pub fn set_public_variables(
-    builder: &mut CircuitBuilder<plonky2::field::goldilocks_field::GoldilocksField, 2>,
-    range_total_value: &BigUintTarget,
-    range_balances_root: [BoolTarget; ETH_SHA256_BIT_SIZE],
-    withdrawal_credentials: &BigUintTarget,
-    range_validator_commitment: HashOutTarget,
-    current_epoch: &BigUintTarget,
-) {
-    builder.register_public_inputs(&range_total_value.limbs.iter().map(|x| x.0).collect_vec());
+/// The public inputs of the first level and inner level circuits, in the order
+/// of the `*_PUB_INDEX` constants.
+pub struct ValidatorBalancePublicInputsTargets {
+    pub range_total_value: BigUintTarget,
+    pub range_balances_root: [BoolTarget; ETH_SHA256_BIT_SIZE],
+    pub withdrawal_credentials_commitment: HashOutTarget,
+    pub range_validator_commitment: HashOutTarget,
+    pub current_epoch: BigUintTarget,
+    pub range_slashed_total_value: BigUintTarget,
+    pub range_counted_validators: Target,
+    pub range_effective_balance_total_value: BigUintTarget,
+    pub range_pending_total_value: BigUintTarget,
+    pub range_active_total_value: BigUintTarget,
+    pub range_exited_total_value: BigUintTarget,
+    pub range_withdrawable_total_value: BigUintTarget,
+    pub range_valid_until_epoch: BigUintTarget,
+}
 
-    builder.register_public_inputs(&range_balances_root.map(|x| x.target));
+impl From<&ValidatorBalanceVerificationTargets> for ValidatorBalancePublicInputsTargets {
+    fn from(targets: &ValidatorBalanceVerificationTargets) -> Self {
+        ValidatorBalancePublicInputsTargets {
+            range_total_value: targets.range_total_value.clone(),
+            range_balances_root: targets.range_balances_root,
+            withdrawal_credentials_commitment: targets.withdrawal_credentials_commitment,
+            range_validator_commitment: targets.range_validator_commitment,
+            current_epoch: targets.current_epoch.clone(),
+            range_slashed_total_value: targets.range_slashed_total_value.clone(),
+            range_counted_validators: targets.range_counted_validators,
+            range_effective_balance_total_value: targets
+                .range_effective_balance_total_value
+                .clone(),
+            range_pending_total_value: targets.range_pending_total_value.clone(),
+            range_active_total_value: targets.range_active_total_value.clone(),
+            range_exited_total_value: targets.range_exited_total_value.clone(),
+            range_withdrawable_total_value: targets.range_withdrawable_total_value.clone(),
+            range_valid_until_epoch: targets.range_valid_until_epoch.clone(),
+        }
+    }
+}
 
-    builder.register_public_inputs(
-        &withdrawal_credentials
-            .limbs
-            .iter()
-            .map(|x| x.0)
-            .collect_vec(),
-    );
+impl ValidatorBalancePublicInputsTargets {
+    pub fn register(&self, builder: &mut CircuitBuilder<GoldilocksField, 2>) {
+        builder.register_public_inputs(
+            &self
+                .range_total_value
+                .limbs
+                .iter()
+                .map(|x| x.0)
+                .collect_vec(),
+        );
+
+        builder.register_public_inputs(&self.range_balances_root.map(|x| x.target));
+
+        builder.register_public_inputs(&self.withdrawal_credentials_commitment.elements);
 
-    builder.register_public_inputs(&range_validator_commitment.elements);
+        builder.register_public_inputs(&self.range_validator_commitment.elements);
 
-    builder.register_public_inputs(&current_epoch.limbs.iter().map(|x| x.0).collect_vec());
+        builder.register_public_inputs(&self.current_epoch.limbs.iter().map(|x| x.0).collect_vec());
+
+        builder.register_public_inputs(
+            &self
+                .range_slashed_total_value
+                .limbs
+                .iter()
+                .map(|x| x.0)
+                .collect_vec(),
+        );
+
+        builder.register_public_input(self.range_counted_validators);
+
+        for value in [
+            &self.range_effective_balance_total_value,
+            &self.range_pending_total_value,
+            &self.range_active_total_value,
+            &self.range_exited_total_value,
+            &self.range_withdrawable_total_value,
+            &self.range_valid_until_epoch,
+        ] {
+            builder.register_public_inputs(&value.limbs.iter().map(|x| x.0).collect_vec());
+        }
+    }
 }
@@ -0,0 +1,95 @@
+use anyhow::{anyhow, Result};
+
+/// The BeaconState layout the circuits are built for. Every fork that adds
+/// BeaconState fields can move the proven fields and deepen the state tree.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fork {
+    Deneb,
+    Electra,
+}
+
+impl Fork {
+    pub fn from_name(name: &str) -> Result<Fork> {
+        match name {
+            "deneb" => Ok(Fork::Deneb),
+            "electra" => Ok(Fork::Electra),
+            _ => Err(anyhow!("Unsupported fork: {}", name)),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Fork::Deneb => "deneb",
+            Fork::Electra => "electra",
+        }
+    }
+
+    // log2 of the number of BeaconState fields rounded up to a power of two
+    pub fn beacon_state_depth(&self) -> usize {
+        match self {
+            Fork::Deneb => 5,
+            Fork::Electra => 6,
+        }
+    }
+
+    pub fn slot_gindex(&self) -> u64 {
+        self.field_gindex(2)
+    }
+
+    pub fn state_roots_gindex(&self) -> u64 {
+        self.field_gindex(6)
+    }
+
+    pub fn validators_gindex(&self) -> u64 {
+        self.field_gindex(11)
+    }
+
+    pub fn balances_gindex(&self) -> u64 {
+        self.field_gindex(12)
+    }
+
+    pub fn historical_summaries_gindex(&self) -> u64 {
+        self.field_gindex(27)
+    }
+
+    // pending_deposits was added in Electra
+    pub fn pending_deposits_gindex(&self) -> Option<u64> {
+        match self {
+            Fork::Deneb => None,
+            Fork::Electra => Some(self.field_gindex(34)),
+        }
+    }
+
+    fn field_gindex(&self, field_index: u64) -> u64 {
+        (1 << self.beacon_state_depth()) + field_index
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Fork;
+
+    #[test]
+    fn test_deneb_gindices() {
+        let fork = Fork::Deneb;
+
+        assert_eq!(fork.slot_gindex(), 34);
+        assert_eq!(fork.state_roots_gindex(), 38);
+        assert_eq!(fork.validators_gindex(), 43);
+        assert_eq!(fork.balances_gindex(), 44);
+        assert_eq!(fork.historical_summaries_gindex(), 59);
+        assert_eq!(fork.pending_deposits_gindex(), None);
+    }
+
+    #[test]
+    fn test_electra_gindices() {
+        let fork = Fork::Electra;
+
+        assert_eq!(fork.slot_gindex(), 66);
+        assert_eq!(fork.state_roots_gindex(), 70);
+        assert_eq!(fork.validators_gindex(), 75);
+        assert_eq!(fork.balances_gindex(), 76);
+        assert_eq!(fork.historical_summaries_gindex(), 91);
+        assert_eq!(fork.pending_deposits_gindex(), Some(98));
+    }
+}
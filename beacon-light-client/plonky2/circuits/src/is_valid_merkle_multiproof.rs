@@ -0,0 +1,167 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use plonky2::{
+    field::extension::Extendable, hash::hash_types::RichField, iop::target::BoolTarget,
+    plonk::circuit_builder::CircuitBuilder,
+};
+
+use crate::{
+    ssz::hash_pair,
+    utils::{create_bool_target_array, ETH_SHA256_BIT_SIZE},
+};
+
+pub struct IsValidMerkleMultiproofTargets {
+    pub leaves: Vec<[BoolTarget; ETH_SHA256_BIT_SIZE]>,
+    pub proof: Vec<[BoolTarget; ETH_SHA256_BIT_SIZE]>,
+    pub root: [BoolTarget; ETH_SHA256_BIT_SIZE],
+}
+
+/// The generalized indices of the proof nodes, in the order they are
+/// expected in the multiproof (descending).
+pub fn get_helper_indices(gindices: &[u64]) -> Vec<u64> {
+    let mut branch_indices = BTreeSet::new();
+    let mut path_indices = BTreeSet::new();
+
+    for &gindex in gindices {
+        let mut index = gindex;
+
+        while index > 1 {
+            branch_indices.insert(index ^ 1);
+            path_indices.insert(index);
+            index /= 2;
+        }
+    }
+
+    branch_indices
+        .difference(&path_indices)
+        .rev()
+        .cloned()
+        .collect()
+}
+
+pub fn is_valid_merkle_multiproof<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    gindices: &[u64],
+) -> IsValidMerkleMultiproofTargets {
+    let helper_indices = get_helper_indices(gindices);
+
+    let leaves: Vec<[BoolTarget; ETH_SHA256_BIT_SIZE]> = gindices
+        .iter()
+        .map(|_| create_bool_target_array(builder))
+        .collect();
+
+    let proof: Vec<[BoolTarget; ETH_SHA256_BIT_SIZE]> = helper_indices
+        .iter()
+        .map(|_| create_bool_target_array(builder))
+        .collect();
+
+    let root = create_bool_target_array(builder);
+
+    let mut nodes: BTreeMap<u64, [BoolTarget; ETH_SHA256_BIT_SIZE]> = gindices
+        .iter()
+        .cloned()
+        .zip(leaves.iter().cloned())
+        .chain(helper_indices.iter().cloned().zip(proof.iter().cloned()))
+        .collect();
+
+    let mut keys: Vec<u64> = nodes.keys().rev().cloned().collect();
+
+    let mut pos = 0;
+    while pos < keys.len() {
+        let k = keys[pos];
+
+        if k > 1 && nodes.contains_key(&(k ^ 1)) && !nodes.contains_key(&(k / 2)) {
+            let parent = hash_pair(builder, &nodes[&(k & !1)], &nodes[&(k | 1)]);
+
+            nodes.insert(k / 2, parent);
+            keys.push(k / 2);
+        }
+
+        pos += 1;
+    }
+
+    for i in 0..ETH_SHA256_BIT_SIZE {
+        builder.connect(nodes[&1][i].target, root[i].target);
+    }
+
+    IsValidMerkleMultiproofTargets {
+        leaves,
+        proof,
+        root,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use plonky2::{
+        field::goldilocks_field::GoldilocksField,
+        iop::witness::{PartialWitness, WitnessWrite},
+        plonk::{
+            circuit_builder::CircuitBuilder, circuit_data::CircuitConfig,
+            config::PoseidonGoldilocksConfig,
+        },
+    };
+    use sha2::{Digest, Sha256};
+
+    use crate::{
+        is_valid_merkle_multiproof::{get_helper_indices, is_valid_merkle_multiproof},
+        sha256::array_to_bits,
+    };
+
+    #[test]
+    fn test_get_helper_indices() {
+        assert_eq!(
+            get_helper_indices(&[43, 44, 34]),
+            vec![45, 42, 35, 23, 20, 16, 9, 3]
+        );
+    }
+
+    #[test]
+    fn test_is_valid_merkle_multiproof() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = GoldilocksField;
+
+        // Tree of depth 3 with leaves 8..16 set to [i; 32]
+        let mut nodes = vec![[0u8; 32]; 16];
+        for i in 8..16 {
+            nodes[i] = [i as u8; 32];
+        }
+        for i in (1..8).rev() {
+            let mut hasher = Sha256::new();
+            hasher.update(nodes[2 * i]);
+            hasher.update(nodes[2 * i + 1]);
+            nodes[i] = hasher.finalize().as_slice().try_into().unwrap();
+        }
+
+        let gindices = [9, 14, 15];
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+
+        let targets = is_valid_merkle_multiproof(&mut builder, &gindices);
+
+        let mut pw = PartialWitness::new();
+
+        for (leaf, gindex) in targets.leaves.iter().zip(gindices) {
+            for (target, bit) in leaf.iter().zip(array_to_bits(&nodes[gindex as usize])) {
+                pw.set_bool_target(*target, bit);
+            }
+        }
+
+        for (node, gindex) in targets.proof.iter().zip(get_helper_indices(&gindices)) {
+            for (target, bit) in node.iter().zip(array_to_bits(&nodes[gindex as usize])) {
+                pw.set_bool_target(*target, bit);
+            }
+        }
+
+        for (target, bit) in targets.root.iter().zip(array_to_bits(&nodes[1])) {
+            pw.set_bool_target(*target, bit);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        data.verify(proof)
+    }
+}
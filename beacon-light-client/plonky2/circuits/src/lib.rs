@@ -1,4 +1,6 @@
+pub mod beacon_block_header_hash_tree_root;
 pub mod biguint;
+pub mod build_commitment_mapper_cyclic_circuit;
 pub mod build_commitment_mapper_first_level_circuit;
 pub mod build_commitment_mapper_inner_level_circuit;
 pub mod build_validator_balance_circuit;
@@ -6,7 +8,9 @@ pub mod generator_serializer;
 pub mod hash_tree_root;
 pub mod hash_tree_root_poseidon;
 pub mod is_valid_merkle_branch;
+pub mod is_valid_merkle_multiproof;
 pub mod sha256;
+pub mod ssz;
 pub mod utils;
 pub mod validator_balance_circuit;
 pub mod validator_commitment_mapper;
@@ -14,5 +18,15 @@ pub mod validator_hash_tree_root;
 pub mod validator_hash_tree_root_poseidon;
 pub mod build_balance_inner_level_circuit;
 pub mod is_active_validator;
+pub mod validator_lifecycle;
 pub mod build_final_circuit;
+pub mod build_final_wrapper_circuit;
+pub mod build_historical_state_root_circuit;
+pub mod network_preset;
+pub mod fork;
+pub mod withdrawal_credentials;
+pub mod pending_deposits_circuit;
+pub mod build_pending_deposits_circuit;
+pub mod build_pending_deposits_inner_level_circuit;
+pub mod build_pending_deposits_final_circuit;
 pub mod targets_serialization;
@@ -0,0 +1,129 @@
+use std::fs;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub struct NetworkPreset {
+    pub slots_per_epoch: u64,
+    pub validator_registry_limit: u64,
+    pub slots_per_historical_root: u64,
+    pub historical_roots_limit: u64,
+    #[serde(default = "default_pending_deposits_limit")]
+    pub pending_deposits_limit: u64,
+}
+
+fn default_pending_deposits_limit() -> u64 {
+    1 << 27
+}
+
+impl NetworkPreset {
+    pub fn mainnet() -> NetworkPreset {
+        NetworkPreset {
+            slots_per_epoch: 32,
+            validator_registry_limit: 1 << 40,
+            slots_per_historical_root: 8192,
+            historical_roots_limit: 1 << 24,
+            pending_deposits_limit: default_pending_deposits_limit(),
+        }
+    }
+
+    pub fn minimal() -> NetworkPreset {
+        NetworkPreset {
+            slots_per_epoch: 8,
+            slots_per_historical_root: 64,
+            ..NetworkPreset::mainnet()
+        }
+    }
+
+    pub fn gnosis() -> NetworkPreset {
+        NetworkPreset {
+            slots_per_epoch: 16,
+            ..NetworkPreset::mainnet()
+        }
+    }
+
+    pub fn from_yaml(path: &str) -> Result<NetworkPreset> {
+        let yaml_str = fs::read_to_string(path)?;
+
+        Ok(serde_yaml::from_str(&yaml_str)?)
+    }
+
+    /// Accepts `mainnet`, `minimal`, `gnosis` or the path to a YAML preset.
+    pub fn from_name(name: &str) -> Result<NetworkPreset> {
+        match name {
+            "mainnet" => Ok(NetworkPreset::mainnet()),
+            "minimal" => Ok(NetworkPreset::minimal()),
+            "gnosis" => Ok(NetworkPreset::gnosis()),
+            path => NetworkPreset::from_yaml(path),
+        }
+    }
+
+    pub fn validator_registry_depth(&self) -> usize {
+        log2(self.validator_registry_limit)
+    }
+
+    pub fn state_roots_depth(&self) -> usize {
+        log2(self.slots_per_historical_root)
+    }
+
+    pub fn historical_summaries_depth(&self) -> usize {
+        log2(self.historical_roots_limit)
+    }
+
+    /// Number of levels above the first one, which proves
+    /// `validators_per_circuit` validators.
+    pub fn balance_verification_levels(&self, validators_per_circuit: usize) -> usize {
+        self.validator_registry_depth() - log2(validators_per_circuit as u64)
+    }
+
+    pub fn commitment_mapper_levels(&self) -> usize {
+        self.validator_registry_depth()
+    }
+
+    pub fn pending_deposits_depth(&self) -> usize {
+        log2(self.pending_deposits_limit)
+    }
+
+    pub fn pending_deposits_levels(&self, deposits_per_circuit: usize) -> usize {
+        self.pending_deposits_depth() - log2(deposits_per_circuit as u64)
+    }
+}
+
+fn log2(value: u64) -> usize {
+    assert!(value.is_power_of_two());
+
+    value.trailing_zeros() as usize
+}
+
+#[cfg(test)]
+mod test {
+    use super::NetworkPreset;
+
+    #[test]
+    fn test_mainnet_levels() {
+        let preset = NetworkPreset::mainnet();
+
+        assert_eq!(preset.balance_verification_levels(8), 37);
+        assert_eq!(preset.commitment_mapper_levels(), 40);
+        assert_eq!(preset.state_roots_depth(), 13);
+        assert_eq!(preset.historical_summaries_depth(), 24);
+        assert_eq!(preset.pending_deposits_levels(8), 24);
+    }
+
+    #[test]
+    fn test_custom_preset_from_yaml() {
+        let preset: NetworkPreset = serde_yaml::from_str(
+            "SLOTS_PER_EPOCH: 4\n\
+             VALIDATOR_REGISTRY_LIMIT: 1024\n\
+             SLOTS_PER_HISTORICAL_ROOT: 64\n\
+             HISTORICAL_ROOTS_LIMIT: 16777216\n",
+        )
+        .unwrap();
+
+        assert_eq!(preset.slots_per_epoch, 4);
+        assert_eq!(preset.validator_registry_depth(), 10);
+        assert_eq!(preset.state_roots_depth(), 6);
+    }
+}
@@ -0,0 +1,503 @@
+use plonky2::{
+    field::extension::Extendable,
+    hash::{
+        hash_types::{HashOutTarget, RichField},
+        poseidon::PoseidonHash,
+    },
+    iop::target::{BoolTarget, Target},
+    plonk::circuit_builder::CircuitBuilder,
+    util::serialization::{Buffer, IoResult, Read, Write},
+};
+
+use crate::{
+    biguint::{BigUintTarget, CircuitBuilderBiguint},
+    hash_tree_root::hash_tree_root,
+    ssz::{hash_pair, hash_tree_root_container, merkleize, pack_bits},
+    targets_serialization::{ReadTargets, WriteTargets},
+    utils::{
+        bits_to_biguint_target, create_bool_target_array, if_biguint, ssz_num_to_bits,
+        ETH_SHA256_BIT_SIZE,
+    },
+    withdrawal_credentials::{is_matching_withdrawal_credentials, WithdrawalCredentialsMatching},
+};
+
+pub const PUBKEY_BIT_SIZE: usize = 384;
+pub const SIGNATURE_BIT_SIZE: usize = 768;
+
+#[derive(Clone, Debug)]
+pub struct PendingDepositTargets {
+    pub pubkey: [BoolTarget; PUBKEY_BIT_SIZE],
+    pub withdrawal_credentials: [BoolTarget; ETH_SHA256_BIT_SIZE],
+    pub amount: BigUintTarget,
+    pub signature: [BoolTarget; SIGNATURE_BIT_SIZE],
+    pub slot: BigUintTarget,
+}
+
+impl ReadTargets for PendingDepositTargets {
+    fn read_targets(data: &mut Buffer) -> IoResult<PendingDepositTargets> {
+        Ok(PendingDepositTargets {
+            pubkey: data.read_target_bool_vec()?.try_into().unwrap(),
+            withdrawal_credentials: data.read_target_bool_vec()?.try_into().unwrap(),
+            amount: BigUintTarget::read_targets(data)?,
+            signature: data.read_target_bool_vec()?.try_into().unwrap(),
+            slot: BigUintTarget::read_targets(data)?,
+        })
+    }
+}
+
+impl WriteTargets for PendingDepositTargets {
+    fn write_targets(&self) -> IoResult<Vec<u8>> {
+        let mut data = Vec::<u8>::new();
+
+        data.write_target_bool_vec(&self.pubkey)?;
+        data.write_target_bool_vec(&self.withdrawal_credentials)?;
+        data.extend(BigUintTarget::write_targets(&self.amount)?);
+        data.write_target_bool_vec(&self.signature)?;
+        data.extend(BigUintTarget::write_targets(&self.slot)?);
+
+        Ok(data)
+    }
+}
+
+impl PendingDepositTargets {
+    pub fn new<F: RichField + Extendable<D>, const D: usize>(
+        builder: &mut CircuitBuilder<F, D>,
+    ) -> PendingDepositTargets {
+        PendingDepositTargets {
+            pubkey: [(); PUBKEY_BIT_SIZE].map(|_| builder.add_virtual_bool_target_safe()),
+            withdrawal_credentials: create_bool_target_array(builder),
+            amount: builder.add_virtual_biguint_target(2),
+            signature: [(); SIGNATURE_BIT_SIZE].map(|_| builder.add_virtual_bool_target_safe()),
+            slot: builder.add_virtual_biguint_target(2),
+        }
+    }
+}
+
+pub struct PendingDepositHashTreeRootTargets {
+    pub pending_deposit: PendingDepositTargets,
+    pub hash_tree_root: [BoolTarget; ETH_SHA256_BIT_SIZE],
+}
+
+pub fn hash_tree_root_pending_deposit<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+) -> PendingDepositHashTreeRootTargets {
+    let pending_deposit = PendingDepositTargets::new(builder);
+
+    let pubkey_chunks = pack_bits(builder, &pending_deposit.pubkey);
+    let pubkey_root = hash_pair(builder, &pubkey_chunks[0], &pubkey_chunks[1]);
+
+    let signature_chunks = pack_bits(builder, &pending_deposit.signature);
+    let signature_root = merkleize(builder, &signature_chunks, None);
+
+    let amount_bits = ssz_num_to_bits(builder, &pending_deposit.amount, 64);
+    let slot_bits = ssz_num_to_bits(builder, &pending_deposit.slot, 64);
+
+    let hash_tree_root = hash_tree_root_container(
+        builder,
+        &[
+            pubkey_root,
+            pending_deposit.withdrawal_credentials,
+            amount_bits.try_into().unwrap(),
+            signature_root,
+            slot_bits.try_into().unwrap(),
+        ],
+    );
+
+    PendingDepositHashTreeRootTargets {
+        pending_deposit,
+        hash_tree_root,
+    }
+}
+
+pub struct PendingDepositsVerificationTargets {
+    pub range_total_value: BigUintTarget,
+    pub range_deposits_root: [BoolTarget; ETH_SHA256_BIT_SIZE],
+    pub range_deposits_count: Target,
+    pub pending_deposits: Vec<PendingDepositTargets>,
+    pub deposit_is_zero: Vec<BoolTarget>,
+    pub withdrawal_credentials: Vec<BigUintTarget>,
+    pub withdrawal_credentials_commitment: HashOutTarget,
+}
+
+impl ReadTargets for PendingDepositsVerificationTargets {
+    fn read_targets(data: &mut Buffer) -> IoResult<PendingDepositsVerificationTargets> {
+        let deposits_len = data.read_usize()?;
+        let withdrawal_credentials_len = data.read_usize()?;
+
+        Ok(PendingDepositsVerificationTargets {
+            range_total_value: BigUintTarget::read_targets(data)?,
+            range_deposits_root: data.read_target_bool_vec()?.try_into().unwrap(),
+            range_deposits_count: data.read_target()?,
+            pending_deposits: (0..deposits_len)
+                .map(|_| PendingDepositTargets::read_targets(data).unwrap())
+                .collect(),
+            deposit_is_zero: data.read_target_bool_vec()?,
+            withdrawal_credentials: (0..withdrawal_credentials_len)
+                .map(|_| BigUintTarget::read_targets(data).unwrap())
+                .collect(),
+            withdrawal_credentials_commitment: data.read_target_hash()?,
+        })
+    }
+}
+
+impl WriteTargets for PendingDepositsVerificationTargets {
+    fn write_targets(&self) -> IoResult<Vec<u8>> {
+        let mut data = Vec::<u8>::new();
+
+        data.write_usize(self.pending_deposits.len())?;
+        data.write_usize(self.withdrawal_credentials.len())?;
+        data.extend(BigUintTarget::write_targets(&self.range_total_value)?);
+        data.write_target_bool_vec(&self.range_deposits_root)?;
+        data.write_target(self.range_deposits_count)?;
+
+        for pending_deposit in &self.pending_deposits {
+            data.extend(PendingDepositTargets::write_targets(pending_deposit)?);
+        }
+
+        data.write_target_bool_vec(&self.deposit_is_zero)?;
+
+        for withdrawal_credentials in &self.withdrawal_credentials {
+            data.extend(BigUintTarget::write_targets(withdrawal_credentials)?);
+        }
+
+        data.write_target_hash(&self.withdrawal_credentials_commitment)?;
+
+        Ok(data)
+    }
+}
+
+/// Sums the amounts of the pending deposits whose withdrawal credentials match the set.
+/// The leaves of the zero deposits past the end of the list are zero chunks and they
+/// are not counted in `range_deposits_count`, which sums up to the list length.
+pub fn pending_deposits_verification<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    deposits_len: usize,
+    withdrawal_credentials_len: usize,
+    matching: &WithdrawalCredentialsMatching,
+) -> PendingDepositsVerificationTargets {
+    if !deposits_len.is_power_of_two() || deposits_len < 2 {
+        panic!("deposits_len must be a power of two greater than one");
+    }
+
+    let deposits_leaves: Vec<PendingDepositHashTreeRootTargets> = (0..deposits_len)
+        .map(|_| hash_tree_root_pending_deposit(builder))
+        .collect();
+
+    let deposit_is_zero: Vec<BoolTarget> = (0..deposits_len)
+        .map(|_| builder.add_virtual_bool_target_safe())
+        .collect();
+
+    let deposits_hash_tree_root_targets = hash_tree_root(builder, deposits_len);
+
+    for i in 0..deposits_len {
+        let is_not_zero = builder.not(deposit_is_zero[i]);
+
+        for j in 0..ETH_SHA256_BIT_SIZE {
+            let leaf_bit = builder.and(is_not_zero, deposits_leaves[i].hash_tree_root[j]);
+
+            builder.connect(
+                deposits_hash_tree_root_targets.leaves[i][j].target,
+                leaf_bit.target,
+            );
+        }
+    }
+
+    let withdrawal_credentials: Vec<BigUintTarget> = (0..withdrawal_credentials_len)
+        .map(|_| builder.add_virtual_biguint_target_safe(matching.credentials_limbs()))
+        .collect();
+
+    let withdrawal_credentials_commitment = builder.hash_n_to_hash_no_pad::<PoseidonHash>(
+        withdrawal_credentials
+            .iter()
+            .flat_map(|x| x.limbs.iter().map(|x| x.0))
+            .collect(),
+    );
+
+    let mut sums: Vec<BigUintTarget> = Vec::new();
+
+    sums.push(builder.zero_biguint());
+
+    let mut counts: Vec<Target> = Vec::new();
+
+    counts.push(builder.zero());
+
+    for i in 0..deposits_len {
+        let deposit_credentials = bits_to_biguint_target(
+            builder,
+            deposits_leaves[i]
+                .pending_deposit
+                .withdrawal_credentials
+                .to_vec(),
+        );
+
+        let mut is_equal = builder._false();
+
+        for credentials in &withdrawal_credentials {
+            let is_equal_to_credentials = is_matching_withdrawal_credentials(
+                builder,
+                matching,
+                &deposit_credentials,
+                credentials,
+            );
+
+            is_equal = builder.or(is_equal, is_equal_to_credentials);
+        }
+
+        let is_not_zero = builder.not(deposit_is_zero[i]);
+
+        let will_be_counted = builder.and(is_equal, is_not_zero);
+
+        let zero = builder.zero_biguint();
+
+        let current = if_biguint(
+            builder,
+            will_be_counted,
+            &deposits_leaves[i].pending_deposit.amount,
+            &zero,
+        );
+
+        let tmp_sum = builder.add_biguint_checked(&sums[i], &current);
+
+        sums.push(tmp_sum);
+
+        counts.push(builder.add(counts[i], is_not_zero.target));
+    }
+
+    PendingDepositsVerificationTargets {
+        range_total_value: sums.pop().unwrap(),
+        range_deposits_root: deposits_hash_tree_root_targets.hash_tree_root,
+        range_deposits_count: counts.pop().unwrap(),
+        pending_deposits: deposits_leaves
+            .into_iter()
+            .map(|leaf| leaf.pending_deposit)
+            .collect(),
+        deposit_is_zero,
+        withdrawal_credentials,
+        withdrawal_credentials_commitment,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use num::BigUint;
+    use plonky2::{
+        field::{goldilocks_field::GoldilocksField, types::Field},
+        iop::{
+            target::BoolTarget,
+            witness::{PartialWitness, WitnessWrite},
+        },
+        plonk::{
+            circuit_builder::CircuitBuilder, circuit_data::CircuitConfig,
+            config::PoseidonGoldilocksConfig,
+        },
+    };
+    use sha2::{Digest, Sha256};
+
+    use crate::{
+        biguint::WitnessBigUint,
+        pending_deposits_circuit::{
+            hash_tree_root_pending_deposit, pending_deposits_verification, PendingDepositTargets,
+        },
+        sha256::array_to_bits,
+        withdrawal_credentials::WithdrawalCredentialsMatching,
+    };
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = GoldilocksField;
+
+    struct PendingDeposit {
+        pubkey: [u8; 48],
+        withdrawal_credentials: [u8; 32],
+        amount: u64,
+        signature: [u8; 96],
+        slot: u64,
+    }
+
+    fn pending_deposit(withdrawal_credentials: [u8; 32], amount: u64) -> PendingDeposit {
+        PendingDeposit {
+            pubkey: [7u8; 48],
+            withdrawal_credentials,
+            amount,
+            signature: [9u8; 96],
+            slot: 6953401,
+        }
+    }
+
+    fn sha256(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(left);
+        hasher.update(right);
+        hasher.finalize().as_slice().try_into().unwrap()
+    }
+
+    fn pending_deposit_root(deposit: &PendingDeposit) -> [u8; 32] {
+        let mut pubkey_chunks = [[0u8; 32]; 2];
+        pubkey_chunks[0].copy_from_slice(&deposit.pubkey[..32]);
+        pubkey_chunks[1][..16].copy_from_slice(&deposit.pubkey[32..]);
+
+        let mut signature_chunks = [[0u8; 32]; 3];
+        for (i, chunk) in signature_chunks.iter_mut().enumerate() {
+            chunk.copy_from_slice(&deposit.signature[i * 32..(i + 1) * 32]);
+        }
+
+        let mut amount_chunk = [0u8; 32];
+        amount_chunk[..8].copy_from_slice(&deposit.amount.to_le_bytes());
+
+        let mut slot_chunk = [0u8; 32];
+        slot_chunk[..8].copy_from_slice(&deposit.slot.to_le_bytes());
+
+        let zero_hash = sha256(&[0; 32], &[0; 32]);
+
+        let pubkey_root = sha256(&pubkey_chunks[0], &pubkey_chunks[1]);
+        let signature_root = sha256(
+            &sha256(&signature_chunks[0], &signature_chunks[1]),
+            &sha256(&signature_chunks[2], &[0; 32]),
+        );
+
+        sha256(
+            &sha256(
+                &sha256(&pubkey_root, &deposit.withdrawal_credentials),
+                &sha256(&amount_chunk, &signature_root),
+            ),
+            &sha256(&sha256(&slot_chunk, &[0; 32]), &zero_hash),
+        )
+    }
+
+    fn set_bits(pw: &mut PartialWitness<GoldilocksField>, targets: &[BoolTarget], bytes: &[u8]) {
+        for (target, bit) in targets.iter().zip(array_to_bits(bytes)) {
+            pw.set_bool_target(*target, bit);
+        }
+    }
+
+    fn set_pending_deposit(
+        pw: &mut PartialWitness<GoldilocksField>,
+        targets: &PendingDepositTargets,
+        deposit: &PendingDeposit,
+    ) {
+        set_bits(pw, &targets.pubkey, &deposit.pubkey);
+        set_bits(
+            pw,
+            &targets.withdrawal_credentials,
+            &deposit.withdrawal_credentials,
+        );
+        pw.set_biguint_target(&targets.amount, &BigUint::from(deposit.amount));
+        set_bits(pw, &targets.signature, &deposit.signature);
+        pw.set_biguint_target(&targets.slot, &BigUint::from(deposit.slot));
+    }
+
+    /// Proves two deposits against a set with `withdrawal_credentials` and checks
+    /// the sum, the count and the root of the range.
+    fn prove_pending_deposits(
+        deposits: &[PendingDeposit; 2],
+        deposit_is_zero: [bool; 2],
+        withdrawal_credentials: [u8; 32],
+        expected_total_value: u64,
+        expected_count: u64,
+        expected_root: [u8; 32],
+    ) -> Result<()> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+
+        let targets = pending_deposits_verification(
+            &mut builder,
+            2,
+            1,
+            &WithdrawalCredentialsMatching::FullCredentials,
+        );
+
+        let mut pw = PartialWitness::new();
+
+        for i in 0..2 {
+            set_pending_deposit(&mut pw, &targets.pending_deposits[i], &deposits[i]);
+            pw.set_bool_target(targets.deposit_is_zero[i], deposit_is_zero[i]);
+        }
+
+        pw.set_biguint_target(
+            &targets.withdrawal_credentials[0],
+            &BigUint::from_bytes_be(&withdrawal_credentials),
+        );
+
+        pw.set_biguint_target(
+            &targets.range_total_value,
+            &BigUint::from(expected_total_value),
+        );
+        pw.set_target(
+            targets.range_deposits_count,
+            F::from_canonical_u64(expected_count),
+        );
+        set_bits(&mut pw, &targets.range_deposits_root, &expected_root);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_hash_tree_root_pending_deposit() -> Result<()> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+
+        let targets = hash_tree_root_pending_deposit(&mut builder);
+
+        let deposit = pending_deposit([2u8; 32], 32000000000);
+
+        let mut pw = PartialWitness::new();
+
+        set_pending_deposit(&mut pw, &targets.pending_deposit, &deposit);
+        set_bits(
+            &mut pw,
+            &targets.hash_tree_root,
+            &pending_deposit_root(&deposit),
+        );
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_only_matching_deposits_are_summed() -> Result<()> {
+        let deposits = [
+            pending_deposit([2u8; 32], 32000000000),
+            pending_deposit([3u8; 32], 1000000000),
+        ];
+
+        let root = sha256(
+            &pending_deposit_root(&deposits[0]),
+            &pending_deposit_root(&deposits[1]),
+        );
+
+        prove_pending_deposits(&deposits, [false, false], [2u8; 32], 32000000000, 2, root)
+    }
+
+    #[test]
+    fn test_zero_deposits_are_not_counted() -> Result<()> {
+        // The zero deposit matches the set, so only `deposit_is_zero` keeps it out
+        let deposits = [
+            pending_deposit([2u8; 32], 32000000000),
+            pending_deposit([2u8; 32], 5000000000),
+        ];
+
+        let root = sha256(&pending_deposit_root(&deposits[0]), &[0; 32]);
+
+        prove_pending_deposits(&deposits, [false, true], [2u8; 32], 32000000000, 1, root)
+    }
+
+    #[test]
+    #[should_panic(expected = "set twice with different values")]
+    fn test_non_matching_deposits_are_not_summed() {
+        let deposits = [
+            pending_deposit([2u8; 32], 32000000000),
+            pending_deposit([3u8; 32], 1000000000),
+        ];
+
+        let root = sha256(
+            &pending_deposit_root(&deposits[0]),
+            &pending_deposit_root(&deposits[1]),
+        );
+
+        prove_pending_deposits(&deposits, [false, false], [2u8; 32], 33000000000, 2, root).unwrap();
+    }
+}
@@ -0,0 +1,385 @@
+use itertools::Itertools;
+use plonky2::{
+    field::extension::Extendable, hash::hash_types::RichField, iop::target::BoolTarget,
+    plonk::circuit_builder::CircuitBuilder,
+};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    biguint::BigUintTarget,
+    sha256::{array_to_bits, make_circuits},
+    utils::{ssz_num_to_bits, ETH_SHA256_BIT_SIZE},
+};
+
+pub fn zero_hashes(depth: usize) -> Vec<[bool; ETH_SHA256_BIT_SIZE]> {
+    let mut hashes = vec![[0u8; 32]];
+
+    for i in 0..depth {
+        let mut hasher = Sha256::new();
+        hasher.update(hashes[i]);
+        hasher.update(hashes[i]);
+        hashes.push(hasher.finalize().as_slice().try_into().unwrap());
+    }
+
+    hashes
+        .iter()
+        .map(|hash| array_to_bits(hash).try_into().unwrap())
+        .collect()
+}
+
+pub fn hash_pair<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    left: &[BoolTarget; ETH_SHA256_BIT_SIZE],
+    right: &[BoolTarget; ETH_SHA256_BIT_SIZE],
+) -> [BoolTarget; ETH_SHA256_BIT_SIZE] {
+    let hasher = make_circuits(builder, (2 * ETH_SHA256_BIT_SIZE) as u64);
+
+    for i in 0..ETH_SHA256_BIT_SIZE {
+        builder.connect(hasher.message[i].target, left[i].target);
+        builder.connect(
+            hasher.message[i + ETH_SHA256_BIT_SIZE].target,
+            right[i].target,
+        );
+    }
+
+    hasher.digest.try_into().unwrap()
+}
+
+/// Merkleizes the chunks, padding with zero hashes up to the next power of two
+/// of `limit` (or of the number of chunks if there is no limit).
+pub fn merkleize<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    chunks: &[[BoolTarget; ETH_SHA256_BIT_SIZE]],
+    limit: Option<usize>,
+) -> [BoolTarget; ETH_SHA256_BIT_SIZE] {
+    let limit = limit.unwrap_or(chunks.len());
+    assert!(chunks.len() <= limit);
+
+    let depth = limit.max(1).next_power_of_two().trailing_zeros() as usize;
+    let zero_hashes = zero_hashes(depth);
+
+    let mut level = chunks.to_vec();
+
+    for i in 0..depth {
+        if level.len() % 2 == 1 {
+            level.push(zero_hashes[i].map(|bit| builder.constant_bool(bit)));
+        }
+
+        level = level
+            .chunks(2)
+            .map(|pair| hash_pair(builder, &pair[0], &pair[1]))
+            .collect();
+    }
+
+    if level.is_empty() {
+        zero_hashes[depth].map(|bit| builder.constant_bool(bit))
+    } else {
+        level[0]
+    }
+}
+
+/// Turns the root of a tree of depth `depth` into the root of a tree of depth
+/// `limit_depth` whose other leaves are all zero.
+pub fn extend_merkle_root<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    root: &[BoolTarget; ETH_SHA256_BIT_SIZE],
+    depth: usize,
+    limit_depth: usize,
+) -> [BoolTarget; ETH_SHA256_BIT_SIZE] {
+    assert!(depth <= limit_depth);
+
+    let zero_hashes = zero_hashes(limit_depth);
+
+    let mut root = *root;
+
+    for zero_hash in &zero_hashes[depth..limit_depth] {
+        let zero_hash = zero_hash.map(|bit| builder.constant_bool(bit));
+        root = hash_pair(builder, &root, &zero_hash);
+    }
+
+    root
+}
+
+/// `length` is the uint64 length of the list, as 2 limbs.
+pub fn mix_in_length<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    root: &[BoolTarget; ETH_SHA256_BIT_SIZE],
+    length: &BigUintTarget,
+) -> [BoolTarget; ETH_SHA256_BIT_SIZE] {
+    assert_eq!(length.num_limbs(), 2, "The length must be a 2-limb uint64");
+
+    let length_bits = ssz_num_to_bits(builder, length, 64);
+
+    hash_pair(builder, root, &length_bits.try_into().unwrap())
+}
+
+/// Packs the little-endian serialization of basic values into 32-byte chunks.
+/// Every value is asserted to fit in `bit_len` bits.
+pub fn pack_basic<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    values: &[BigUintTarget],
+    bit_len: usize,
+) -> Vec<[BoolTarget; ETH_SHA256_BIT_SIZE]> {
+    assert!(bit_len % 8 == 0 && ETH_SHA256_BIT_SIZE % bit_len == 0);
+
+    let bits = values
+        .iter()
+        .flat_map(|value| {
+            let bits = ssz_num_to_bits(builder, value, bit_len);
+
+            for bit in bits.iter().take(value.num_limbs() * 32).skip(bit_len) {
+                builder.assert_zero(bit.target);
+            }
+
+            bits[..bit_len].to_vec()
+        })
+        .collect_vec();
+
+    pack_bits(builder, &bits)
+}
+
+/// Splits a byte vector, given as bits, into zero-padded 32-byte chunks.
+pub fn pack_bits<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    bits: &[BoolTarget],
+) -> Vec<[BoolTarget; ETH_SHA256_BIT_SIZE]> {
+    assert!(bits.len() % 8 == 0);
+
+    bits.chunks(ETH_SHA256_BIT_SIZE)
+        .map(|chunk| {
+            let mut chunk = chunk.to_vec();
+            chunk.resize_with(ETH_SHA256_BIT_SIZE, || builder._false());
+            chunk.try_into().unwrap()
+        })
+        .collect()
+}
+
+pub fn hash_tree_root_container<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    field_roots: &[[BoolTarget; ETH_SHA256_BIT_SIZE]],
+) -> [BoolTarget; ETH_SHA256_BIT_SIZE] {
+    merkleize(builder, field_roots, None)
+}
+
+/// `chunks` are the chunks of all the elements the circuit has room for. The
+/// circuit does not constrain the elements past `length`: the caller has to
+/// assert that they are zero, otherwise the root is not the one of the list.
+pub fn hash_tree_root_list<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    chunks: &[[BoolTarget; ETH_SHA256_BIT_SIZE]],
+    chunks_limit: usize,
+    length: &BigUintTarget,
+) -> [BoolTarget; ETH_SHA256_BIT_SIZE] {
+    let root = merkleize(builder, chunks, Some(chunks_limit));
+
+    mix_in_length(builder, &root, length)
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use num::BigUint;
+    use plonky2::{
+        field::goldilocks_field::GoldilocksField,
+        iop::witness::{PartialWitness, WitnessWrite},
+        plonk::{
+            circuit_builder::CircuitBuilder, circuit_data::CircuitConfig,
+            config::PoseidonGoldilocksConfig,
+        },
+    };
+    use sha2::{Digest, Sha256};
+
+    use crate::{
+        biguint::{CircuitBuilderBiguint, WitnessBigUint},
+        sha256::array_to_bits,
+        ssz::{
+            extend_merkle_root, hash_tree_root_container, hash_tree_root_list, mix_in_length,
+            pack_basic, zero_hashes,
+        },
+        utils::create_bool_target_array,
+    };
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = GoldilocksField;
+
+    fn sha256(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(left);
+        hasher.update(right);
+        hasher.finalize().as_slice().try_into().unwrap()
+    }
+
+    #[test]
+    fn test_zero_hashes() {
+        let hashes = zero_hashes(1);
+
+        assert_eq!(hashes[0].to_vec(), array_to_bits(&[0; 32]));
+        assert_eq!(
+            hashes[1].to_vec(),
+            array_to_bits(
+                &hex::decode("f5a5fd42d16a20302798ef6ed309979b43003d2320d9f0e8ea9831a92759fb4b")
+                    .unwrap()
+            )
+        );
+    }
+
+    #[test]
+    fn test_hash_tree_root_container_with_three_fields() -> Result<()> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+
+        let fields = (0..3)
+            .map(|_| create_bool_target_array(&mut builder))
+            .collect::<Vec<_>>();
+
+        let root = hash_tree_root_container(&mut builder, &fields);
+
+        let mut pw = PartialWitness::new();
+
+        let values = [[1u8; 32], [2u8; 32], [3u8; 32]];
+
+        for (field, value) in fields.iter().zip(values.iter()) {
+            for (target, bit) in field.iter().zip(array_to_bits(value)) {
+                pw.set_bool_target(*target, bit);
+            }
+        }
+
+        let expected = sha256(
+            &sha256(&values[0], &values[1]),
+            &sha256(&values[2], &[0; 32]),
+        );
+
+        for (target, bit) in root.iter().zip(array_to_bits(&expected)) {
+            pw.set_bool_target(*target, bit);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_extend_merkle_root() -> Result<()> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+
+        let root = create_bool_target_array(&mut builder);
+
+        let extended_root = extend_merkle_root(&mut builder, &root, 1, 3);
+
+        let mut pw = PartialWitness::new();
+
+        let value = [1u8; 32];
+
+        for (target, bit) in root.iter().zip(array_to_bits(&value)) {
+            pw.set_bool_target(*target, bit);
+        }
+
+        let zero_hash = sha256(&[0; 32], &[0; 32]);
+
+        let expected = sha256(&sha256(&value, &zero_hash), &sha256(&zero_hash, &zero_hash));
+
+        for (target, bit) in extended_root.iter().zip(array_to_bits(&expected)) {
+            pw.set_bool_target(*target, bit);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_hash_tree_root_list_of_u64() -> Result<()> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+
+        let values = (0..4)
+            .map(|_| builder.add_virtual_biguint_target(2))
+            .collect::<Vec<_>>();
+
+        let length = builder.add_virtual_biguint_target(2);
+
+        let chunks = pack_basic(&mut builder, &values, 64);
+
+        // List[uint64, 16] takes up 4 chunks
+        let root = hash_tree_root_list(&mut builder, &chunks, 4, &length);
+
+        let mut pw = PartialWitness::new();
+
+        for (target, value) in values.iter().zip([5u64, 7, 9, 0]) {
+            pw.set_biguint_target(target, &BigUint::from(value));
+        }
+
+        pw.set_biguint_target(&length, &BigUint::from(3u64));
+
+        let mut chunk = [0u8; 32];
+        for (i, value) in [5u64, 7, 9].iter().enumerate() {
+            chunk[i * 8..(i + 1) * 8].copy_from_slice(&value.to_le_bytes());
+        }
+
+        let mut length_chunk = [0u8; 32];
+        length_chunk[..8].copy_from_slice(&3u64.to_le_bytes());
+
+        let zero_hash = sha256(&[0; 32], &[0; 32]);
+
+        let expected = sha256(
+            &sha256(&sha256(&chunk, &[0; 32]), &zero_hash),
+            &length_chunk,
+        );
+
+        for (target, bit) in root.iter().zip(array_to_bits(&expected)) {
+            pw.set_bool_target(*target, bit);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        data.verify(proof)
+    }
+
+    #[test]
+    #[should_panic(expected = "The length must be a 2-limb uint64")]
+    fn test_mix_in_length_rejects_a_one_limb_length() {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+
+        let root = create_bool_target_array(&mut builder);
+        let length = builder.add_virtual_biguint_target(1);
+
+        mix_in_length(&mut builder, &root, &length);
+    }
+
+    fn prove_pack_u32(value: u64) -> Result<()> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+
+        let target = builder.add_virtual_biguint_target(2);
+
+        let chunks = pack_basic(&mut builder, &[target.clone()], 32);
+
+        let mut pw = PartialWitness::new();
+
+        pw.set_biguint_target(&target, &BigUint::from(value));
+
+        let mut chunk = [0u8; 32];
+        chunk[..4].copy_from_slice(&(value as u32).to_le_bytes());
+
+        for (target, bit) in chunks[0].iter().zip(array_to_bits(&chunk)) {
+            pw.set_bool_target(*target, bit);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_pack_basic_u32() -> Result<()> {
+        prove_pack_u32(0xDEADBEEF)
+    }
+
+    #[test]
+    #[should_panic(expected = "set twice with different values")]
+    fn test_pack_basic_rejects_values_wider_than_bit_len() {
+        prove_pack_u32(1 << 32).unwrap();
+    }
+}
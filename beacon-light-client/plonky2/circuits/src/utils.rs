@@ -1,6 +1,13 @@
+use num::BigUint;
 use plonky2::{
-    field::extension::Extendable, hash::hash_types::RichField, iop::target::BoolTarget,
-    plonk::circuit_builder::CircuitBuilder,
+    field::{extension::Extendable, goldilocks_field::GoldilocksField, types::Field64},
+    hash::hash_types::RichField,
+    iop::target::BoolTarget,
+    plonk::{
+        circuit_builder::CircuitBuilder,
+        config::PoseidonGoldilocksConfig,
+        proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget},
+    },
 };
 use plonky2_u32::gadgets::arithmetic_u32::U32Target;
 use sha2::{Digest, Sha256};
@@ -10,6 +17,37 @@ use crate::biguint::{BigUintTarget, CircuitBuilderBiguint};
 pub const ETH_SHA256_BIT_SIZE: usize = 256;
 pub const POSEIDON_HASH_SIZE: usize = 4;
 
+/// Reads the 64-bit values published as two 32-bit limbs.
+pub trait ProofBiguintExt {
+    fn biguint_at(&self, index: usize) -> BigUint;
+}
+
+impl ProofBiguintExt for ProofWithPublicInputs<GoldilocksField, PoseidonGoldilocksConfig, 2> {
+    fn biguint_at(&self, index: usize) -> BigUint {
+        BigUint::new(
+            self.public_inputs[index..index + 2]
+                .iter()
+                .map(|x| (x.0 % GoldilocksField::ORDER) as u32)
+                .collect(),
+        )
+    }
+}
+
+pub trait ProofTargetBiguintExt {
+    fn biguint_at(&self, index: usize) -> BigUintTarget;
+}
+
+impl ProofTargetBiguintExt for ProofWithPublicInputsTarget<2> {
+    fn biguint_at(&self, index: usize) -> BigUintTarget {
+        BigUintTarget {
+            limbs: self.public_inputs[index..index + 2]
+                .iter()
+                .map(|x| U32Target(*x))
+                .collect(),
+        }
+    }
+}
+
 pub fn hash_bit_array(validator_pubkey: Vec<&str>) -> Vec<String> {
     // Concatenate the array into a single binary string
     let binary_string: String = validator_pubkey.join("");
@@ -180,12 +218,8 @@ pub fn if_biguint<F: RichField + Extendable<D>, const D: usize>(
 
     let maybe_y = builder.mul_biguint_by_bool(y, not_b);
 
-    let mut result = builder.add_biguint(&maybe_y, &maybe_x);
-
-    // trim the carry
-    result.limbs.pop();
-
-    result
+    // one of the summands is zero, so the sum never overflows
+    builder.add_biguint_checked(&maybe_y, &maybe_x)
 }
 
 #[cfg(test)]
@@ -1,7 +1,11 @@
+use num::BigUint;
 use plonky2::{
     field::extension::Extendable,
-    hash::hash_types::{HashOutTarget, RichField},
-    iop::target::BoolTarget,
+    hash::{
+        hash_types::{HashOutTarget, RichField},
+        poseidon::PoseidonHash,
+    },
+    iop::target::{BoolTarget, Target},
     plonk::circuit_builder::CircuitBuilder,
     util::serialization::{Buffer, IoResult, Read, Write},
 };
@@ -13,32 +17,52 @@ use crate::{
     is_active_validator::is_active_validator,
     targets_serialization::{ReadTargets, WriteTargets},
     utils::{
-        biguint_is_equal, create_bool_target_array, if_biguint,
+        create_bool_target_array, if_biguint,
         ssz_num_from_bits, ETH_SHA256_BIT_SIZE,
     },
     validator_hash_tree_root_poseidon::{
         hash_tree_root_validator_poseidon, ValidatorPoseidonHashTreeRootTargets,
         ValidatorPoseidonTargets,
     },
+    validator_lifecycle::validator_lifecycle,
+    withdrawal_credentials::{is_matching_withdrawal_credentials, WithdrawalCredentialsMatching},
 };
 
 pub struct ValidatorBalanceVerificationTargets {
     pub range_total_value: BigUintTarget,
+    pub range_slashed_total_value: BigUintTarget,
+    pub range_counted_validators: Target,
+    pub range_effective_balance_total_value: BigUintTarget,
+    pub range_pending_total_value: BigUintTarget,
+    pub range_active_total_value: BigUintTarget,
+    pub range_exited_total_value: BigUintTarget,
+    pub range_withdrawable_total_value: BigUintTarget,
+    pub range_valid_until_epoch: BigUintTarget,
     pub range_balances_root: [BoolTarget; ETH_SHA256_BIT_SIZE],
     pub range_validator_commitment: HashOutTarget,
     pub validators: Vec<ValidatorPoseidonTargets>,
     pub validator_is_zero: Vec<BoolTarget>,
     pub balances: Vec<[BoolTarget; ETH_SHA256_BIT_SIZE]>,
-    pub withdrawal_credentials: BigUintTarget,
+    pub withdrawal_credentials: Vec<BigUintTarget>,
+    pub withdrawal_credentials_commitment: HashOutTarget,
     pub current_epoch: BigUintTarget,
 }
 
 impl ReadTargets for ValidatorBalanceVerificationTargets {
     fn read_targets(data: &mut Buffer) -> IoResult<ValidatorBalanceVerificationTargets> {
         let validators_len = data.read_usize()?;
+        let withdrawal_credentials_len = data.read_usize()?;
 
         Ok(ValidatorBalanceVerificationTargets {
             range_total_value: BigUintTarget::read_targets(data)?,
+            range_slashed_total_value: BigUintTarget::read_targets(data)?,
+            range_counted_validators: data.read_target()?,
+            range_effective_balance_total_value: BigUintTarget::read_targets(data)?,
+            range_pending_total_value: BigUintTarget::read_targets(data)?,
+            range_active_total_value: BigUintTarget::read_targets(data)?,
+            range_exited_total_value: BigUintTarget::read_targets(data)?,
+            range_withdrawable_total_value: BigUintTarget::read_targets(data)?,
+            range_valid_until_epoch: BigUintTarget::read_targets(data)?,
             range_balances_root: data.read_target_bool_vec()?.try_into().unwrap(),
             range_validator_commitment: data.read_target_hash()?,
             validators: (0..validators_len)
@@ -48,7 +72,10 @@ impl ReadTargets for ValidatorBalanceVerificationTargets {
             balances: (0..validators_len / 4)
                 .map(|_| data.read_target_bool_vec().unwrap().try_into().unwrap())
                 .collect(),
-            withdrawal_credentials: BigUintTarget::read_targets(data)?,
+            withdrawal_credentials: (0..withdrawal_credentials_len)
+                .map(|_| BigUintTarget::read_targets(data).unwrap())
+                .collect(),
+            withdrawal_credentials_commitment: data.read_target_hash()?,
             current_epoch: BigUintTarget::read_targets(data)?,
         })
     }
@@ -59,7 +86,22 @@ impl WriteTargets for ValidatorBalanceVerificationTargets {
         let mut data = Vec::<u8>::new();
 
         data.write_usize(self.validators.len())?;
+        data.write_usize(self.withdrawal_credentials.len())?;
         data.extend(BigUintTarget::write_targets(&self.range_total_value)?);
+        data.extend(BigUintTarget::write_targets(
+            &self.range_slashed_total_value,
+        )?);
+        data.write_target(self.range_counted_validators)?;
+        data.extend(BigUintTarget::write_targets(
+            &self.range_effective_balance_total_value,
+        )?);
+        data.extend(BigUintTarget::write_targets(&self.range_pending_total_value)?);
+        data.extend(BigUintTarget::write_targets(&self.range_active_total_value)?);
+        data.extend(BigUintTarget::write_targets(&self.range_exited_total_value)?);
+        data.extend(BigUintTarget::write_targets(
+            &self.range_withdrawable_total_value,
+        )?);
+        data.extend(BigUintTarget::write_targets(&self.range_valid_until_epoch)?);
         data.write_target_bool_vec(&self.range_balances_root)?;
         data.write_target_hash(&self.range_validator_commitment)?;
 
@@ -73,16 +115,34 @@ impl WriteTargets for ValidatorBalanceVerificationTargets {
             data.write_target_bool_vec(balance)?;
         }
 
-        data.extend(BigUintTarget::write_targets(&self.withdrawal_credentials)?);
+        for withdrawal_credentials in &self.withdrawal_credentials {
+            data.extend(BigUintTarget::write_targets(withdrawal_credentials)?);
+        }
+
+        data.write_target_hash(&self.withdrawal_credentials_commitment)?;
         data.extend(BigUintTarget::write_targets(&self.current_epoch)?);
 
         Ok(data)
     }
 }
 
+/// Sums the balances of the active validators whose withdrawal credentials match one
+/// of the `withdrawal_credentials_len` entries of the set.
+///
+/// Outputs:
+/// - `range_total_value`: the sum, without slashed validators if `exclude_slashed` is set
+/// - `range_slashed_total_value`: the balances of the counted slashed validators
+/// - `range_counted_validators` and `range_effective_balance_total_value`
+/// - `range_{pending,active,exited,withdrawable}_total_value`: the balances by lifecycle state
+/// - `range_valid_until_epoch`: the first later epoch at which a validator changes state
+/// - `range_balances_root` and `range_validator_commitment`
+/// - `withdrawal_credentials_commitment`: the Poseidon hash of the set
 pub fn validator_balance_verification<F: RichField + Extendable<D>, const D: usize>(
     builder: &mut CircuitBuilder<F, D>,
     validators_len: usize,
+    withdrawal_credentials_len: usize,
+    exclude_slashed: bool,
+    matching: &WithdrawalCredentialsMatching,
 ) -> ValidatorBalanceVerificationTargets {
     if !validators_len.is_power_of_two() {
         panic!("validators_len must be a power of two");
@@ -139,20 +199,55 @@ pub fn validator_balance_verification<F: RichField + Extendable<D>, const D: usi
         );
     }
 
-    let withdrawal_credentials = builder.add_virtual_biguint_target(8);
+    let withdrawal_credentials: Vec<BigUintTarget> = (0..withdrawal_credentials_len)
+        .map(|_| builder.add_virtual_biguint_target_safe(matching.credentials_limbs()))
+        .collect();
+
+    let withdrawal_credentials_commitment = builder.hash_n_to_hash_no_pad::<PoseidonHash>(
+        withdrawal_credentials
+            .iter()
+            .flat_map(|x| x.limbs.iter().map(|x| x.0))
+            .collect(),
+    );
 
-    let current_epoch = builder.add_virtual_biguint_target(2);
+    let current_epoch = builder.add_virtual_biguint_target_safe(2);
 
     let mut sums: Vec<BigUintTarget> = Vec::new();
 
     sums.push(builder.zero_biguint());
 
+    let mut slashed_sums: Vec<BigUintTarget> = Vec::new();
+
+    slashed_sums.push(builder.zero_biguint());
+
+    let mut counts: Vec<Target> = Vec::new();
+
+    counts.push(builder.zero());
+
+    let mut effective_balance_sums: Vec<BigUintTarget> = Vec::new();
+
+    effective_balance_sums.push(builder.zero_biguint());
+
+    // pending, active, exited, withdrawable
+    let mut lifecycle_sums: Vec<[BigUintTarget; 4]> = Vec::new();
+
+    lifecycle_sums.push([(); 4].map(|_| builder.zero_biguint()));
+
+    let mut range_valid_until_epoch = builder.constant_biguint(&BigUint::from(u64::MAX));
+
     for i in 0..validators_len {
-        let is_equal = biguint_is_equal(
-            builder,
-            &validators_leaves[i].validator.withdrawal_credentials,
-            &withdrawal_credentials,
-        );
+        let mut is_equal = builder._false();
+
+        for credentials in &withdrawal_credentials {
+            let is_equal_to_credentials = is_matching_withdrawal_credentials(
+                builder,
+                matching,
+                &validators_leaves[i].validator.withdrawal_credentials,
+                credentials,
+            );
+
+            is_equal = builder.or(is_equal, is_equal_to_credentials);
+        }
 
         let balance = ssz_num_from_bits(
             builder,
@@ -168,20 +263,111 @@ pub fn validator_balance_verification<F: RichField + Extendable<D>, const D: usi
             &validators_leaves[i].validator.exit_epoch,
         );
 
-        let will_be_counted = builder.and(is_equal, is_valid_validator);
+        let is_matching_active_validator = builder.and(is_equal, is_valid_validator);
 
-        let current = if_biguint(builder, will_be_counted, &balance, &zero);
+        let is_slashed = validators_leaves[i].validator.slashed;
 
-        let mut tmp_sum = builder.add_biguint(&sums[i], &current);
+        let will_be_counted = if exclude_slashed {
+            let is_not_slashed = builder.not(is_slashed);
+            builder.and(is_matching_active_validator, is_not_slashed)
+        } else {
+            is_matching_active_validator
+        };
 
-        tmp_sum.limbs.pop();
+        let current = if_biguint(builder, will_be_counted, &balance, &zero);
+
+        let tmp_sum = builder.add_biguint_checked(&sums[i], &current);
 
         sums.push(tmp_sum);
+
+        counts.push(builder.add(counts[i], will_be_counted.target));
+
+        let current_effective_balance = if_biguint(
+            builder,
+            will_be_counted,
+            &validators_leaves[i].validator.effective_balance,
+            &zero,
+        );
+
+        let tmp_effective_balance_sum =
+            builder.add_biguint_checked(&effective_balance_sums[i], &current_effective_balance);
+
+        effective_balance_sums.push(tmp_effective_balance_sum);
+
+        let lifecycle = validator_lifecycle(
+            builder,
+            &validators_leaves[i].validator.activation_eligibility_epoch,
+            &validators_leaves[i].validator.activation_epoch,
+            &current_epoch,
+            &validators_leaves[i].validator.exit_epoch,
+            &validators_leaves[i].validator.withdrawable_epoch,
+        );
+
+        let is_matching_validator = if exclude_slashed {
+            let is_not_slashed = builder.not(is_slashed);
+            builder.and(is_equal, is_not_slashed)
+        } else {
+            is_equal
+        };
+
+        let mut tmp_lifecycle_sums = Vec::new();
+
+        for (j, is_in_state) in [
+            lifecycle.is_pending,
+            lifecycle.is_active,
+            lifecycle.is_exited,
+            lifecycle.is_withdrawable,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let will_be_counted_in_state = builder.and(is_matching_validator, is_in_state);
+
+            let current_in_state = if_biguint(builder, will_be_counted_in_state, &balance, &zero);
+
+            let tmp_sum = builder.add_biguint_checked(&lifecycle_sums[i][j], &current_in_state);
+
+            tmp_lifecycle_sums.push(tmp_sum);
+        }
+
+        lifecycle_sums.push(tmp_lifecycle_sums.try_into().unwrap());
+
+        // Whether a validator is counted and its lifecycle state only change
+        // once the current epoch reaches one of its epochs
+        for epoch in [
+            &validators_leaves[i].validator.activation_eligibility_epoch,
+            &validators_leaves[i].validator.activation_epoch,
+            &validators_leaves[i].validator.exit_epoch,
+            &validators_leaves[i].validator.withdrawable_epoch,
+        ] {
+            let is_upcoming = builder.lt_biguint(&current_epoch, epoch);
+            let is_sooner = builder.lt_biguint(epoch, &range_valid_until_epoch);
+            let is_next_change = builder.and(is_upcoming, is_sooner);
+
+            range_valid_until_epoch =
+                if_biguint(builder, is_next_change, epoch, &range_valid_until_epoch);
+        }
+
+        let will_be_counted_as_slashed = builder.and(is_matching_active_validator, is_slashed);
+
+        let current_slashed = if_biguint(builder, will_be_counted_as_slashed, &balance, &zero);
+
+        let tmp_slashed_sum = builder.add_biguint_checked(&slashed_sums[i], &current_slashed);
+
+        slashed_sums.push(tmp_slashed_sum);
     }
 
     ValidatorBalanceVerificationTargets {
         validator_is_zero: validator_is_zero,
         range_total_value: sums[validators_len].clone(),
+        range_slashed_total_value: slashed_sums[validators_len].clone(),
+        range_counted_validators: counts[validators_len],
+        range_effective_balance_total_value: effective_balance_sums[validators_len].clone(),
+        range_pending_total_value: lifecycle_sums[validators_len][0].clone(),
+        range_active_total_value: lifecycle_sums[validators_len][1].clone(),
+        range_exited_total_value: lifecycle_sums[validators_len][2].clone(),
+        range_withdrawable_total_value: lifecycle_sums[validators_len][3].clone(),
+        range_valid_until_epoch,
         range_balances_root: balances_hash_tree_root_targets.hash_tree_root,
         range_validator_commitment: hash_tree_root_poseidon_targets.hash_tree_root,
         validators: validators_leaves
@@ -190,6 +376,99 @@ pub fn validator_balance_verification<F: RichField + Extendable<D>, const D: usi
             .collect(),
         balances: balances_leaves,
         withdrawal_credentials: withdrawal_credentials,
+        withdrawal_credentials_commitment,
         current_epoch,
     }
 }
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use num::BigUint;
+    use plonky2::{
+        field::goldilocks_field::GoldilocksField,
+        iop::witness::{PartialWitness, WitnessWrite},
+        plonk::{
+            circuit_builder::CircuitBuilder, circuit_data::CircuitConfig,
+            config::PoseidonGoldilocksConfig,
+        },
+    };
+
+    use crate::{
+        biguint::WitnessBigUint, sha256::array_to_bits,
+        withdrawal_credentials::WithdrawalCredentialsMatching,
+    };
+
+    use super::validator_balance_verification;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = GoldilocksField;
+
+    /// Proves 8 active validators with matching credentials, the first two of
+    /// them with `balances` and the rest with zero balances.
+    fn prove_balances(balances: [u64; 2], expected_total_value: Option<u64>) -> Result<()> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+
+        let targets = validator_balance_verification(
+            &mut builder,
+            8,
+            1,
+            false,
+            &WithdrawalCredentialsMatching::FullCredentials,
+        );
+
+        let mut pw = PartialWitness::new();
+
+        let mut balances_chunk = [0u8; 32];
+        balances_chunk[..8].copy_from_slice(&balances[0].to_le_bytes());
+        balances_chunk[8..16].copy_from_slice(&balances[1].to_le_bytes());
+
+        for (i, chunk) in [balances_chunk, [0u8; 32]].iter().enumerate() {
+            for (target, bit) in targets.balances[i].iter().zip(array_to_bits(chunk)) {
+                pw.set_bool_target(*target, bit);
+            }
+        }
+
+        for (validator, is_zero) in targets.validators.iter().zip(&targets.validator_is_zero) {
+            pw.set_biguint_target(&validator.pubkey, &BigUint::from(7u32));
+            pw.set_biguint_target(&validator.withdrawal_credentials, &BigUint::from(1u32));
+            pw.set_biguint_target(&validator.effective_balance, &BigUint::from(32000000000u64));
+            pw.set_bool_target(validator.slashed, false);
+            pw.set_biguint_target(
+                &validator.activation_eligibility_epoch,
+                &BigUint::from(0u32),
+            );
+            pw.set_biguint_target(&validator.activation_epoch, &BigUint::from(0u32));
+            pw.set_biguint_target(&validator.exit_epoch, &BigUint::from(u64::MAX));
+            pw.set_biguint_target(&validator.withdrawable_epoch, &BigUint::from(u64::MAX));
+            pw.set_bool_target(*is_zero, false);
+        }
+
+        pw.set_biguint_target(&targets.withdrawal_credentials[0], &BigUint::from(1u32));
+        pw.set_biguint_target(&targets.current_epoch, &BigUint::from(10u32));
+
+        if let Some(expected_total_value) = expected_total_value {
+            pw.set_biguint_target(
+                &targets.range_total_value,
+                &BigUint::from(expected_total_value),
+            );
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_balances_summing_up_to_u64_max() -> Result<()> {
+        prove_balances([1 << 63, (1 << 63) - 1], Some(u64::MAX))
+    }
+
+    #[test]
+    #[should_panic(expected = "set twice with different values")]
+    fn test_balances_summing_up_over_u64_max_are_rejected() {
+        prove_balances([1 << 63, 1 << 63], None).unwrap();
+    }
+}
@@ -8,8 +8,10 @@ use plonky2::{
 
 use crate::{
     biguint::CircuitBuilderBiguint,
+    hash_tree_root::hash_tree_root,
+    hash_tree_root_poseidon::hash_tree_root_poseidon,
     targets_serialization::{ReadTargets, WriteTargets},
-    utils::{bits_to_biguint_target, ssz_num_from_bits},
+    utils::{bits_to_biguint_target, ssz_num_from_bits, ETH_SHA256_BIT_SIZE},
     validator_hash_tree_root::{hash_tree_root_validator_sha256, ValidatorShaTargets},
     validator_hash_tree_root_poseidon::{
         hash_tree_root_validator_poseidon, ValidatorPoseidonTargets,
@@ -118,11 +120,111 @@ pub fn validator_commitment_mapper<F: RichField + Extendable<D>, const D: usize>
     }
 }
 
+pub struct ValidatorsCommitmentTargets {
+    pub validators: Vec<ValidatorShaTargets>,
+    pub validator_is_zero: Vec<BoolTarget>,
+    pub sha256_hash_tree_root: [BoolTarget; 256],
+    pub poseidon_hash_tree_root: HashOutTarget,
+}
+
+impl ReadTargets for ValidatorsCommitmentTargets {
+    fn read_targets(data: &mut Buffer) -> IoResult<Self>
+    where
+        Self: Sized,
+    {
+        let validators_len = data.read_usize()?;
+
+        Ok(ValidatorsCommitmentTargets {
+            validators: (0..validators_len)
+                .map(|_| ValidatorShaTargets::read_targets(data).unwrap())
+                .collect(),
+            validator_is_zero: data.read_target_bool_vec()?,
+            sha256_hash_tree_root: data.read_target_bool_vec()?.try_into().unwrap(),
+            poseidon_hash_tree_root: data.read_target_hash()?,
+        })
+    }
+}
+
+impl WriteTargets for ValidatorsCommitmentTargets {
+    fn write_targets(&self) -> IoResult<Vec<u8>> {
+        let mut data = Vec::<u8>::new();
+
+        data.write_usize(self.validators.len())?;
+
+        for validator in &self.validators {
+            data.extend(validator.write_targets()?);
+        }
+
+        data.write_target_bool_vec(&self.validator_is_zero)?;
+        data.write_target_bool_vec(&self.sha256_hash_tree_root)?;
+        data.write_target_hash(&self.poseidon_hash_tree_root)?;
+
+        Ok(data)
+    }
+}
+
+/// Maps `validators_len` consecutive validators at once. The roots are the ones
+/// of the subtree of the validators, where the leaves of the validators marked
+/// as zero are zero, as in the balance verification circuit.
+pub fn validators_commitment_mapper<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    validators_len: usize,
+) -> ValidatorsCommitmentTargets {
+    assert!(validators_len.is_power_of_two() && validators_len >= 2);
+
+    let validators_commitments: Vec<ValidatorCommitmentTargets> = (0..validators_len)
+        .map(|_| validator_commitment_mapper(builder))
+        .collect();
+
+    let validator_is_zero: Vec<BoolTarget> = (0..validators_len)
+        .map(|_| builder.add_virtual_bool_target_safe())
+        .collect();
+
+    let sha256_hash_tree_root_targets = hash_tree_root(builder, validators_len);
+    let poseidon_hash_tree_root_targets = hash_tree_root_poseidon(builder, validators_len);
+
+    let zero = builder.zero();
+
+    for i in 0..validators_len {
+        let is_one = builder.not(validator_is_zero[i]);
+
+        for j in 0..ETH_SHA256_BIT_SIZE {
+            let bit = builder.and(validators_commitments[i].sha256_hash_tree_root[j], is_one);
+
+            builder.connect(
+                sha256_hash_tree_root_targets.leaves[i][j].target,
+                bit.target,
+            );
+        }
+
+        let elements = validators_commitments[i]
+            .poseidon_hash_tree_root
+            .elements
+            .map(|element| builder._if(validator_is_zero[i], zero, element));
+
+        builder.connect_hashes(
+            poseidon_hash_tree_root_targets.leaves[i],
+            HashOutTarget { elements },
+        );
+    }
+
+    ValidatorsCommitmentTargets {
+        validators: validators_commitments
+            .into_iter()
+            .map(|commitment| commitment.validator)
+            .collect(),
+        validator_is_zero,
+        sha256_hash_tree_root: sha256_hash_tree_root_targets.hash_tree_root,
+        poseidon_hash_tree_root: poseidon_hash_tree_root_targets.hash_tree_root,
+    }
+}
+
 #[cfg(test)]
 mod test {
     use anyhow::Result;
     use plonky2::{
-        field::goldilocks_field::GoldilocksField,
+        field::{goldilocks_field::GoldilocksField, types::Field},
+        hash::{hashing::hash_n_to_hash_no_pad, poseidon::PoseidonPermutation},
         iop::witness::{PartialWitness, WitnessWrite},
         plonk::{
             circuit_builder::CircuitBuilder, circuit_data::CircuitConfig,
@@ -131,7 +233,9 @@ mod test {
     };
 
     use crate::{
-        utils::ETH_SHA256_BIT_SIZE, validator_commitment_mapper::validator_commitment_mapper,
+        ssz::zero_hashes,
+        utils::ETH_SHA256_BIT_SIZE,
+        validator_commitment_mapper::{validator_commitment_mapper, validators_commitment_mapper},
     };
 
     #[test]
@@ -338,4 +442,57 @@ mod test {
 
         data.verify(proof)
     }
+
+    #[test]
+    fn test_validators_commitment_of_zero_validators() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = GoldilocksField;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+
+        let targets = validators_commitment_mapper(&mut builder, 2);
+
+        let mut pw = PartialWitness::new();
+
+        for (validator, is_zero) in targets
+            .validators
+            .iter()
+            .zip(targets.validator_is_zero.iter())
+        {
+            for i in 0..384 {
+                pw.set_bool_target(validator.pubkey[i], false);
+            }
+
+            for i in 0..ETH_SHA256_BIT_SIZE {
+                pw.set_bool_target(validator.withdrawal_credentials[i], false);
+                pw.set_bool_target(validator.effective_balance[i], false);
+                pw.set_bool_target(validator.slashed[i], false);
+                pw.set_bool_target(validator.activation_eligibility_epoch[i], false);
+                pw.set_bool_target(validator.activation_epoch[i], false);
+                pw.set_bool_target(validator.exit_epoch[i], false);
+                pw.set_bool_target(validator.withdrawable_epoch[i], false);
+            }
+
+            pw.set_bool_target(*is_zero, true);
+        }
+
+        // The root of two zero leaves
+        for (target, bit) in targets
+            .sha256_hash_tree_root
+            .iter()
+            .zip(zero_hashes(1)[1].iter())
+        {
+            pw.set_bool_target(*target, *bit);
+        }
+
+        let zero_poseidon_hash = hash_n_to_hash_no_pad::<F, PoseidonPermutation<F>>(&[F::ZERO; 8]);
+
+        pw.set_hash_target(targets.poseidon_hash_tree_root, zero_poseidon_hash);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw).unwrap();
+
+        data.verify(proof)
+    }
 }
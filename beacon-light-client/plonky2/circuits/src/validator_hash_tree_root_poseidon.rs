@@ -66,14 +66,14 @@ impl ValidatorPoseidonTargets {
         builder: &mut CircuitBuilder<F, D>,
     ) -> ValidatorPoseidonTargets {
         ValidatorPoseidonTargets {
-            pubkey: builder.add_virtual_biguint_target(12),
-            withdrawal_credentials: builder.add_virtual_biguint_target(8),
-            effective_balance: builder.add_virtual_biguint_target(2),
+            pubkey: builder.add_virtual_biguint_target_safe(12),
+            withdrawal_credentials: builder.add_virtual_biguint_target_safe(8),
+            effective_balance: builder.add_virtual_biguint_target_safe(2),
             slashed: builder.add_virtual_bool_target_safe(),
-            activation_eligibility_epoch: builder.add_virtual_biguint_target(2),
-            activation_epoch: builder.add_virtual_biguint_target(2),
-            exit_epoch: builder.add_virtual_biguint_target(2),
-            withdrawable_epoch: builder.add_virtual_biguint_target(2),
+            activation_eligibility_epoch: builder.add_virtual_biguint_target_safe(2),
+            activation_epoch: builder.add_virtual_biguint_target_safe(2),
+            exit_epoch: builder.add_virtual_biguint_target_safe(2),
+            withdrawable_epoch: builder.add_virtual_biguint_target_safe(2),
         }
     }
 }
@@ -0,0 +1,195 @@
+use plonky2::{
+    field::extension::Extendable, hash::hash_types::RichField, iop::target::BoolTarget,
+    plonk::circuit_builder::CircuitBuilder,
+};
+
+use crate::biguint::{BigUintTarget, CircuitBuilderBiguint};
+
+pub struct ValidatorLifecycleTargets {
+    pub is_pending: BoolTarget,
+    pub is_active: BoolTarget,
+    pub is_exited: BoolTarget,
+    pub is_withdrawable: BoolTarget,
+}
+
+// Pending covers both validators waiting to become eligible and validators
+// waiting in the activation queue.
+pub fn validator_lifecycle<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    activation_eligibility_epoch: &BigUintTarget,
+    activation_epoch: &BigUintTarget,
+    current_epoch: &BigUintTarget,
+    exit_epoch: &BigUintTarget,
+    withdrawable_epoch: &BigUintTarget,
+) -> ValidatorLifecycleTargets {
+    let is_not_eligible = builder.lt_biguint(current_epoch, activation_eligibility_epoch);
+    let is_not_activated = builder.lt_biguint(current_epoch, activation_epoch);
+    let is_pending = builder.or(is_not_eligible, is_not_activated);
+    let is_not_pending = builder.not(is_pending);
+
+    let is_not_exited = builder.lt_biguint(current_epoch, exit_epoch);
+    let is_active = builder.and(is_not_pending, is_not_exited);
+
+    let has_exited = builder.not(is_not_exited);
+    let has_exited = builder.and(is_not_pending, has_exited);
+
+    let is_not_withdrawable = builder.lt_biguint(current_epoch, withdrawable_epoch);
+    let is_exited = builder.and(has_exited, is_not_withdrawable);
+
+    let has_reached_withdrawable_epoch = builder.not(is_not_withdrawable);
+    let is_withdrawable = builder.and(has_exited, has_reached_withdrawable_epoch);
+
+    ValidatorLifecycleTargets {
+        is_pending,
+        is_active,
+        is_exited,
+        is_withdrawable,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use num::{BigUint, FromPrimitive};
+    use plonky2::{
+        field::goldilocks_field::GoldilocksField,
+        iop::witness::PartialWitness,
+        plonk::{
+            circuit_builder::CircuitBuilder, circuit_data::CircuitConfig,
+            config::PoseidonGoldilocksConfig,
+        },
+    };
+
+    use crate::{
+        biguint::{CircuitBuilderBiguint, WitnessBigUint},
+        validator_lifecycle::validator_lifecycle,
+    };
+
+    fn test_helper(
+        activation_eligibility_epoch_value: u64,
+        activation_epoch_value: u64,
+        current_epoch_value: u64,
+        exit_epoch_value: u64,
+        withdrawable_epoch_value: u64,
+        expected: [bool; 4],
+    ) -> std::result::Result<(), anyhow::Error> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = GoldilocksField;
+
+        let mut pw = PartialWitness::new();
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+
+        let activation_eligibility_epoch = builder.add_virtual_biguint_target(2);
+        let activation_epoch = builder.add_virtual_biguint_target(2);
+        let current_epoch = builder.add_virtual_biguint_target(2);
+        let exit_epoch = builder.add_virtual_biguint_target(2);
+        let withdrawable_epoch = builder.add_virtual_biguint_target(2);
+
+        let result = validator_lifecycle(
+            &mut builder,
+            &activation_eligibility_epoch,
+            &activation_epoch,
+            &current_epoch,
+            &exit_epoch,
+            &withdrawable_epoch,
+        );
+
+        for (target, value) in [
+            (
+                &activation_eligibility_epoch,
+                activation_eligibility_epoch_value,
+            ),
+            (&activation_epoch, activation_epoch_value),
+            (&current_epoch, current_epoch_value),
+            (&exit_epoch, exit_epoch_value),
+            (&withdrawable_epoch, withdrawable_epoch_value),
+        ] {
+            pw.set_biguint_target(target, &BigUint::from_u64(value).unwrap());
+        }
+
+        for (flag, is_positive) in [
+            result.is_pending,
+            result.is_active,
+            result.is_exited,
+            result.is_withdrawable,
+        ]
+        .iter()
+        .zip(expected)
+        {
+            if is_positive {
+                builder.assert_one(flag.target);
+            } else {
+                builder.assert_zero(flag.target);
+            }
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw).unwrap();
+
+        data.verify(proof)
+    }
+
+    const FAR_FUTURE_EPOCH: u64 = u64::MAX;
+
+    #[test]
+    fn test_validator_lifecycle_not_yet_eligible() -> Result<()> {
+        test_helper(
+            6953402,
+            FAR_FUTURE_EPOCH,
+            6953401,
+            FAR_FUTURE_EPOCH,
+            FAR_FUTURE_EPOCH,
+            [true, false, false, false],
+        )
+    }
+
+    #[test]
+    fn test_validator_lifecycle_in_activation_queue() -> Result<()> {
+        test_helper(
+            6953400,
+            6953402,
+            6953401,
+            FAR_FUTURE_EPOCH,
+            FAR_FUTURE_EPOCH,
+            [true, false, false, false],
+        )
+    }
+
+    #[test]
+    fn test_validator_lifecycle_active() -> Result<()> {
+        test_helper(
+            6953399,
+            6953400,
+            6953401,
+            FAR_FUTURE_EPOCH,
+            FAR_FUTURE_EPOCH,
+            [false, true, false, false],
+        )
+    }
+
+    #[test]
+    fn test_validator_lifecycle_exited() -> Result<()> {
+        test_helper(
+            6953399,
+            6953400,
+            6953401,
+            6953401,
+            6953657,
+            [false, false, true, false],
+        )
+    }
+
+    #[test]
+    fn test_validator_lifecycle_withdrawable() -> Result<()> {
+        test_helper(
+            6953399,
+            6953400,
+            6953657,
+            6953401,
+            6953657,
+            [false, false, false, true],
+        )
+    }
+}
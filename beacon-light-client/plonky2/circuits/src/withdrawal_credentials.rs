@@ -0,0 +1,195 @@
+use plonky2::{
+    field::{extension::Extendable, types::Field},
+    hash::hash_types::RichField,
+    iop::target::BoolTarget,
+    plonk::circuit_builder::CircuitBuilder,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{biguint::BigUintTarget, utils::biguint_is_equal};
+
+// The 20-byte execution address is in the last 5 limbs of the credentials
+const EXECUTION_ADDRESS_LIMBS: usize = 5;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WithdrawalCredentialsMatching {
+    // All 32 bytes of the credentials are compared
+    FullCredentials,
+    // Only the execution address is compared. The credentials must start with one of
+    // the allowed prefix bytes followed by 11 zero bytes.
+    #[serde(rename_all = "camelCase")]
+    ExecutionAddress {
+        allowed_prefixes: Vec<u8>,
+    },
+}
+
+impl WithdrawalCredentialsMatching {
+    /// Number of limbs of each entry in the withdrawal credentials set.
+    pub fn credentials_limbs(&self) -> usize {
+        match self {
+            WithdrawalCredentialsMatching::FullCredentials => 8,
+            WithdrawalCredentialsMatching::ExecutionAddress { .. } => EXECUTION_ADDRESS_LIMBS,
+        }
+    }
+}
+
+/// `validator_credentials` is the 8-limb big-endian number of the 32 credential bytes
+/// and `credentials` an entry of the set, sized by `matching.credentials_limbs()`.
+pub fn is_matching_withdrawal_credentials<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    matching: &WithdrawalCredentialsMatching,
+    validator_credentials: &BigUintTarget,
+    credentials: &BigUintTarget,
+) -> BoolTarget {
+    match matching {
+        WithdrawalCredentialsMatching::FullCredentials => {
+            biguint_is_equal(builder, validator_credentials, credentials)
+        }
+        WithdrawalCredentialsMatching::ExecutionAddress { allowed_prefixes } => {
+            let execution_address = BigUintTarget {
+                limbs: validator_credentials.limbs[..EXECUTION_ADDRESS_LIMBS].to_vec(),
+            };
+
+            let is_equal_address = biguint_is_equal(builder, &execution_address, credentials);
+
+            let zero = builder.zero();
+            let mut is_zero_padding = builder._true();
+
+            for limb in &validator_credentials.limbs[EXECUTION_ADDRESS_LIMBS..7] {
+                let is_zero_limb = builder.is_equal(limb.0, zero);
+                is_zero_padding = builder.and(is_zero_padding, is_zero_limb);
+            }
+
+            // The most significant limb holds the prefix byte and 3 zero bytes
+            let mut is_allowed_prefix = builder._false();
+
+            for prefix in allowed_prefixes {
+                let expected_limb = builder.constant(F::from_canonical_u32((*prefix as u32) << 24));
+                let is_prefix = builder.is_equal(validator_credentials.limbs[7].0, expected_limb);
+                is_allowed_prefix = builder.or(is_allowed_prefix, is_prefix);
+            }
+
+            let is_valid_credentials = builder.and(is_zero_padding, is_allowed_prefix);
+
+            builder.and(is_equal_address, is_valid_credentials)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use num::{BigUint, Num};
+    use plonky2::{
+        field::{goldilocks_field::GoldilocksField, types::Field},
+        iop::witness::PartialWitness,
+        plonk::{
+            circuit_builder::CircuitBuilder, circuit_data::CircuitConfig,
+            config::PoseidonGoldilocksConfig,
+        },
+    };
+
+    use crate::{
+        biguint::{CircuitBuilderBiguint, WitnessBigUint},
+        withdrawal_credentials::{
+            is_matching_withdrawal_credentials, WithdrawalCredentialsMatching,
+        },
+    };
+
+    const ADDRESS: &str = "15f4b914a0ccd14333d850ff311d6dafbfbaa32b";
+
+    fn is_matching(matching: WithdrawalCredentialsMatching, validator_credentials: &str) -> bool {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = GoldilocksField;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+
+        let validator_credentials_target = builder.add_virtual_biguint_target(8);
+        let credentials_target = builder.add_virtual_biguint_target(matching.credentials_limbs());
+
+        let is_matching = is_matching_withdrawal_credentials(
+            &mut builder,
+            &matching,
+            &validator_credentials_target,
+            &credentials_target,
+        );
+
+        builder.register_public_input(is_matching.target);
+
+        let credentials = match matching {
+            WithdrawalCredentialsMatching::FullCredentials => {
+                format!("010000000000000000000000{}", ADDRESS)
+            }
+            WithdrawalCredentialsMatching::ExecutionAddress { .. } => ADDRESS.to_string(),
+        };
+
+        let mut pw = PartialWitness::new();
+
+        pw.set_biguint_target(
+            &validator_credentials_target,
+            &BigUint::from_str_radix(validator_credentials, 16).unwrap(),
+        );
+        pw.set_biguint_target(
+            &credentials_target,
+            &BigUint::from_str_radix(&credentials, 16).unwrap(),
+        );
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw).unwrap();
+
+        proof.public_inputs[0] == GoldilocksField::ONE
+    }
+
+    fn execution_address_matching() -> WithdrawalCredentialsMatching {
+        WithdrawalCredentialsMatching::ExecutionAddress {
+            allowed_prefixes: vec![0x01, 0x02],
+        }
+    }
+
+    #[test]
+    fn test_execution_address_matches_both_prefixes() {
+        assert!(is_matching(
+            execution_address_matching(),
+            &format!("010000000000000000000000{}", ADDRESS)
+        ));
+        assert!(is_matching(
+            execution_address_matching(),
+            &format!("020000000000000000000000{}", ADDRESS)
+        ));
+    }
+
+    #[test]
+    fn test_execution_address_rejects_other_prefixes() {
+        assert!(!is_matching(
+            execution_address_matching(),
+            &format!("000000000000000000000000{}", ADDRESS)
+        ));
+        assert!(!is_matching(
+            WithdrawalCredentialsMatching::ExecutionAddress {
+                allowed_prefixes: vec![0x01],
+            },
+            &format!("020000000000000000000000{}", ADDRESS)
+        ));
+    }
+
+    #[test]
+    fn test_execution_address_rejects_non_zero_padding() {
+        assert!(!is_matching(
+            execution_address_matching(),
+            &format!("010000000000000000000001{}", ADDRESS)
+        ));
+    }
+
+    #[test]
+    fn test_full_credentials_reject_compounding_credentials() {
+        assert!(is_matching(
+            WithdrawalCredentialsMatching::FullCredentials,
+            &format!("010000000000000000000000{}", ADDRESS)
+        ));
+        assert!(!is_matching(
+            WithdrawalCredentialsMatching::FullCredentials,
+            &format!("020000000000000000000000{}", ADDRESS)
+        ));
+    }
+}
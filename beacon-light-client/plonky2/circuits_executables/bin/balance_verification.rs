@@ -11,10 +11,12 @@ use circuits::{
 };
 use circuits_executables::{
     crud::{
-        fetch_proofs, fetch_validator_balance_input, load_circuit_data, read_from_file,
-        save_balance_proof, BalanceProof,
+        fetch_balance_verification_epoch, fetch_children_proofs, fetch_validator_balance_input,
+        load_circuit_data, read_from_file, save_balance_proof, save_proven_validator_balance_input,
+        BalanceProof,
     },
     provers::{handle_balance_inner_level_proof, SetPWValues},
+    queue_worker::QueueWorker,
     validator_commitment_constants::get_validator_commitment_constants,
 };
 use futures_lite::future;
@@ -150,83 +152,46 @@ async fn async_main() -> Result<()> {
 
     println!("level {}", level);
 
-    let start: Instant = Instant::now();
+    let worker = QueueWorker::new(queue, time_to_run, stop_after, lease_for);
 
     process_queue(
         &mut con,
-        &queue,
+        &worker,
         &circuit_data,
         inner_circuit_data.as_ref(),
         &targets,
         level,
-        start,
-        time_to_run,
-        stop_after,
-        lease_for,
     )
     .await
 }
 
 async fn process_queue(
     con: &mut redis::aio::Connection,
-    queue: &WorkQueue,
+    worker: &QueueWorker,
     circuit_data: &CircuitData<GoldilocksField, PoseidonGoldilocksConfig, 2>,
     inner_circuit_data: Option<&CircuitData<GoldilocksField, PoseidonGoldilocksConfig, 2>>,
     targets: &Targets,
     level: usize,
-    start: Instant,
-    time_to_run: Option<Duration>,
-    stop_after: u64,
-    lease_for: u64,
 ) -> Result<()> {
-    while time_to_run.is_none() || start.elapsed() < time_to_run.unwrap() {
-        let job = match queue
-            .lease(
-                con,
-                Some(Duration::from_secs(stop_after)),
-                Duration::from_secs(lease_for),
-            )
-            .await?
-        {
-            Some(job) => job,
-            None => {
-                println!("No jobs left in queue");
-
-                return Ok(());
-            }
-        };
-
-        if job.data.is_empty() {
-            println!("Skipping empty data job");
-            queue.complete(con, &job).await?;
-
-            continue;
-        }
-
-        println!("Processing job data: {:?}", job.data);
-
+    while let Some(job) = worker.next_job(con).await? {
         match targets {
             Targets::FirstLevel(targets) => {
-                match process_first_level_job(
+                if let Err(err) = process_first_level_job(
                     con,
-                    queue,
+                    worker.queue(),
                     job,
                     circuit_data,
                     targets.as_ref().unwrap(),
                 )
                 .await
                 {
-                    Err(_err) => {
-                        println!("Error processing first level job {:?}", _err);
-                        continue;
-                    }
-                    Ok(_) => {}
-                };
+                    println!("Error processing first level job {:?}", err);
+                }
             }
             Targets::InnerLevel(inner_circuit_targets) => {
-                match process_inner_level_job(
+                if let Err(err) = process_inner_level_job(
                     con,
-                    queue,
+                    worker.queue(),
                     job,
                     circuit_data,
                     inner_circuit_data.unwrap(),
@@ -235,9 +200,8 @@ async fn process_queue(
                 )
                 .await
                 {
-                    Err(_err) => continue,
-                    Ok(_) => {}
-                };
+                    println!("Error processing inner level job {:?}", err);
+                }
             }
         }
     }
@@ -276,6 +240,9 @@ async fn process_first_level_job(
             return Err(err);
         }
         Ok(_) => {
+            save_proven_validator_balance_input(con, balance_input_index, &validator_balance_input)
+                .await?;
+
             queue.complete(con, &job).await?;
         }
     }
@@ -304,20 +271,27 @@ async fn process_inner_level_job(
 
     println!("Got indexes: {:?}", proof_indexes);
 
-    match fetch_proofs::<BalanceProof>(con, &proof_indexes).await {
+    match fetch_children_proofs::<BalanceProof>(con, &proof_indexes).await {
         Err(err) => {
             print!("Error: {}", err);
             return Err(err);
         }
         Ok(proofs) => {
+            let current_epoch =
+                fetch_balance_verification_epoch(con)
+                    .await?
+                    .ok_or(anyhow::anyhow!(
+                        "The epoch of the balance verification is not set"
+                    ))?;
+
             let start = Instant::now();
 
             let proof = handle_balance_inner_level_proof(
-                proofs.0,
-                proofs.1,
+                proofs,
                 &inner_circuit_data,
                 &inner_circuit_targets.as_ref().unwrap(),
                 &circuit_data,
+                &current_epoch,
             )?;
 
             match save_balance_proof(con, proof, level, proof_indexes[1]).await {
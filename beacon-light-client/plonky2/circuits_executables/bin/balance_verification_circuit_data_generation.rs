@@ -2,11 +2,16 @@ use std::{fs, marker::PhantomData};
 
 use anyhow::Result;
 use circuits::{
-    build_balance_inner_level_circuit::build_inner_level_circuit,
+    build_balance_inner_level_circuit::build_inner_level_circuit_with_arity,
     build_validator_balance_circuit::build_validator_balance_circuit,
     generator_serializer::{DendrETHGateSerializer, DendrETHGeneratorSerializer}, targets_serialization::WriteTargets,
+    network_preset::NetworkPreset,
+    withdrawal_credentials::WithdrawalCredentialsMatching,
 };
 
+use circuits_executables::circuits_manifest::{
+    BalanceVerificationManifest, CircuitsManifest, WithdrawalCredentialsManifest,
+};
 use clap::{App, Arg};
 use futures_lite::future;
 
@@ -43,6 +48,93 @@ pub async fn async_main() -> Result<()> {
                     }
                 }),
         )
+        .arg(
+            Arg::with_name("withdrawal_credentials_len")
+                .long("withdrawal-credentials")
+                .value_name("WITHDRAWAL_CREDENTIALS")
+                .help("Sets the number of withdrawal credentials in the set")
+                .takes_value(true)
+                .default_value("1")
+                .validator(|x| {
+                    if x.parse::<usize>().map_or(false, |x| x > 0) {
+                        Ok(())
+                    } else {
+                        Err(String::from(
+                            "The number of withdrawal credentials must be a positive number",
+                        ))
+                    }
+                }),
+        )
+        .arg(
+            Arg::with_name("exclude_slashed")
+                .long("exclude-slashed")
+                .help("Leaves slashed validators out of the balance sum")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("match_execution_address")
+                .long("match-execution-address")
+                .help("Compares only the execution address of the withdrawal credentials")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("allowed_prefixes")
+                .long("allowed-prefixes")
+                .value_name("PREFIXES")
+                .help("Sets the comma-separated credential prefixes allowed when matching by execution address")
+                .takes_value(true)
+                .default_value("01,02")
+                .validator(|x| {
+                    if x.split(',').all(|prefix| u8::from_str_radix(prefix, 16).is_ok()) {
+                        Ok(())
+                    } else {
+                        Err(String::from("The prefixes must be hex bytes"))
+                    }
+                }),
+        )
+        .arg(
+            Arg::with_name("validators_per_circuit")
+                .long("validators-per-circuit")
+                .value_name("VALIDATORS")
+                .help("Sets the number of validators proven by a first level circuit")
+                .takes_value(true)
+                .default_value("8")
+                .validator(|x| {
+                    if x.parse::<usize>().map_or(false, |x| x >= 8 && x.is_power_of_two()) {
+                        Ok(())
+                    } else {
+                        Err(String::from(
+                            "The number of validators must be a power of two and at least 8",
+                        ))
+                    }
+                }),
+        )
+        .arg(
+            Arg::with_name("tree_depth")
+                .long("tree-depth")
+                .value_name("DEPTH")
+                .help("Sets the depth of the validators tree, defaults to the depth of the validator registry")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("arity")
+                .long("arity")
+                .value_name("ARITY")
+                .help("Sets the number of proofs each inner level circuit aggregates")
+                .takes_value(true)
+                .possible_values(["2", "4", "8"])
+                .default_value("2"),
+        )
+        .arg(
+            Arg::with_name("preset")
+                .long("preset")
+                .value_name("PRESET")
+                .help(
+                    "Sets the network preset: mainnet, minimal, gnosis or a path to a YAML preset",
+                )
+                .takes_value(true)
+                .default_value("mainnet"),
+        )
         .get_matches();
 
     let level = match matches.value_of("circuit_level").unwrap() {
@@ -50,8 +142,67 @@ pub async fn async_main() -> Result<()> {
         x => Some(x.parse::<usize>().unwrap()),
     };
 
+    let withdrawal_credentials_len = matches
+        .value_of("withdrawal_credentials_len")
+        .unwrap()
+        .parse::<usize>()
+        .unwrap();
+
+    let exclude_slashed = matches.is_present("exclude_slashed");
+
+    let matching = if matches.is_present("match_execution_address") {
+        WithdrawalCredentialsMatching::ExecutionAddress {
+            allowed_prefixes: matches
+                .value_of("allowed_prefixes")
+                .unwrap()
+                .split(',')
+                .map(|prefix| u8::from_str_radix(prefix, 16).unwrap())
+                .collect(),
+        }
+    } else {
+        WithdrawalCredentialsMatching::FullCredentials
+    };
+
+    let arity = matches.value_of("arity").unwrap().parse::<usize>().unwrap();
+
+    let preset = NetworkPreset::from_name(matches.value_of("preset").unwrap())?;
+
+    let validators_per_circuit = matches
+        .value_of("validators_per_circuit")
+        .unwrap()
+        .parse::<usize>()
+        .unwrap();
+
+    let tree_depth = match matches.value_of("tree_depth") {
+        Some(x) => x.parse::<usize>()?,
+        None => preset.validator_registry_depth(),
+    };
+
+    if tree_depth > preset.validator_registry_depth() || (1 << tree_depth) < validators_per_circuit
+    {
+        return Err(anyhow::anyhow!(
+            "The tree depth must be between log2(validators per circuit) and the depth of the validator registry"
+        ));
+    }
+
+    let balance_manifest = BalanceVerificationManifest::new(
+        validators_per_circuit,
+        tree_depth,
+        arity,
+        WithdrawalCredentialsManifest {
+            len: withdrawal_credentials_len,
+            matching: matching.clone(),
+        },
+        exclude_slashed,
+    );
+
     let (validators_balance_verification_targets, first_level_data) =
-        build_validator_balance_circuit(8);
+        build_validator_balance_circuit(
+            validators_per_circuit,
+            withdrawal_credentials_len,
+            exclude_slashed,
+            &matching,
+        );
 
     let gate_serializer = DendrETHGateSerializer;
 
@@ -74,8 +225,8 @@ pub async fn async_main() -> Result<()> {
 
     let mut prev_circuit_data = first_level_data;
 
-    for i in 1..38 {
-        let (targets, data) = build_inner_level_circuit(&prev_circuit_data);
+    for (i, &level_arity) in (1..).zip(balance_manifest.inner_level_arities.iter()) {
+        let (targets, data) = build_inner_level_circuit_with_arity(&prev_circuit_data, level_arity);
 
         if level == Some(i) || level == None {
             let circuit_bytes = data
@@ -96,6 +247,13 @@ pub async fn async_main() -> Result<()> {
         prev_circuit_data = data;
     }
 
+    // Only a full generation matches the manifest
+    if level == None {
+        let mut manifest = CircuitsManifest::load_or_default()?;
+        manifest.balance_verification = Some(balance_manifest);
+        manifest.save()?;
+    }
+
     Ok(())
 }
 
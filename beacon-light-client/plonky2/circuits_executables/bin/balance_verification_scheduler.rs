@@ -0,0 +1,174 @@
+use std::println;
+
+use anyhow::Result;
+use circuits_executables::{
+    balance_verification_scheduler::BalanceVerificationTree,
+    circuits_manifest::CircuitsManifest,
+    crud::{
+        fetch_balance_proof_if_exists, fetch_proven_validator_balance_input,
+        fetch_validator_balance_input, fetch_validator_balance_input_if_exists,
+        invalidate_balance_proof, save_balance_verification_epoch, BalanceProof,
+    },
+    validator::VALIDATOR_REGISTRY_LIMIT,
+    validator_commitment_constants::get_validator_commitment_constants,
+};
+use clap::{App, Arg};
+use futures_lite::future;
+use num::BigUint;
+use redis::aio::Connection;
+use redis_work_queue::{Item, KeyPrefix, WorkQueue};
+
+fn main() -> Result<()> {
+    future::block_on(async_main())
+}
+
+async fn async_main() -> Result<()> {
+    let matches = App::new("")
+        .arg(
+            Arg::with_name("redis_connection")
+                .short('r')
+                .long("redis")
+                .value_name("Redis Connection")
+                .help("Sets a custom Redis connection")
+                .takes_value(true)
+                .default_value("redis://127.0.0.1:6379/"),
+        )
+        .arg(
+            Arg::with_name("validators")
+                .long("validators")
+                .value_name("COUNT")
+                .help("Sets the number of validators with first level inputs for the new epoch")
+                .takes_value(true)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("zero_proofs")
+                .long("zero-proofs")
+                .help("Schedules the zero proofs even if they are still valid")
+                .takes_value(false),
+        )
+        .get_matches();
+
+    let validators = matches.value_of("validators").unwrap().parse::<usize>()?;
+
+    let manifest = CircuitsManifest::load()?;
+    let balance_verification_manifest = manifest.balance_verification()?;
+
+    let mut tree = BalanceVerificationTree::new(balance_verification_manifest);
+
+    let client = redis::Client::open(matches.value_of("redis_connection").unwrap())?;
+    let mut con = client.get_async_connection().await?;
+
+    let mut current_epoch: Option<BigUint> = None;
+    let mut changed_inputs = 0;
+
+    for index in (0..validators).step_by(balance_verification_manifest.validators_per_circuit) {
+        let input = fetch_validator_balance_input(&mut con, index).await?;
+
+        match &current_epoch {
+            None => current_epoch = Some(input.current_epoch.clone()),
+            Some(epoch) if *epoch != input.current_epoch => {
+                return Err(anyhow::anyhow!(
+                    "The input at {} is for epoch {} instead of {}",
+                    index,
+                    input.current_epoch,
+                    epoch
+                ));
+            }
+            Some(_) => {}
+        }
+
+        let is_unchanged = match fetch_proven_validator_balance_input(&mut con, index).await? {
+            Some(previous) => {
+                input.is_unchanged_from(&previous)
+                    && is_still_valid(
+                        fetch_balance_proof_if_exists(&mut con, 0, index).await?,
+                        &input.current_epoch,
+                    )
+            }
+            None => false,
+        };
+
+        if !is_unchanged {
+            tree.mark_changed_validator(index)?;
+            changed_inputs += 1;
+        }
+    }
+
+    let current_epoch = current_epoch.ok_or(anyhow::anyhow!("There are no validators"))?;
+
+    if matches.is_present("zero_proofs")
+        || !is_still_valid(
+            fetch_balance_proof_if_exists(&mut con, 0, VALIDATOR_REGISTRY_LIMIT).await?,
+            &current_epoch,
+        )
+    {
+        if let Some(input) =
+            fetch_validator_balance_input_if_exists(&mut con, VALIDATOR_REGISTRY_LIMIT).await?
+        {
+            if input.current_epoch > current_epoch {
+                return Err(anyhow::anyhow!(
+                    "The zero input is for a later epoch than {}",
+                    current_epoch
+                ));
+            }
+        }
+
+        tree.mark_zero_nodes();
+    }
+
+    tree.mark_root();
+
+    save_balance_verification_epoch(&mut con, &current_epoch).await?;
+
+    let jobs = tree.take_jobs();
+
+    // Every proof is invalidated before any job is queued, so no job can use the
+    // proof of a node that is yet to be proven again
+    for job in &jobs {
+        let (depth, index) = job.proof_key();
+
+        invalidate_balance_proof(&mut con, depth, index).await?;
+    }
+
+    for job in &jobs {
+        let (level, _) = job.proof_key();
+
+        add_job(&mut con, level, job.to_bytes()).await?;
+    }
+
+    println!(
+        "Scheduled {} jobs for epoch {}, {} first level inputs changed",
+        jobs.len(),
+        current_epoch,
+        changed_inputs
+    );
+
+    Ok(())
+}
+
+/// Whether the proof can be carried forward to `current_epoch`.
+fn is_still_valid(proof: Option<BalanceProof>, current_epoch: &BigUint) -> bool {
+    match proof {
+        Some(proof) => {
+            !proof.needs_change
+                && proof.current_epoch <= *current_epoch
+                && *current_epoch < proof.range_valid_until_epoch
+        }
+        None => false,
+    }
+}
+
+async fn add_job(con: &mut Connection, level: usize, data: Vec<u8>) -> Result<()> {
+    let queue = WorkQueue::new(KeyPrefix::new(format!(
+        "{}:{}",
+        get_validator_commitment_constants().balance_verification_queue,
+        level
+    )));
+
+    queue
+        .add_item(con, &Item::new(data.into_boxed_slice()))
+        .await?;
+
+    Ok(())
+}
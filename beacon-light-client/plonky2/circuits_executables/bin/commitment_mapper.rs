@@ -1,14 +1,20 @@
 use anyhow::Result;
 use circuits::{
+    build_commitment_mapper_cyclic_circuit::CommitmentMapperCyclicCircuitTargets,
     build_commitment_mapper_inner_level_circuit::CommitmentMapperInnerCircuitTargets,
-    targets_serialization::ReadTargets, validator_commitment_mapper::ValidatorCommitmentTargets,
+    targets_serialization::ReadTargets,
+    validator_commitment_mapper::{ValidatorCommitmentTargets, ValidatorsCommitmentTargets},
 };
 use circuits_executables::{
+    circuits_manifest::{CircuitsManifest, CommitmentMapperManifest},
     crud::{
-        fetch_proofs, fetch_validator, load_circuit_data, read_from_file, save_validator_proof,
-        ValidatorProof,
+        fetch_proofs, fetch_validator, fetch_validators, load_circuit_data, read_from_file,
+        save_validator_proof, ValidatorProof, COMMITMENT_MAPPER_CYCLIC_CIRCUIT,
+    },
+    provers::{
+        handle_commitment_mapper_cyclic_proof, handle_commitment_mapper_inner_level_proof,
+        SetPWValues,
     },
-    provers::{handle_commitment_mapper_inner_level_proof, SetPWValues},
     validator::VALIDATOR_REGISTRY_LIMIT,
     validator_commitment_constants,
 };
@@ -20,6 +26,7 @@ use plonky2::{
     plonk::{circuit_data::CircuitData, config::PoseidonGoldilocksConfig},
     util::serialization::Buffer,
 };
+use redis::aio::Connection;
 use redis_work_queue::{KeyPrefix, WorkQueue};
 use std::{format, print, println, thread, time::Duration};
 
@@ -30,6 +37,11 @@ use jemallocator::Jemalloc;
 #[global_allocator]
 static GLOBAL: Jemalloc = Jemalloc;
 
+enum FirstLevelTargets {
+    Validator(ValidatorCommitmentTargets),
+    Validators(ValidatorsCommitmentTargets),
+}
+
 fn main() -> Result<()> {
     future::block_on(async_main())
 }
@@ -63,6 +75,9 @@ async fn async_main() -> Result<()> {
 
     let redis_connection = matches.value_of("redis_connection").unwrap();
 
+    let manifest = CircuitsManifest::load()?;
+    let commitment_mapper_manifest = manifest.commitment_mapper()?;
+
     let client = redis::Client::open(redis_connection)?;
     let mut con = client.get_async_connection().await?;
 
@@ -71,19 +86,28 @@ async fn async_main() -> Result<()> {
     ));
 
     let first_level_circuit_data = load_circuit_data("commitment_mapper_0")?;
-    let validator_commitment = get_first_level_targets()?;
+    let first_level_targets = get_first_level_targets(commitment_mapper_manifest)?;
 
     let mut inner_circuits: Vec<(
         CommitmentMapperInnerCircuitTargets,
         CircuitData<GoldilocksField, PoseidonGoldilocksConfig, 2>,
     )> = Vec::new();
 
-    for i in 1..41 {
-        inner_circuits.push((
-            get_inner_targets(i)?,
-            load_circuit_data(&format!("commitment_mapper_{}", i))?,
-        ));
-    }
+    let cyclic_circuit = if commitment_mapper_manifest.cyclic {
+        Some((
+            get_cyclic_targets()?,
+            load_circuit_data(COMMITMENT_MAPPER_CYCLIC_CIRCUIT)?,
+        ))
+    } else {
+        for i in 1..=commitment_mapper_manifest.levels() {
+            inner_circuits.push((
+                get_inner_targets(i)?,
+                load_circuit_data(&format!("commitment_mapper_{}", i))?,
+            ));
+        }
+
+        None
+    };
 
     let stop_after = matches
         .value_of("stop_after")
@@ -117,22 +141,16 @@ async fn async_main() -> Result<()> {
         if job.data.len() == 8 {
             let validator_index = u64::from_be_bytes(job.data[0..8].try_into().unwrap()) as usize;
 
-            match fetch_validator(&mut con, validator_index).await {
+            match fetch_first_level_witness(&mut con, &first_level_targets, validator_index).await {
                 Err(err) => {
                     print!("Error: {}", err);
                     thread::sleep(Duration::from_secs(10));
                     continue;
                 }
-                Ok(validator) => {
-                    let mut pw = PartialWitness::new();
-
-                    validator_commitment
-                        .validator
-                        .set_pw_values(&mut pw, &validator);
-
+                Ok((leaf_index, pw)) => {
                     let proof = first_level_circuit_data.prove(pw)?;
 
-                    match save_validator_proof(&mut con, proof, 0, validator_index).await {
+                    match save_validator_proof(&mut con, proof, 0, leaf_index).await {
                         Err(err) => {
                             print!("Error: {}", err);
                             thread::sleep(Duration::from_secs(10));
@@ -159,20 +177,37 @@ async fn async_main() -> Result<()> {
                     continue;
                 }
                 Ok(proofs) => {
-                    let inner_circuit_data = if proof_indexes[0] > 0 {
-                        &inner_circuits[proof_indexes[0] - 1].1
+                    // A leaf with several validators is zeroed by its own circuit
+                    let is_zero = commitment_mapper_manifest.validators_per_leaf == 1
+                        && proof_indexes[2] == VALIDATOR_REGISTRY_LIMIT
+                        && proof_indexes[0] == 0;
+
+                    let proof = if let Some((cyclic_targets, cyclic_data)) = &cyclic_circuit {
+                        handle_commitment_mapper_cyclic_proof(
+                            proofs.0,
+                            proofs.1,
+                            &first_level_circuit_data,
+                            cyclic_targets,
+                            cyclic_data,
+                            proof_indexes[0] == 0,
+                            is_zero,
+                        )?
                     } else {
-                        &first_level_circuit_data
-                    };
+                        let inner_circuit_data = if proof_indexes[0] > 0 {
+                            &inner_circuits[proof_indexes[0] - 1].1
+                        } else {
+                            &first_level_circuit_data
+                        };
 
-                    let proof = handle_commitment_mapper_inner_level_proof(
-                        proofs.0,
-                        proofs.1,
-                        inner_circuit_data,
-                        &inner_circuits[proof_indexes[0]].0,
-                        &inner_circuits[proof_indexes[0]].1,
-                        proof_indexes[2] == VALIDATOR_REGISTRY_LIMIT && proof_indexes[0] == 0,
-                    )?;
+                        handle_commitment_mapper_inner_level_proof(
+                            proofs.0,
+                            proofs.1,
+                            inner_circuit_data,
+                            &inner_circuits[proof_indexes[0]].0,
+                            &inner_circuits[proof_indexes[0]].1,
+                            is_zero,
+                        )?
+                    };
 
                     match save_validator_proof(
                         &mut con,
@@ -209,9 +244,59 @@ fn get_inner_targets(i: usize) -> Result<CommitmentMapperInnerCircuitTargets> {
     Ok(CommitmentMapperInnerCircuitTargets::read_targets(&mut target_buffer).unwrap())
 }
 
-fn get_first_level_targets() -> Result<ValidatorCommitmentTargets> {
+fn get_cyclic_targets() -> Result<CommitmentMapperCyclicCircuitTargets> {
+    let target_bytes = read_from_file(&format!(
+        "{}.plonky2_targets",
+        COMMITMENT_MAPPER_CYCLIC_CIRCUIT
+    ))?;
+    let mut target_buffer = Buffer::new(&target_bytes);
+
+    Ok(CommitmentMapperCyclicCircuitTargets::read_targets(&mut target_buffer).unwrap())
+}
+
+fn get_first_level_targets(
+    commitment_mapper_manifest: &CommitmentMapperManifest,
+) -> Result<FirstLevelTargets> {
     let target_bytes = read_from_file(&format!("commitment_mapper_{}.plonky2_targets", 0))?;
     let mut target_buffer = Buffer::new(&target_bytes);
 
-    Ok(ValidatorCommitmentTargets::read_targets(&mut target_buffer).unwrap())
+    if commitment_mapper_manifest.validators_per_leaf == 1 {
+        Ok(FirstLevelTargets::Validator(
+            ValidatorCommitmentTargets::read_targets(&mut target_buffer).unwrap(),
+        ))
+    } else {
+        Ok(FirstLevelTargets::Validators(
+            ValidatorsCommitmentTargets::read_targets(&mut target_buffer).unwrap(),
+        ))
+    }
+}
+
+/// Returns the index the leaf proof is saved at, which for a leaf with several
+/// validators is the index of the first one, together with its witness.
+async fn fetch_first_level_witness(
+    con: &mut Connection,
+    first_level_targets: &FirstLevelTargets,
+    validator_index: usize,
+) -> Result<(usize, PartialWitness<GoldilocksField>)> {
+    let mut pw = PartialWitness::new();
+
+    match first_level_targets {
+        FirstLevelTargets::Validator(targets) => {
+            let validator = fetch_validator(con, validator_index).await?;
+
+            targets.validator.set_pw_values(&mut pw, &validator);
+
+            Ok((validator_index, pw))
+        }
+        FirstLevelTargets::Validators(targets) => {
+            let validators_len = targets.validators.len();
+            let first_index = validator_index - validator_index % validators_len;
+
+            let validators = fetch_validators(con, first_index, validators_len).await?;
+
+            targets.set_pw_values(&mut pw, &validators);
+
+            Ok((first_index, pw))
+        }
+    }
 }
@@ -2,11 +2,19 @@ use std::{fs, marker::PhantomData};
 
 use anyhow::Result;
 use circuits::{
-    build_commitment_mapper_first_level_circuit::build_commitment_mapper_first_level_circuit,
+    build_commitment_mapper_cyclic_circuit::build_commitment_mapper_cyclic_circuit,
+    build_commitment_mapper_first_level_circuit::{
+        build_commitment_mapper_batch_first_level_circuit,
+        build_commitment_mapper_first_level_circuit,
+    },
     build_commitment_mapper_inner_level_circuit::build_commitment_mapper_inner_circuit,
     generator_serializer::{DendrETHGateSerializer, DendrETHGeneratorSerializer},
+    network_preset::NetworkPreset,
     targets_serialization::WriteTargets,
-    validator_commitment_mapper::ValidatorCommitmentTargets,
+};
+use circuits_executables::{
+    circuits_manifest::{CircuitsManifest, CommitmentMapperManifest},
+    crud::COMMITMENT_MAPPER_CYCLIC_CIRCUIT,
 };
 
 use clap::{App, Arg};
@@ -45,6 +53,44 @@ pub async fn async_main() -> Result<()> {
                     }
                 }),
         )
+        .arg(
+            Arg::with_name("cyclic")
+                .long("cyclic")
+                .help("Generates the first level and a single cyclic circuit for all other levels")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("validators_per_leaf")
+                .long("validators-per-leaf")
+                .value_name("VALIDATORS")
+                .help("Sets the number of validators mapped by a first level circuit")
+                .takes_value(true)
+                .default_value("1")
+                .validator(|x| {
+                    if x.parse::<usize>().map_or(false, |x| x.is_power_of_two()) {
+                        Ok(())
+                    } else {
+                        Err(String::from("The number of validators must be a power of two"))
+                    }
+                }),
+        )
+        .arg(
+            Arg::with_name("tree_depth")
+                .long("tree-depth")
+                .value_name("DEPTH")
+                .help("Sets the depth of the validators tree, defaults to the depth of the validator registry")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("preset")
+                .long("preset")
+                .value_name("PRESET")
+                .help(
+                    "Sets the network preset: mainnet, minimal, gnosis or a path to a YAML preset",
+                )
+                .takes_value(true)
+                .default_value("mainnet"),
+        )
         .get_matches();
 
     let level = match matches.value_of("circuit_level").unwrap() {
@@ -52,8 +98,41 @@ pub async fn async_main() -> Result<()> {
         x => Some(x.parse::<usize>().unwrap()),
     };
 
-    let (validators_balance_verification_targets, first_level_data) =
-        build_commitment_mapper_first_level_circuit();
+    let preset = NetworkPreset::from_name(matches.value_of("preset").unwrap())?;
+
+    let tree_depth = match matches.value_of("tree_depth") {
+        Some(x) => x.parse::<usize>()?,
+        None => preset.validator_registry_depth(),
+    };
+
+    let validators_per_leaf = matches
+        .value_of("validators_per_leaf")
+        .unwrap()
+        .parse::<usize>()
+        .unwrap();
+
+    if tree_depth > preset.validator_registry_depth() || (1 << tree_depth) < validators_per_leaf {
+        return Err(anyhow::anyhow!(
+            "The tree depth must be between log2(validators per leaf) and the depth of the validator registry"
+        ));
+    }
+
+    let commitment_mapper_manifest = CommitmentMapperManifest {
+        validators_per_leaf,
+        tree_depth,
+        cyclic: matches.is_present("cyclic"),
+    };
+
+    let (first_level_targets_bytes, first_level_data) = if validators_per_leaf == 1 {
+        let (targets, data) = build_commitment_mapper_first_level_circuit();
+
+        (targets.write_targets().unwrap(), data)
+    } else {
+        let (targets, data) =
+            build_commitment_mapper_batch_first_level_circuit(validators_per_leaf);
+
+        (targets.write_targets().unwrap(), data)
+    };
 
     let gate_serializer = DendrETHGateSerializer;
 
@@ -66,7 +145,7 @@ pub async fn async_main() -> Result<()> {
             &first_level_data,
             &gate_serializer,
             &generator_serializer,
-            validators_balance_verification_targets,
+            &first_level_targets_bytes,
         );
     }
 
@@ -74,9 +153,35 @@ pub async fn async_main() -> Result<()> {
         return Ok(());
     }
 
+    if matches.is_present("cyclic") {
+        let (targets, data) = build_commitment_mapper_cyclic_circuit(&first_level_data);
+
+        let circuit_bytes = data
+            .to_bytes(&gate_serializer, &generator_serializer)
+            .unwrap();
+
+        write_to_file(
+            &format!("{}.plonky2_circuit", COMMITMENT_MAPPER_CYCLIC_CIRCUIT),
+            &circuit_bytes,
+        )
+        .unwrap();
+
+        write_to_file(
+            &format!("{}.plonky2_targets", COMMITMENT_MAPPER_CYCLIC_CIRCUIT),
+            &targets.write_targets().unwrap(),
+        )
+        .unwrap();
+
+        if level == None {
+            save_manifest(commitment_mapper_manifest)?;
+        }
+
+        return Ok(());
+    }
+
     let mut prev_circuit_data = first_level_data;
 
-    for i in 1..41 {
+    for i in 1..=commitment_mapper_manifest.levels() {
         let (targets, data) = build_commitment_mapper_inner_circuit(&prev_circuit_data);
 
         if level == Some(i) || level == None {
@@ -106,9 +211,20 @@ pub async fn async_main() -> Result<()> {
         prev_circuit_data = data;
     }
 
+    if level == None {
+        save_manifest(commitment_mapper_manifest)?;
+    }
+
     Ok(())
 }
 
+/// Only a full generation matches the manifest.
+fn save_manifest(commitment_mapper_manifest: CommitmentMapperManifest) -> Result<()> {
+    let mut manifest = CircuitsManifest::load_or_default()?;
+    manifest.commitment_mapper = Some(commitment_mapper_manifest);
+    manifest.save()
+}
+
 fn write_first_level_circuit(
     first_level_data: &plonky2::plonk::circuit_data::CircuitData<
         plonky2::field::goldilocks_field::GoldilocksField,
@@ -117,7 +233,7 @@ fn write_first_level_circuit(
     >,
     gate_serializer: &DendrETHGateSerializer,
     generator_serializer: &DendrETHGeneratorSerializer<PoseidonGoldilocksConfig, 2>,
-    validator_commitment_targets: ValidatorCommitmentTargets,
+    validator_commitment_targets_bytes: &[u8],
 ) {
     let circuit_bytes = first_level_data
         .to_bytes(gate_serializer, generator_serializer)
@@ -129,11 +245,9 @@ fn write_first_level_circuit(
     )
     .unwrap();
 
-    let validator_commitment_targets_bytes = validator_commitment_targets.write_targets().unwrap();
-
     write_to_file(
         &format!("commitment_mapper_{}.plonky2_targets", 0),
-        &validator_commitment_targets_bytes,
+        validator_commitment_targets_bytes,
     )
     .unwrap();
 }
@@ -0,0 +1,102 @@
+use std::{
+    fs,
+    io::{self, Read},
+    println,
+};
+
+use anyhow::Result;
+use circuits_executables::{
+    circuits_manifest::CircuitsManifest,
+    commitment_mapper_scheduler::CommitmentMapperTree,
+    crud::{invalidate_validator_proof, validator_proof_exists},
+    validator::VALIDATOR_REGISTRY_LIMIT,
+    validator_commitment_constants::get_validator_commitment_constants,
+};
+use clap::{App, Arg};
+use futures_lite::future;
+use redis_work_queue::{Item, KeyPrefix, WorkQueue};
+
+fn main() -> Result<()> {
+    future::block_on(async_main())
+}
+
+async fn async_main() -> Result<()> {
+    let matches = App::new("")
+        .arg(
+            Arg::with_name("redis_connection")
+                .short('r')
+                .long("redis")
+                .value_name("Redis Connection")
+                .help("Sets a custom Redis connection")
+                .takes_value(true)
+                .default_value("redis://127.0.0.1:6379/"),
+        )
+        .arg(
+            Arg::with_name("indices")
+                .long("indices")
+                .value_name("FILE")
+                .help("Sets the file with the indices of the changed validators, separated by whitespace or commas. Read from stdin if not set")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("zero_proofs")
+                .long("zero-proofs")
+                .help("Schedules the zero proofs even if they were already proven")
+                .takes_value(false),
+        )
+        .get_matches();
+
+    let indices = match matches.value_of("indices") {
+        Some(file) => fs::read_to_string(file)?,
+        None => {
+            let mut indices = String::new();
+            io::stdin().read_to_string(&mut indices)?;
+            indices
+        }
+    };
+
+    let manifest = CircuitsManifest::load()?;
+    let commitment_mapper_manifest = manifest.commitment_mapper()?;
+
+    let mut tree = CommitmentMapperTree::new(commitment_mapper_manifest);
+
+    for index in indices
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|index| !index.is_empty())
+    {
+        tree.mark_changed_validator(index.parse::<usize>()?)?;
+    }
+
+    let client = redis::Client::open(matches.value_of("redis_connection").unwrap())?;
+    let mut con = client.get_async_connection().await?;
+
+    if matches.is_present("zero_proofs")
+        || !validator_proof_exists(&mut con, tree.levels(), VALIDATOR_REGISTRY_LIMIT).await?
+    {
+        tree.mark_zero_nodes();
+    }
+
+    let jobs = tree.take_jobs();
+
+    // Every proof is invalidated before any job is queued, so no job can use the
+    // proof of a node that is yet to be proven again
+    for job in &jobs {
+        let (depth, index) = job.proof_key(commitment_mapper_manifest.validators_per_leaf);
+
+        invalidate_validator_proof(&mut con, depth, index).await?;
+    }
+
+    let queue = WorkQueue::new(KeyPrefix::new(
+        get_validator_commitment_constants().validator_proofs_queue,
+    ));
+
+    for job in &jobs {
+        queue
+            .add_item(&mut con, &Item::new(job.to_bytes().into_boxed_slice()))
+            .await?;
+    }
+
+    println!("Scheduled {} jobs", jobs.len());
+
+    Ok(())
+}
@@ -0,0 +1,88 @@
+use anyhow::Result;
+use circuits_executables::{
+    crud::{fetch_proof_bytes, load_circuit_data},
+    json_export::{export_circuit_data_json, export_proof_json},
+};
+use clap::{App, Arg};
+use futures_lite::future;
+use plonky2::{
+    field::goldilocks_field::GoldilocksField,
+    plonk::{config::PoseidonGoldilocksConfig, proof::ProofWithPublicInputs},
+};
+
+fn main() -> Result<()> {
+    future::block_on(async_main())
+}
+
+async fn async_main() -> Result<()> {
+    let matches = App::new("")
+        .arg(
+            Arg::with_name("circuit")
+                .short('c')
+                .long("circuit")
+                .value_name("CIRCUIT")
+                .help("Sets the circuit file name without the .plonky2_circuit extension")
+                .takes_value(true)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("proof_key")
+                .short('p')
+                .long("proof-key")
+                .value_name("KEY")
+                .help("Sets the Redis key of a stored proof of the circuit to export")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("redis_connection")
+                .short('r')
+                .long("redis")
+                .value_name("Redis Connection")
+                .help("Sets a custom Redis connection")
+                .takes_value(true)
+                .default_value("redis://127.0.0.1:6379/"),
+        )
+        .arg(
+            Arg::with_name("output_dir")
+                .short('o')
+                .long("output-dir")
+                .value_name("DIR")
+                .help("Sets the directory the JSON files are written to")
+                .takes_value(true)
+                .default_value("json_export"),
+        )
+        .get_matches();
+
+    let circuit = matches.value_of("circuit").unwrap();
+
+    let output_dir = matches.value_of("output_dir").unwrap();
+
+    let circuit_data = load_circuit_data(circuit)?;
+
+    export_circuit_data_json(
+        output_dir,
+        &circuit_data.verifier_only,
+        &circuit_data.common,
+    )?;
+
+    println!("Circuit data exported!");
+
+    if let Some(proof_key) = matches.value_of("proof_key") {
+        let client = redis::Client::open(matches.value_of("redis_connection").unwrap())?;
+        let mut con = client.get_async_connection().await?;
+
+        let proof =
+            ProofWithPublicInputs::<GoldilocksField, PoseidonGoldilocksConfig, 2>::from_bytes(
+                fetch_proof_bytes(&mut con, proof_key).await?,
+                &circuit_data.common,
+            )?;
+
+        circuit_data.verify(proof.clone())?;
+
+        export_proof_json(output_dir, &proof)?;
+
+        println!("Proof exported!");
+    }
+
+    Ok(())
+}
@@ -1,11 +1,17 @@
 use std::{println, time::Instant};
 
 use anyhow::Result;
-use circuits::build_final_circuit::build_final_circuit;
+use circuits::{
+    build_final_circuit::{build_final_circuit, FinalCircuitConfig},
+    fork::Fork,
+    network_preset::NetworkPreset,
+};
 use circuits_executables::{
+    circuits_manifest::CircuitsManifest,
     crud::{
-        fetch_final_layer_input, fetch_proof, load_circuit_data, save_final_proof, BalanceProof,
-        ValidatorProof,
+        fetch_final_layer_input, fetch_proof, final_layer_circuit_name, load_circuit_data,
+        save_circuit_data, save_committed_final_proof, save_final_proof, BalanceProof,
+        ValidatorProof, COMMITMENT_MAPPER_CYCLIC_CIRCUIT,
     },
     provers::SetPWValues,
 };
@@ -32,10 +38,76 @@ async fn async_main() -> Result<()> {
                 .takes_value(true)
                 .default_value("redis://127.0.0.1:6379/"),
         )
+        .arg(
+            Arg::with_name("anchor_to_block_root")
+                .long("anchor-to-block-root")
+                .help("Anchors the final proof to the beacon block root")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("commit_public_inputs")
+                .long("commit-public-inputs")
+                .help("Publishes a single SHA-256 commitment to the public inputs")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("preset")
+                .long("preset")
+                .value_name("PRESET")
+                .help(
+                    "Sets the network preset: mainnet, minimal, gnosis or a path to a YAML preset",
+                )
+                .takes_value(true)
+                .default_value("mainnet"),
+        )
+        .arg(
+            Arg::with_name("fork")
+                .long("fork")
+                .value_name("FORK")
+                .help("Sets the fork whose BeaconState layout is proven")
+                .takes_value(true)
+                .possible_values(["deneb", "electra"])
+                .default_value("deneb"),
+        )
         .get_matches();
 
     let redis_connection = matches.value_of("redis_connection").unwrap();
 
+    let anchor_to_block_root = matches.is_present("anchor_to_block_root");
+
+    let commit_public_inputs = matches.is_present("commit_public_inputs");
+
+    let preset = NetworkPreset::from_name(matches.value_of("preset").unwrap())?;
+
+    let fork = Fork::from_name(matches.value_of("fork").unwrap())?;
+
+    let manifest = CircuitsManifest::load()?;
+
+    let tree_depth = manifest.tree_depth()?;
+
+    if tree_depth > preset.validator_registry_depth() {
+        return Err(anyhow::anyhow!(
+            "The circuits were generated for a deeper tree than the validator registry of the preset"
+        ));
+    }
+
+    let balance_manifest = manifest.balance_verification()?;
+
+    println!(
+        "Summing up the balances for {} withdrawal credentials matched by {:?}, slashed validators {}",
+        balance_manifest.withdrawal_credentials.len,
+        balance_manifest.withdrawal_credentials.matching,
+        if balance_manifest.exclude_slashed {
+            "excluded"
+        } else {
+            "included"
+        }
+    );
+
+    let balance_level = balance_manifest.levels();
+    let commitment_mapper_level = manifest.commitment_mapper()?.levels();
+    let commitment_mapper_cyclic = manifest.commitment_mapper()?.cyclic;
+
     let start = Instant::now();
     let client = redis::Client::open(redis_connection)?;
     let mut con = client.get_async_connection().await?;
@@ -44,10 +116,31 @@ async fn async_main() -> Result<()> {
 
     println!("Redis connection took: {:?}", elapsed);
 
-    let balance_data = load_circuit_data("37").unwrap();
-    let commitment_data = load_circuit_data("commitment_mapper_40").unwrap();
+    let balance_data = load_circuit_data(&balance_level.to_string()).unwrap();
+    let commitment_data = if commitment_mapper_cyclic {
+        load_circuit_data(COMMITMENT_MAPPER_CYCLIC_CIRCUIT).unwrap()
+    } else {
+        load_circuit_data(&format!("commitment_mapper_{}", commitment_mapper_level)).unwrap()
+    };
+
+    let (circuit_targets, circuit_data) = build_final_circuit(
+        &balance_data,
+        &commitment_data,
+        &preset,
+        &FinalCircuitConfig {
+            tree_depth,
+            commitment_mapper_levels: commitment_mapper_level,
+            commitment_mapper_cyclic,
+            fork,
+            anchor_to_block_root,
+            commit_public_inputs,
+        },
+    );
 
-    let (circuit_targets, circuit_data) = build_final_circuit(&balance_data, &commitment_data);
+    save_circuit_data(
+        &final_layer_circuit_name(fork, anchor_to_block_root, commit_public_inputs),
+        &circuit_data,
+    )?;
 
     let final_input_data = fetch_final_layer_input(&mut con).await?;
 
@@ -55,7 +148,7 @@ async fn async_main() -> Result<()> {
 
     circuit_targets.set_pw_values(&mut pw, &final_input_data);
 
-    let balance_proof: BalanceProof = fetch_proof(&mut con, 37, 0).await?;
+    let balance_proof: BalanceProof = fetch_proof(&mut con, balance_level, 0).await?;
 
     let balance_final_proof =
         ProofWithPublicInputs::<GoldilocksField, PoseidonGoldilocksConfig, 2>::from_bytes(
@@ -68,23 +161,8 @@ async fn async_main() -> Result<()> {
         &balance_final_proof,
     );
 
-    pw.set_cap_target(
-        &circuit_targets
-            .balance_circuit_targets
-            .verifier_circuit_target
-            .constants_sigmas_cap,
-        &balance_data.verifier_only.constants_sigmas_cap,
-    );
-
-    pw.set_hash_target(
-        circuit_targets
-            .balance_circuit_targets
-            .verifier_circuit_target
-            .circuit_digest,
-        balance_data.verifier_only.circuit_digest,
-    );
-
-    let commitment_proof: ValidatorProof = fetch_proof(&mut con, 40, 0).await?;
+    let commitment_proof: ValidatorProof =
+        fetch_proof(&mut con, commitment_mapper_level, 0).await?;
 
     let commitment_final_proof = ProofWithPublicInputs::<
         GoldilocksField,
@@ -97,25 +175,13 @@ async fn async_main() -> Result<()> {
         &commitment_final_proof,
     );
 
-    pw.set_cap_target(
-        &circuit_targets
-            .commitment_mapper_circuit_targets
-            .verifier_circuit_target
-            .constants_sigmas_cap,
-        &commitment_data.verifier_only.constants_sigmas_cap,
-    );
-
-    pw.set_hash_target(
-        circuit_targets
-            .commitment_mapper_circuit_targets
-            .verifier_circuit_target
-            .circuit_digest,
-        commitment_data.verifier_only.circuit_digest,
-    );
-
     let proof = circuit_data.prove(pw)?;
 
-    save_final_proof(&mut con, &proof).await?;
+    if commit_public_inputs {
+        save_committed_final_proof(&mut con, &proof).await?;
+    } else {
+        save_final_proof(&mut con, &proof, anchor_to_block_root).await?;
+    }
 
     println!("Proof size: {}", proof.to_bytes().len());
 
@@ -0,0 +1,143 @@
+use std::{println, time::Instant};
+
+use anyhow::Result;
+use circuits::{
+    build_final_wrapper_circuit::{build_final_compression_circuit, build_final_wrapper_circuit},
+    fork::Fork,
+};
+use circuits_executables::{
+    crud::{
+        fetch_committed_final_proof, fetch_final_proof, final_layer_circuit_name, load_circuit_data,
+    },
+    json_export::{export_circuit_data_json, export_proof_json},
+};
+use clap::{App, Arg};
+use futures_lite::future;
+use plonky2::{
+    field::goldilocks_field::GoldilocksField,
+    iop::witness::{PartialWitness, WitnessWrite},
+    plonk::{config::PoseidonGoldilocksConfig, proof::ProofWithPublicInputs},
+};
+
+fn main() -> Result<()> {
+    future::block_on(async_main())
+}
+
+async fn async_main() -> Result<()> {
+    let matches = App::new("")
+        .arg(
+            Arg::with_name("redis_connection")
+                .short('r')
+                .long("redis")
+                .value_name("Redis Connection")
+                .help("Sets a custom Redis connection")
+                .takes_value(true)
+                .default_value("redis://127.0.0.1:6379/"),
+        )
+        .arg(
+            Arg::with_name("anchor_to_block_root")
+                .long("anchor-to-block-root")
+                .help("Whether the final proof was anchored to the beacon block root")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("commit_public_inputs")
+                .long("commit-public-inputs")
+                .help("Whether the final proof publishes a commitment to its public inputs")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("fork")
+                .long("fork")
+                .value_name("FORK")
+                .help("Sets the fork the final proof was generated for")
+                .takes_value(true)
+                .possible_values(["deneb", "electra"])
+                .default_value("deneb"),
+        )
+        .arg(
+            Arg::with_name("output_dir")
+                .short('o')
+                .long("output-dir")
+                .value_name("DIR")
+                .help("Sets the directory the wrapped proof and verifier data are written to")
+                .takes_value(true)
+                .default_value("final_wrapper"),
+        )
+        .get_matches();
+
+    let redis_connection = matches.value_of("redis_connection").unwrap();
+
+    let anchor_to_block_root = matches.is_present("anchor_to_block_root");
+
+    let commit_public_inputs = matches.is_present("commit_public_inputs");
+
+    let fork = Fork::from_name(matches.value_of("fork").unwrap())?;
+
+    let output_dir = matches.value_of("output_dir").unwrap();
+
+    let start = Instant::now();
+    let client = redis::Client::open(redis_connection)?;
+    let mut con = client.get_async_connection().await?;
+
+    let elapsed = start.elapsed();
+
+    println!("Redis connection took: {:?}", elapsed);
+
+    let final_circuit_data = load_circuit_data(&final_layer_circuit_name(
+        fork,
+        anchor_to_block_root,
+        commit_public_inputs,
+    ))?;
+
+    let final_proof_bytes = if commit_public_inputs {
+        fetch_committed_final_proof(&mut con).await?.proof
+    } else {
+        fetch_final_proof(&mut con).await?.proof
+    };
+
+    let final_proof =
+        ProofWithPublicInputs::<GoldilocksField, PoseidonGoldilocksConfig, 2>::from_bytes(
+            final_proof_bytes,
+            &final_circuit_data.common,
+        )?;
+
+    let (compression_targets, compression_circuit_data) =
+        build_final_compression_circuit(&final_circuit_data);
+
+    let mut pw: PartialWitness<GoldilocksField> = PartialWitness::new();
+
+    pw.set_proof_with_pis_target(&compression_targets.proof, &final_proof);
+
+    let compression_proof = compression_circuit_data.prove(pw)?;
+
+    println!(
+        "Compression proof size: {}",
+        compression_proof.to_bytes().len()
+    );
+
+    let (wrapper_targets, wrapper_circuit_data) =
+        build_final_wrapper_circuit(&compression_circuit_data);
+
+    let mut pw: PartialWitness<GoldilocksField> = PartialWitness::new();
+
+    pw.set_proof_with_pis_target(&wrapper_targets.proof, &compression_proof);
+
+    let wrapper_proof = wrapper_circuit_data.prove(pw)?;
+
+    wrapper_circuit_data.verify(wrapper_proof.clone())?;
+
+    export_proof_json(output_dir, &wrapper_proof)?;
+
+    export_circuit_data_json(
+        output_dir,
+        &wrapper_circuit_data.verifier_only,
+        &wrapper_circuit_data.common,
+    )?;
+
+    println!("Proof size: {}", wrapper_proof.to_bytes().len());
+
+    println!("Wrapped proof saved!");
+
+    Ok(())
+}
@@ -0,0 +1,135 @@
+use std::{println, time::Instant};
+
+use anyhow::Result;
+use circuits::{
+    build_historical_state_root_circuit::{
+        build_historical_state_root_circuit, HistoricalStateRootSource,
+    },
+    fork::Fork,
+    network_preset::NetworkPreset,
+};
+use circuits_executables::{
+    crud::{
+        fetch_final_proof, fetch_historical_state_root_input, final_layer_circuit_name,
+        load_circuit_data, save_historical_state_root_proof,
+    },
+    provers::SetPWValues,
+};
+use clap::{App, Arg};
+use futures_lite::future;
+use plonky2::{
+    field::goldilocks_field::GoldilocksField,
+    iop::witness::{PartialWitness, WitnessWrite},
+    plonk::{config::PoseidonGoldilocksConfig, proof::ProofWithPublicInputs},
+};
+
+fn main() -> Result<()> {
+    future::block_on(async_main())
+}
+
+async fn async_main() -> Result<()> {
+    let matches = App::new("")
+        .arg(
+            Arg::with_name("redis_connection")
+                .short('r')
+                .long("redis")
+                .value_name("Redis Connection")
+                .help("Sets a custom Redis connection")
+                .takes_value(true)
+                .default_value("redis://127.0.0.1:6379/"),
+        )
+        .arg(
+            Arg::with_name("source")
+                .long("source")
+                .value_name("source")
+                .help("Where the older state root is found in the newer state")
+                .takes_value(true)
+                .possible_values(["state-roots", "historical-summaries"])
+                .default_value("state-roots"),
+        )
+        .arg(
+            Arg::with_name("preset")
+                .long("preset")
+                .value_name("PRESET")
+                .help(
+                    "Sets the network preset: mainnet, minimal, gnosis or a path to a YAML preset",
+                )
+                .takes_value(true)
+                .default_value("mainnet"),
+        )
+        .arg(
+            Arg::with_name("fork")
+                .long("fork")
+                .value_name("FORK")
+                .help("Sets the fork of the newer state")
+                .takes_value(true)
+                .possible_values(["deneb", "electra"])
+                .default_value("deneb"),
+        )
+        .arg(
+            Arg::with_name("final_fork")
+                .long("final-fork")
+                .value_name("FORK")
+                .help("Sets the fork the final proof was generated for. Defaults to --fork")
+                .takes_value(true)
+                .possible_values(["deneb", "electra"]),
+        )
+        .get_matches();
+
+    let redis_connection = matches.value_of("redis_connection").unwrap();
+
+    let source = match matches.value_of("source").unwrap() {
+        "historical-summaries" => HistoricalStateRootSource::HistoricalSummaries,
+        _ => HistoricalStateRootSource::StateRoots,
+    };
+
+    let preset = NetworkPreset::from_name(matches.value_of("preset").unwrap())?;
+
+    let fork = Fork::from_name(matches.value_of("fork").unwrap())?;
+
+    let final_fork = match matches.value_of("final_fork") {
+        Some(name) => Fork::from_name(name)?,
+        None => fork,
+    };
+
+    let start = Instant::now();
+    let client = redis::Client::open(redis_connection)?;
+    let mut con = client.get_async_connection().await?;
+
+    let elapsed = start.elapsed();
+
+    println!("Redis connection took: {:?}", elapsed);
+
+    // Anchored final proofs are not supported, their block root would not match the newer state
+    let final_circuit_data =
+        load_circuit_data(&final_layer_circuit_name(final_fork, false, false))?;
+
+    let (circuit_targets, circuit_data) =
+        build_historical_state_root_circuit(&final_circuit_data, source, &preset, fork);
+
+    let historical_state_root_input = fetch_historical_state_root_input(&mut con).await?;
+
+    let mut pw: PartialWitness<GoldilocksField> = PartialWitness::new();
+
+    circuit_targets.set_pw_values(&mut pw, &historical_state_root_input);
+
+    let final_proof = fetch_final_proof(&mut con).await?;
+
+    let final_proof =
+        ProofWithPublicInputs::<GoldilocksField, PoseidonGoldilocksConfig, 2>::from_bytes(
+            final_proof.proof,
+            &final_circuit_data.common,
+        )?;
+
+    pw.set_proof_with_pis_target(&circuit_targets.proof, &final_proof);
+
+    let proof = circuit_data.prove(pw)?;
+
+    save_historical_state_root_proof(&mut con, &proof).await?;
+
+    println!("Proof size: {}", proof.to_bytes().len());
+
+    println!("Historical proof saved!");
+
+    Ok(())
+}
@@ -0,0 +1,175 @@
+use anyhow::Result;
+use circuits::{
+    build_pending_deposits_circuit::{
+        build_pending_deposits_circuit, PENDING_DEPOSITS_PER_CIRCUIT,
+    },
+    build_pending_deposits_inner_level_circuit::build_pending_deposits_inner_level_circuit,
+    network_preset::NetworkPreset,
+    targets_serialization::WriteTargets,
+    withdrawal_credentials::WithdrawalCredentialsMatching,
+};
+use circuits_executables::{
+    circuits_manifest::{CircuitsManifest, PendingDepositsManifest, WithdrawalCredentialsManifest},
+    crud::{pending_deposits_circuit_name, save_circuit_data, write_to_file},
+};
+
+use clap::{App, Arg};
+use futures_lite::future;
+
+use jemallocator::Jemalloc;
+
+#[global_allocator]
+static GLOBAL: Jemalloc = Jemalloc;
+
+fn main() -> Result<()> {
+    future::block_on(async_main())
+}
+
+pub async fn async_main() -> Result<()> {
+    let matches = App::new("")
+        .arg(
+            Arg::with_name("circuit_level")
+                .short('l')
+                .long("level")
+                .value_name("LEVEL")
+                .help("Sets the circuit level")
+                .takes_value(true)
+                .default_value("all")
+                .validator(|x| {
+                    if x == "all" || x.parse::<usize>().is_ok() {
+                        Ok(())
+                    } else {
+                        Err(String::from("The level must be a number or 'all'"))
+                    }
+                }),
+        )
+        .arg(
+            Arg::with_name("withdrawal_credentials_len")
+                .long("withdrawal-credentials")
+                .value_name("WITHDRAWAL_CREDENTIALS")
+                .help("Sets the number of withdrawal credentials in the set")
+                .takes_value(true)
+                .default_value("1")
+                .validator(|x| {
+                    if x.parse::<usize>().map_or(false, |x| x > 0) {
+                        Ok(())
+                    } else {
+                        Err(String::from(
+                            "The number of withdrawal credentials must be a positive number",
+                        ))
+                    }
+                }),
+        )
+        .arg(
+            Arg::with_name("match_execution_address")
+                .long("match-execution-address")
+                .help("Compares only the execution address of the withdrawal credentials")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("allowed_prefixes")
+                .long("allowed-prefixes")
+                .value_name("PREFIXES")
+                .help("Sets the comma-separated credential prefixes allowed when matching by execution address")
+                .takes_value(true)
+                .default_value("01,02")
+                .validator(|x| {
+                    if x.split(',').all(|prefix| u8::from_str_radix(prefix, 16).is_ok()) {
+                        Ok(())
+                    } else {
+                        Err(String::from("The prefixes must be hex bytes"))
+                    }
+                }),
+        )
+        .arg(
+            Arg::with_name("preset")
+                .long("preset")
+                .value_name("PRESET")
+                .help(
+                    "Sets the network preset: mainnet, minimal, gnosis or a path to a YAML preset",
+                )
+                .takes_value(true)
+                .default_value("mainnet"),
+        )
+        .get_matches();
+
+    let level = match matches.value_of("circuit_level").unwrap() {
+        "all" => None,
+        x => Some(x.parse::<usize>().unwrap()),
+    };
+
+    let withdrawal_credentials_len = matches
+        .value_of("withdrawal_credentials_len")
+        .unwrap()
+        .parse::<usize>()
+        .unwrap();
+
+    let matching = if matches.is_present("match_execution_address") {
+        WithdrawalCredentialsMatching::ExecutionAddress {
+            allowed_prefixes: matches
+                .value_of("allowed_prefixes")
+                .unwrap()
+                .split(',')
+                .map(|prefix| u8::from_str_radix(prefix, 16).unwrap())
+                .collect(),
+        }
+    } else {
+        WithdrawalCredentialsMatching::FullCredentials
+    };
+
+    let preset = NetworkPreset::from_name(matches.value_of("preset").unwrap())?;
+
+    let (pending_deposits_targets, first_level_data) = build_pending_deposits_circuit(
+        PENDING_DEPOSITS_PER_CIRCUIT,
+        withdrawal_credentials_len,
+        &matching,
+    );
+
+    if level == None || level == Some(0) {
+        save_circuit_data(&pending_deposits_circuit_name(0), &first_level_data)?;
+
+        write_to_file(
+            &format!("{}.plonky2_targets", pending_deposits_circuit_name(0)),
+            &pending_deposits_targets.write_targets().unwrap(),
+        )?;
+    }
+
+    if level == Some(0) {
+        return Ok(());
+    }
+
+    let mut prev_circuit_data = first_level_data;
+
+    for i in 1..=preset.pending_deposits_levels(PENDING_DEPOSITS_PER_CIRCUIT) {
+        let (targets, data) = build_pending_deposits_inner_level_circuit(&prev_circuit_data);
+
+        if level == Some(i) || level == None {
+            save_circuit_data(&pending_deposits_circuit_name(i), &data)?;
+
+            write_to_file(
+                &format!("{}.plonky2_targets", pending_deposits_circuit_name(i)),
+                &targets.write_targets().unwrap(),
+            )?;
+        }
+
+        if level == Some(i) {
+            return Ok(());
+        }
+
+        prev_circuit_data = data;
+    }
+
+    // Only a full generation matches the manifest
+    if level == None {
+        let mut manifest = CircuitsManifest::load_or_default()?;
+        manifest.pending_deposits = Some(PendingDepositsManifest {
+            withdrawal_credentials: WithdrawalCredentialsManifest {
+                len: withdrawal_credentials_len,
+                matching,
+            },
+        });
+        manifest.save()?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,128 @@
+use std::{println, time::Instant};
+
+use anyhow::Result;
+use circuits::{
+    build_pending_deposits_circuit::PENDING_DEPOSITS_PER_CIRCUIT,
+    build_pending_deposits_final_circuit::build_pending_deposits_final_circuit, fork::Fork,
+    network_preset::NetworkPreset,
+};
+use circuits_executables::{
+    circuits_manifest::CircuitsManifest,
+    crud::{
+        fetch_final_proof, fetch_pending_deposits_layer_input, fetch_proof,
+        final_layer_circuit_name, load_circuit_data, pending_deposits_circuit_name,
+        save_pending_deposits_layer_proof, PendingDepositsProof,
+    },
+    provers::SetPWValues,
+};
+use clap::{App, Arg};
+use futures_lite::future;
+use plonky2::{
+    field::goldilocks_field::GoldilocksField,
+    iop::witness::{PartialWitness, WitnessWrite},
+    plonk::{config::PoseidonGoldilocksConfig, proof::ProofWithPublicInputs},
+};
+
+fn main() -> Result<()> {
+    future::block_on(async_main())
+}
+
+async fn async_main() -> Result<()> {
+    let matches = App::new("")
+        .arg(
+            Arg::with_name("redis_connection")
+                .short('r')
+                .long("redis")
+                .value_name("Redis Connection")
+                .help("Sets a custom Redis connection")
+                .takes_value(true)
+                .default_value("redis://127.0.0.1:6379/"),
+        )
+        .arg(
+            Arg::with_name("anchor_to_block_root")
+                .long("anchor-to-block-root")
+                .help("Whether the final proof was anchored to the beacon block root")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("preset")
+                .long("preset")
+                .value_name("PRESET")
+                .help(
+                    "Sets the network preset: mainnet, minimal, gnosis or a path to a YAML preset",
+                )
+                .takes_value(true)
+                .default_value("mainnet"),
+        )
+        .get_matches();
+
+    let redis_connection = matches.value_of("redis_connection").unwrap();
+
+    let anchor_to_block_root = matches.is_present("anchor_to_block_root");
+
+    let preset = NetworkPreset::from_name(matches.value_of("preset").unwrap())?;
+
+    // Pending deposits are part of the BeaconState since Electra
+    let fork = Fork::Electra;
+
+    // Fails unless the pending deposits match the withdrawal credentials of the balances
+    CircuitsManifest::load()?.pending_deposits()?;
+
+    let pending_deposits_level = preset.pending_deposits_levels(PENDING_DEPOSITS_PER_CIRCUIT);
+
+    let start = Instant::now();
+    let client = redis::Client::open(redis_connection)?;
+    let mut con = client.get_async_connection().await?;
+
+    let elapsed = start.elapsed();
+
+    println!("Redis connection took: {:?}", elapsed);
+
+    let final_circuit_data =
+        load_circuit_data(&final_layer_circuit_name(fork, anchor_to_block_root, false))?;
+    let pending_deposits_data =
+        load_circuit_data(&pending_deposits_circuit_name(pending_deposits_level))?;
+
+    let (circuit_targets, circuit_data) =
+        build_pending_deposits_final_circuit(&final_circuit_data, &pending_deposits_data, fork);
+
+    let pending_deposits_layer_input = fetch_pending_deposits_layer_input(&mut con).await?;
+
+    let mut pw: PartialWitness<GoldilocksField> = PartialWitness::new();
+
+    circuit_targets.set_pw_values(&mut pw, &pending_deposits_layer_input);
+
+    let final_proof = fetch_final_proof(&mut con).await?;
+
+    let final_proof =
+        ProofWithPublicInputs::<GoldilocksField, PoseidonGoldilocksConfig, 2>::from_bytes(
+            final_proof.proof,
+            &final_circuit_data.common,
+        )?;
+
+    pw.set_proof_with_pis_target(&circuit_targets.final_proof, &final_proof);
+
+    let pending_deposits_proof: PendingDepositsProof =
+        fetch_proof(&mut con, pending_deposits_level, 0).await?;
+
+    let pending_deposits_proof =
+        ProofWithPublicInputs::<GoldilocksField, PoseidonGoldilocksConfig, 2>::from_bytes(
+            pending_deposits_proof.proof,
+            &pending_deposits_data.common,
+        )?;
+
+    pw.set_proof_with_pis_target(
+        &circuit_targets.pending_deposits_proof,
+        &pending_deposits_proof,
+    );
+
+    let proof = circuit_data.prove(pw)?;
+
+    save_pending_deposits_layer_proof(&mut con, &proof).await?;
+
+    println!("Proof size: {}", proof.to_bytes().len());
+
+    println!("Pending deposits proof saved!");
+
+    Ok(())
+}
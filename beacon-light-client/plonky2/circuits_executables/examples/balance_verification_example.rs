@@ -5,6 +5,7 @@ use circuits::{
     build_validator_balance_circuit::build_validator_balance_circuit,
     generator_serializer::{DendrETHGateSerializer, DendrETHGeneratorSerializer},
     targets_serialization::WriteTargets,
+    withdrawal_credentials::WithdrawalCredentialsMatching,
 };
 use circuits_executables::{
     crud::{fetch_validator_balance_input, write_to_file},
@@ -34,7 +35,12 @@ async fn async_main() -> Result<()> {
     println!("Redis connection took: {:?}", elapsed);
 
     let start = Instant::now();
-    let (validators_balance_verification_targets, data) = build_validator_balance_circuit(8);
+    let (validators_balance_verification_targets, data) = build_validator_balance_circuit(
+        8,
+        1,
+        false,
+        &WithdrawalCredentialsMatching::FullCredentials,
+    );
     let elapsed = start.elapsed();
 
     println!("Circuit generation took: {:?}", elapsed);
@@ -6,6 +6,7 @@ use circuits::{
         build_commitment_mapper_inner_circuit, CommitmentMapperInnerCircuitTargets,
     },
     build_validator_balance_circuit::build_validator_balance_circuit,
+    withdrawal_credentials::WithdrawalCredentialsMatching,
 };
 use circuits_executables::crud::{fetch_proof, BalanceProof, ValidatorProof};
 use futures_lite::future;
@@ -26,7 +27,12 @@ async fn async_main() -> Result<()> {
         .get_async_connection()
         .await?;
 
-    let (_, first_level_circuit_data) = build_validator_balance_circuit(8);
+    let (_, first_level_circuit_data) = build_validator_balance_circuit(
+        8,
+        1,
+        false,
+        &WithdrawalCredentialsMatching::FullCredentials,
+    );
 
     // let mut inner_circuits: Vec<(
     //     BalanceInnerCircuitTargets,
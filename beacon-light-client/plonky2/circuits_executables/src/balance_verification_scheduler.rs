@@ -0,0 +1,249 @@
+use std::collections::BTreeSet;
+
+use anyhow::Result;
+
+use crate::{circuits_manifest::BalanceVerificationManifest, validator::VALIDATOR_REGISTRY_LIMIT};
+
+/// A job of the `balance_verification` workers, in the format of the balance
+/// verification queues.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BalanceVerificationJob {
+    /// Proves the first level input at `index`.
+    Leaf { index: usize },
+    /// Proves the node with the proofs `children` on `level`. The proof is saved
+    /// on the next level under the first child.
+    Inner { level: usize, children: Vec<usize> },
+}
+
+impl BalanceVerificationJob {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let values = match self {
+            BalanceVerificationJob::Leaf { index } => vec![*index],
+            BalanceVerificationJob::Inner { level, children } => {
+                [vec![*level], children.clone()].concat()
+            }
+        };
+
+        values
+            .into_iter()
+            .flat_map(|value| (value as u64).to_be_bytes())
+            .collect()
+    }
+
+    /// The level and index the proof of the job is saved under. The level is
+    /// also the one of the queue of the job.
+    pub fn proof_key(&self) -> (usize, usize) {
+        match self {
+            BalanceVerificationJob::Leaf { index } => (0, *index),
+            BalanceVerificationJob::Inner { level, children } => (level + 1, children[0]),
+        }
+    }
+}
+
+/// The nodes of the balance verification tree that have to be proven for the
+/// next epoch. A node is identified by its level and the index of the first
+/// validator under it, and the zero node of every level by
+/// `VALIDATOR_REGISTRY_LIMIT`. The proofs of all other nodes are carried
+/// forward from the epoch they were made for.
+pub struct BalanceVerificationTree {
+    tree_depth: usize,
+    validators_per_circuit: usize,
+    inner_level_arities: Vec<usize>,
+    dirty_nodes: Vec<BTreeSet<usize>>,
+}
+
+impl BalanceVerificationTree {
+    pub fn new(manifest: &BalanceVerificationManifest) -> Self {
+        BalanceVerificationTree {
+            tree_depth: manifest.tree_depth,
+            validators_per_circuit: manifest.validators_per_circuit,
+            inner_level_arities: manifest.inner_level_arities.clone(),
+            dirty_nodes: vec![BTreeSet::new(); manifest.levels() + 1],
+        }
+    }
+
+    /// The level of the root.
+    pub fn levels(&self) -> usize {
+        self.inner_level_arities.len()
+    }
+
+    /// The number of validators under a node on `level`.
+    fn node_size(&self, level: usize) -> usize {
+        self.inner_level_arities[..level]
+            .iter()
+            .fold(self.validators_per_circuit, |size, arity| size * arity)
+    }
+
+    fn mark_path(&mut self, validator_index: usize) {
+        for level in 0..=self.levels() {
+            let node_size = self.node_size(level);
+
+            if !self.dirty_nodes[level].insert(validator_index - validator_index % node_size) {
+                // The rest of the path is already dirty
+                break;
+            }
+        }
+    }
+
+    /// Marks the first level input containing the validator as changed.
+    pub fn mark_changed_validator(&mut self, validator_index: usize) -> Result<()> {
+        if validator_index >= 1 << self.tree_depth {
+            return Err(anyhow::anyhow!(
+                "Validator {} is outside of a tree of depth {}",
+                validator_index,
+                self.tree_depth
+            ));
+        }
+
+        self.mark_path(validator_index);
+
+        Ok(())
+    }
+
+    /// Marks the zero nodes every missing sibling falls back to.
+    pub fn mark_zero_nodes(&mut self) {
+        self.mark_path(VALIDATOR_REGISTRY_LIMIT);
+    }
+
+    /// The root publishes the current epoch, so it is proven for every epoch
+    /// even if nothing under it has changed.
+    pub fn mark_root(&mut self) {
+        let levels = self.levels();
+
+        self.dirty_nodes[levels].insert(0);
+    }
+
+    pub fn is_dirty(&self, level: usize, index: usize) -> bool {
+        self.dirty_nodes[level].contains(&index)
+    }
+
+    /// Returns the jobs for every dirty node, level by level, so the children of
+    /// a node are always scheduled before it, and clears the dirty nodes.
+    pub fn take_jobs(&mut self) -> Vec<BalanceVerificationJob> {
+        let mut jobs = self.dirty_nodes[0]
+            .iter()
+            .map(|&index| BalanceVerificationJob::Leaf { index })
+            .collect::<Vec<_>>();
+
+        for level in 1..=self.levels() {
+            let arity = self.inner_level_arities[level - 1];
+            let child_size = self.node_size(level - 1);
+
+            jobs.extend(self.dirty_nodes[level].iter().map(|&first| {
+                let children = (0..arity)
+                    .map(|i| {
+                        if first == VALIDATOR_REGISTRY_LIMIT {
+                            first
+                        } else {
+                            first + i * child_size
+                        }
+                    })
+                    .collect();
+
+                BalanceVerificationJob::Inner {
+                    level: level - 1,
+                    children,
+                }
+            }));
+        }
+
+        for nodes in self.dirty_nodes.iter_mut() {
+            nodes.clear();
+        }
+
+        jobs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use circuits::withdrawal_credentials::WithdrawalCredentialsMatching;
+
+    use crate::circuits_manifest::WithdrawalCredentialsManifest;
+
+    use super::*;
+
+    fn manifest(tree_depth: usize, arity: usize) -> BalanceVerificationManifest {
+        BalanceVerificationManifest::new(
+            8,
+            tree_depth,
+            arity,
+            WithdrawalCredentialsManifest {
+                len: 1,
+                matching: WithdrawalCredentialsMatching::FullCredentials,
+            },
+            false,
+        )
+    }
+
+    #[test]
+    fn test_jobs_of_changed_inputs() -> Result<()> {
+        // 8 validators per circuit and 4 levels of 2 merged into levels of 4
+        let mut tree = BalanceVerificationTree::new(&manifest(7, 4));
+
+        tree.mark_changed_validator(40)?;
+        tree.mark_changed_validator(47)?;
+        tree.mark_root();
+
+        assert!(tree.mark_changed_validator(128).is_err());
+        assert!(tree.is_dirty(1, 32));
+        assert!(!tree.is_dirty(1, 0));
+
+        let jobs = tree.take_jobs();
+
+        assert_eq!(
+            jobs,
+            vec![
+                BalanceVerificationJob::Leaf { index: 40 },
+                BalanceVerificationJob::Inner {
+                    level: 0,
+                    children: vec![32, 40, 48, 56]
+                },
+                BalanceVerificationJob::Inner {
+                    level: 1,
+                    children: vec![0, 32, 64, 96]
+                },
+            ]
+        );
+
+        assert_eq!(jobs[1].proof_key(), (1, 32));
+        assert_eq!(
+            jobs[2].to_bytes(),
+            [1u64, 0, 32, 64, 96]
+                .iter()
+                .flat_map(|value| value.to_be_bytes())
+                .collect::<Vec<u8>>()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jobs_of_unchanged_tree() {
+        let mut tree = BalanceVerificationTree::new(&manifest(5, 2));
+
+        tree.mark_root();
+        tree.mark_zero_nodes();
+
+        assert_eq!(
+            tree.take_jobs(),
+            vec![
+                BalanceVerificationJob::Leaf {
+                    index: VALIDATOR_REGISTRY_LIMIT
+                },
+                BalanceVerificationJob::Inner {
+                    level: 0,
+                    children: vec![VALIDATOR_REGISTRY_LIMIT; 2]
+                },
+                BalanceVerificationJob::Inner {
+                    level: 1,
+                    children: vec![0, 16]
+                },
+                BalanceVerificationJob::Inner {
+                    level: 1,
+                    children: vec![VALIDATOR_REGISTRY_LIMIT; 2]
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,247 @@
+use std::path::Path;
+
+use anyhow::Result;
+use circuits::{
+    build_balance_inner_level_circuit::balance_inner_level_arities,
+    withdrawal_credentials::WithdrawalCredentialsMatching,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::crud::{read_from_file, write_to_file};
+
+pub const CIRCUITS_MANIFEST_FILE: &str = "circuits_manifest.json";
+
+/// The withdrawal credentials set a first level circuit sums the balances for.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WithdrawalCredentialsManifest {
+    pub len: usize,
+    pub matching: WithdrawalCredentialsMatching,
+}
+
+/// The parameters the balance verification circuits were generated with.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BalanceVerificationManifest {
+    pub validators_per_circuit: usize,
+    pub tree_depth: usize,
+    pub inner_level_arities: Vec<usize>,
+    pub withdrawal_credentials: WithdrawalCredentialsManifest,
+    pub exclude_slashed: bool,
+}
+
+impl BalanceVerificationManifest {
+    pub fn new(
+        validators_per_circuit: usize,
+        tree_depth: usize,
+        arity: usize,
+        withdrawal_credentials: WithdrawalCredentialsManifest,
+        exclude_slashed: bool,
+    ) -> Self {
+        assert!(validators_per_circuit.is_power_of_two());
+
+        let binary_levels = tree_depth - validators_per_circuit.trailing_zeros() as usize;
+
+        BalanceVerificationManifest {
+            validators_per_circuit,
+            tree_depth,
+            inner_level_arities: balance_inner_level_arities(binary_levels, arity),
+            withdrawal_credentials,
+            exclude_slashed,
+        }
+    }
+
+    /// The level of the proof for the whole tree.
+    pub fn levels(&self) -> usize {
+        self.inner_level_arities.len()
+    }
+}
+
+/// The parameters the commitment mapper circuits were generated with.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitmentMapperManifest {
+    #[serde(default = "default_validators_per_leaf")]
+    pub validators_per_leaf: usize,
+    pub tree_depth: usize,
+    pub cyclic: bool,
+}
+
+fn default_validators_per_leaf() -> usize {
+    1
+}
+
+impl CommitmentMapperManifest {
+    /// The level of the proof for the whole tree.
+    pub fn levels(&self) -> usize {
+        self.tree_depth - self.validators_per_leaf.trailing_zeros() as usize
+    }
+}
+
+/// The parameters the pending deposits circuits were generated with.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingDepositsManifest {
+    pub withdrawal_credentials: WithdrawalCredentialsManifest,
+}
+
+/// Written next to the serialized circuits by the data generation binaries and
+/// read by every binary that loads them.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CircuitsManifest {
+    #[serde(default)]
+    pub balance_verification: Option<BalanceVerificationManifest>,
+    #[serde(default)]
+    pub commitment_mapper: Option<CommitmentMapperManifest>,
+    #[serde(default)]
+    pub pending_deposits: Option<PendingDepositsManifest>,
+}
+
+impl CircuitsManifest {
+    pub fn load() -> Result<CircuitsManifest> {
+        Ok(serde_json::from_slice(&read_from_file(
+            CIRCUITS_MANIFEST_FILE,
+        )?)?)
+    }
+
+    /// Same as `load`, but a missing manifest is an empty one.
+    pub fn load_or_default() -> Result<CircuitsManifest> {
+        if Path::new(CIRCUITS_MANIFEST_FILE).exists() {
+            CircuitsManifest::load()
+        } else {
+            Ok(CircuitsManifest::default())
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        write_to_file(
+            CIRCUITS_MANIFEST_FILE,
+            serde_json::to_string_pretty(self)?.as_bytes(),
+        )
+    }
+
+    pub fn balance_verification(&self) -> Result<&BalanceVerificationManifest> {
+        self.balance_verification.as_ref().ok_or(anyhow::anyhow!(
+            "The balance verification circuits are missing from {}",
+            CIRCUITS_MANIFEST_FILE
+        ))
+    }
+
+    pub fn commitment_mapper(&self) -> Result<&CommitmentMapperManifest> {
+        self.commitment_mapper.as_ref().ok_or(anyhow::anyhow!(
+            "The commitment mapper circuits are missing from {}",
+            CIRCUITS_MANIFEST_FILE
+        ))
+    }
+
+    /// The pending deposits are added to the balance sum, so both circuits have
+    /// to be generated for the same withdrawal credentials.
+    pub fn pending_deposits(&self) -> Result<&PendingDepositsManifest> {
+        let pending_deposits = self.pending_deposits.as_ref().ok_or(anyhow::anyhow!(
+            "The pending deposits circuits are missing from {}",
+            CIRCUITS_MANIFEST_FILE
+        ))?;
+
+        let balance_verification = self.balance_verification()?;
+
+        if pending_deposits.withdrawal_credentials != balance_verification.withdrawal_credentials {
+            return Err(anyhow::anyhow!(
+                "The pending deposits withdrawal credentials {:?} differ from the balance verification withdrawal credentials {:?}",
+                pending_deposits.withdrawal_credentials,
+                balance_verification.withdrawal_credentials
+            ));
+        }
+
+        Ok(pending_deposits)
+    }
+
+    /// The depth both trees were generated for, which has to be the same for
+    /// the final layer.
+    pub fn tree_depth(&self) -> Result<usize> {
+        let balance_tree_depth = self.balance_verification()?.tree_depth;
+        let commitment_mapper_tree_depth = self.commitment_mapper()?.tree_depth;
+
+        if balance_tree_depth != commitment_mapper_tree_depth {
+            return Err(anyhow::anyhow!(
+                "The balance verification tree depth {} differs from the commitment mapper tree depth {}",
+                balance_tree_depth,
+                commitment_mapper_tree_depth
+            ));
+        }
+
+        Ok(balance_tree_depth)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn withdrawal_credentials(len: usize) -> WithdrawalCredentialsManifest {
+        WithdrawalCredentialsManifest {
+            len,
+            matching: WithdrawalCredentialsMatching::ExecutionAddress {
+                allowed_prefixes: vec![1, 2],
+            },
+        }
+    }
+
+    #[test]
+    fn test_circuits_manifest() -> Result<()> {
+        let manifest = CircuitsManifest {
+            balance_verification: Some(BalanceVerificationManifest::new(
+                64,
+                10,
+                2,
+                withdrawal_credentials(4),
+                true,
+            )),
+            commitment_mapper: Some(CommitmentMapperManifest {
+                validators_per_leaf: 8,
+                tree_depth: 10,
+                cyclic: false,
+            }),
+            pending_deposits: Some(PendingDepositsManifest {
+                withdrawal_credentials: withdrawal_credentials(4),
+            }),
+        };
+
+        assert_eq!(manifest.balance_verification()?.levels(), 4);
+        assert_eq!(manifest.commitment_mapper()?.levels(), 7);
+        assert_eq!(manifest.tree_depth()?, 10);
+        assert!(manifest.pending_deposits().is_ok());
+
+        let json = serde_json::to_string(&manifest)?;
+
+        assert!(json.contains("\"validatorsPerCircuit\":64"));
+        assert!(json.contains("\"excludeSlashed\":true"));
+        assert!(json.contains("\"executionAddress\":{\"allowedPrefixes\":[1,2]}"));
+        assert_eq!(serde_json::from_str::<CircuitsManifest>(&json)?, manifest);
+
+        let manifest: CircuitsManifest = serde_json::from_str("{}")?;
+
+        assert!(manifest.balance_verification().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pending_deposits_with_different_withdrawal_credentials() {
+        let manifest = CircuitsManifest {
+            balance_verification: Some(BalanceVerificationManifest::new(
+                64,
+                10,
+                2,
+                withdrawal_credentials(4),
+                false,
+            )),
+            commitment_mapper: None,
+            pending_deposits: Some(PendingDepositsManifest {
+                withdrawal_credentials: withdrawal_credentials(2),
+            }),
+        };
+
+        assert!(manifest.pending_deposits().is_err());
+    }
+}
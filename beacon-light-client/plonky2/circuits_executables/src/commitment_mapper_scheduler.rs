@@ -0,0 +1,260 @@
+use std::collections::BTreeSet;
+
+use anyhow::Result;
+
+use crate::{circuits_manifest::CommitmentMapperManifest, validator::VALIDATOR_REGISTRY_LIMIT};
+
+/// A job of the `commitment_mapper` worker, in the format of the validator proofs
+/// queue.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CommitmentMapperJob {
+    /// Proves the leaf containing the validator.
+    Leaf { validator_index: usize },
+    /// Hashes the proofs `left` and `right` on `level`. The proof is saved on the
+    /// next level under `left`.
+    Inner {
+        level: usize,
+        left: usize,
+        right: usize,
+    },
+}
+
+impl CommitmentMapperJob {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let values = match *self {
+            CommitmentMapperJob::Leaf { validator_index } => vec![validator_index],
+            CommitmentMapperJob::Inner { level, left, right } => vec![level, left, right],
+        };
+
+        values
+            .into_iter()
+            .flat_map(|value| (value as u64).to_be_bytes())
+            .collect()
+    }
+
+    /// The level and index the proof of the job is saved under.
+    pub fn proof_key(&self, validators_per_leaf: usize) -> (usize, usize) {
+        match *self {
+            CommitmentMapperJob::Leaf { validator_index } => {
+                (0, validator_index - validator_index % validators_per_leaf)
+            }
+            CommitmentMapperJob::Inner { level, left, .. } => (level + 1, left),
+        }
+    }
+}
+
+/// The nodes of the commitment mapper tree that have to be proven again. A node
+/// is identified by its level and the index of the first validator under it,
+/// and the zero node of every level by `VALIDATOR_REGISTRY_LIMIT`, so only the
+/// paths from the changed validators to the root are ever stored.
+pub struct CommitmentMapperTree {
+    tree_depth: usize,
+    validators_per_leaf: usize,
+    dirty_nodes: Vec<BTreeSet<usize>>,
+}
+
+impl CommitmentMapperTree {
+    pub fn new(manifest: &CommitmentMapperManifest) -> Self {
+        CommitmentMapperTree {
+            tree_depth: manifest.tree_depth,
+            validators_per_leaf: manifest.validators_per_leaf,
+            dirty_nodes: vec![BTreeSet::new(); manifest.levels() + 1],
+        }
+    }
+
+    /// The level of the root.
+    pub fn levels(&self) -> usize {
+        self.dirty_nodes.len() - 1
+    }
+
+    /// The number of validators under a node on `level`.
+    fn node_size(&self, level: usize) -> usize {
+        self.validators_per_leaf << level
+    }
+
+    fn mark_path(&mut self, validator_index: usize) {
+        for level in 0..=self.levels() {
+            let node_size = self.node_size(level);
+
+            if !self.dirty_nodes[level].insert(validator_index - validator_index % node_size) {
+                // The rest of the path is already dirty
+                break;
+            }
+        }
+    }
+
+    pub fn mark_changed_validator(&mut self, validator_index: usize) -> Result<()> {
+        if validator_index >= 1 << self.tree_depth {
+            return Err(anyhow::anyhow!(
+                "Validator {} is outside of a tree of depth {}",
+                validator_index,
+                self.tree_depth
+            ));
+        }
+
+        self.mark_path(validator_index);
+
+        Ok(())
+    }
+
+    /// Marks the zero nodes every missing sibling falls back to.
+    pub fn mark_zero_nodes(&mut self) {
+        self.mark_path(VALIDATOR_REGISTRY_LIMIT);
+    }
+
+    pub fn is_dirty(&self, level: usize, index: usize) -> bool {
+        self.dirty_nodes[level].contains(&index)
+    }
+
+    /// Returns the jobs for every dirty node, level by level, so the children of
+    /// a node are always scheduled before it, and clears the dirty nodes.
+    pub fn take_jobs(&mut self) -> Vec<CommitmentMapperJob> {
+        let mut jobs = self.dirty_nodes[0]
+            .iter()
+            .map(|&validator_index| CommitmentMapperJob::Leaf { validator_index })
+            .collect::<Vec<_>>();
+
+        for level in 1..=self.levels() {
+            let child_size = self.node_size(level - 1);
+
+            jobs.extend(self.dirty_nodes[level].iter().map(|&left| {
+                let right = if left == VALIDATOR_REGISTRY_LIMIT {
+                    left
+                } else {
+                    left + child_size
+                };
+
+                CommitmentMapperJob::Inner {
+                    level: level - 1,
+                    left,
+                    right,
+                }
+            }));
+        }
+
+        for nodes in self.dirty_nodes.iter_mut() {
+            nodes.clear();
+        }
+
+        jobs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(validators_per_leaf: usize, tree_depth: usize) -> CommitmentMapperManifest {
+        CommitmentMapperManifest {
+            validators_per_leaf,
+            tree_depth,
+            cyclic: false,
+        }
+    }
+
+    #[test]
+    fn test_jobs_of_changed_validators() -> Result<()> {
+        let mut tree = CommitmentMapperTree::new(&manifest(1, 3));
+
+        tree.mark_changed_validator(2)?;
+        tree.mark_changed_validator(3)?;
+        tree.mark_changed_validator(6)?;
+
+        assert!(tree.mark_changed_validator(8).is_err());
+        assert!(tree.is_dirty(3, 0));
+
+        assert_eq!(
+            tree.take_jobs(),
+            vec![
+                CommitmentMapperJob::Leaf { validator_index: 2 },
+                CommitmentMapperJob::Leaf { validator_index: 3 },
+                CommitmentMapperJob::Leaf { validator_index: 6 },
+                CommitmentMapperJob::Inner {
+                    level: 0,
+                    left: 2,
+                    right: 3
+                },
+                CommitmentMapperJob::Inner {
+                    level: 0,
+                    left: 6,
+                    right: 7
+                },
+                CommitmentMapperJob::Inner {
+                    level: 1,
+                    left: 0,
+                    right: 2
+                },
+                CommitmentMapperJob::Inner {
+                    level: 1,
+                    left: 4,
+                    right: 6
+                },
+                CommitmentMapperJob::Inner {
+                    level: 2,
+                    left: 0,
+                    right: 4
+                },
+            ]
+        );
+
+        assert!(tree.take_jobs().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jobs_of_batched_leaves() -> Result<()> {
+        let mut tree = CommitmentMapperTree::new(&manifest(4, 4));
+
+        tree.mark_changed_validator(5)?;
+        tree.mark_changed_validator(6)?;
+        tree.mark_zero_nodes();
+
+        let jobs = tree.take_jobs();
+
+        assert_eq!(
+            jobs,
+            vec![
+                CommitmentMapperJob::Leaf { validator_index: 4 },
+                CommitmentMapperJob::Leaf {
+                    validator_index: VALIDATOR_REGISTRY_LIMIT
+                },
+                CommitmentMapperJob::Inner {
+                    level: 0,
+                    left: 0,
+                    right: 4
+                },
+                CommitmentMapperJob::Inner {
+                    level: 0,
+                    left: VALIDATOR_REGISTRY_LIMIT,
+                    right: VALIDATOR_REGISTRY_LIMIT
+                },
+                CommitmentMapperJob::Inner {
+                    level: 1,
+                    left: 0,
+                    right: 8
+                },
+                CommitmentMapperJob::Inner {
+                    level: 1,
+                    left: VALIDATOR_REGISTRY_LIMIT,
+                    right: VALIDATOR_REGISTRY_LIMIT
+                },
+            ]
+        );
+
+        assert_eq!(
+            CommitmentMapperJob::Leaf { validator_index: 6 }.proof_key(4),
+            (0, 4)
+        );
+        assert_eq!(jobs[4].proof_key(4), (2, 0));
+        assert_eq!(
+            jobs[4].to_bytes(),
+            [1u64, 0, 8]
+                .iter()
+                .flat_map(|value| value.to_be_bytes())
+                .collect::<Vec<u8>>()
+        );
+
+        Ok(())
+    }
+}
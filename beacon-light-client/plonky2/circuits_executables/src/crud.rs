@@ -1,8 +1,9 @@
 use std::{fs, marker::PhantomData, thread, time::Duration};
 
 use crate::{
+    pending_deposits_input::PendingDepositsInput,
     validator::{
-        bool_vec_as_int_vec, bool_vec_as_int_vec_nested, ValidatorShaInput,
+        bool_vec_as_int_vec, bool_vec_as_int_vec_nested, ValidatorShaInput, ValidatorsShaInput,
         VALIDATOR_REGISTRY_LIMIT,
     },
     validator_balances_input::ValidatorBalancesInput,
@@ -12,9 +13,12 @@ use anyhow::Result;
 use circuits::{
     build_commitment_mapper_first_level_circuit::CommitmentMapperProofExt,
     build_final_circuit::FinalCircuitProofExt,
+    build_pending_deposits_circuit::PendingDepositsProofExt,
+    build_pending_deposits_final_circuit::PendingDepositsFinalCircuitProofExt,
     build_validator_balance_circuit::{
         ValidatorBalanceProofExt,
     },
+    fork::Fork,
     generator_serializer::{DendrETHGateSerializer, DendrETHGeneratorSerializer},
 };
 use num::BigUint;
@@ -42,12 +46,38 @@ pub struct BalanceProof {
     pub needs_change: bool,
     #[serde(serialize_with = "biguint_to_str", deserialize_with = "parse_biguint")]
     pub range_total_value: BigUint,
+    #[serde(serialize_with = "biguint_to_str", deserialize_with = "parse_biguint")]
+    pub range_slashed_total_value: BigUint,
+    pub range_counted_validators: u64,
+    #[serde(serialize_with = "biguint_to_str", deserialize_with = "parse_biguint")]
+    pub range_effective_balance_total_value: BigUint,
+    #[serde(serialize_with = "biguint_to_str", deserialize_with = "parse_biguint")]
+    pub range_pending_total_value: BigUint,
+    #[serde(serialize_with = "biguint_to_str", deserialize_with = "parse_biguint")]
+    pub range_active_total_value: BigUint,
+    #[serde(serialize_with = "biguint_to_str", deserialize_with = "parse_biguint")]
+    pub range_exited_total_value: BigUint,
+    #[serde(serialize_with = "biguint_to_str", deserialize_with = "parse_biguint")]
+    pub range_withdrawable_total_value: BigUint,
     pub validators_commitment: Vec<u64>,
     pub balances_hash: Vec<u64>,
-    #[serde(serialize_with = "biguint_to_str", deserialize_with = "parse_biguint")]
-    pub withdrawal_credentials: BigUint,
+    pub withdrawal_credentials_commitment: Vec<u64>,
     #[serde(serialize_with = "biguint_to_str", deserialize_with = "parse_biguint")]
     pub current_epoch: BigUint,
+    #[serde(serialize_with = "biguint_to_str", deserialize_with = "parse_biguint")]
+    pub range_valid_until_epoch: BigUint,
+    pub proof: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingDepositsProof {
+    pub needs_change: bool,
+    #[serde(serialize_with = "biguint_to_str", deserialize_with = "parse_biguint")]
+    pub range_total_value: BigUint,
+    pub range_deposits_root: Vec<u64>,
+    pub withdrawal_credentials_commitment: Vec<u64>,
+    pub range_deposits_count: u64,
     pub proof: Vec<u8>,
 }
 
@@ -70,6 +100,33 @@ where
         .map_err(serde::de::Error::custom)
 }
 
+pub fn biguint_vec_to_str<S>(values: &Vec<BigUint>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    values
+        .iter()
+        .map(|value| value.to_str_radix(10))
+        .collect::<Vec<String>>()
+        .serialize(serializer)
+}
+
+pub fn parse_biguint_vec<'de, D>(deserializer: D) -> Result<Vec<BigUint>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let str_values = Vec::<String>::deserialize(deserializer)?;
+
+    str_values
+        .iter()
+        .map(|str_value| {
+            str_value
+                .parse::<BigUint>()
+                .map_err(serde::de::Error::custom)
+        })
+        .collect()
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct FinalCircuitInput {
@@ -78,15 +135,41 @@ pub struct FinalCircuitInput {
     #[serde(serialize_with = "biguint_to_str", deserialize_with = "parse_biguint")]
     pub slot: BigUint,
     #[serde(with = "bool_vec_as_int_vec_nested")]
-    pub slot_branch: Vec<Vec<bool>>,
-    #[serde(serialize_with = "biguint_to_str", deserialize_with = "parse_biguint")]
-    pub withdrawal_credentials: BigUint,
+    pub state_root_multiproof: Vec<Vec<bool>>,
+    #[serde(with = "bool_vec_as_int_vec")]
+    pub validators_size_bits: Vec<bool>,
+    #[serde(default)]
+    pub block_header: Option<BlockHeaderInput>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoricalStateRootInput {
+    #[serde(with = "bool_vec_as_int_vec")]
+    pub state_root: Vec<bool>,
     #[serde(with = "bool_vec_as_int_vec_nested")]
-    pub balance_branch: Vec<Vec<bool>>,
+    pub branch: Vec<Vec<bool>>,
+    pub state_roots_index: u64,
+    #[serde(default)]
+    pub historical_summary_index: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingDepositsLayerInput {
     #[serde(with = "bool_vec_as_int_vec_nested")]
-    pub validators_branch: Vec<Vec<bool>>,
+    pub branch: Vec<Vec<bool>>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockHeaderInput {
+    #[serde(serialize_with = "biguint_to_str", deserialize_with = "parse_biguint")]
+    pub proposer_index: BigUint,
     #[serde(with = "bool_vec_as_int_vec")]
-    pub validators_size_bits: Vec<bool>,
+    pub parent_root: Vec<bool>,
+    #[serde(with = "bool_vec_as_int_vec")]
+    pub body_root: Vec<bool>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -94,8 +177,38 @@ pub struct FinalCircuitInput {
 pub struct FinalProof {
     pub needs_change: bool,
     pub state_root: Vec<u64>,
-    pub withdrawal_credentials: BigUint,
+    pub withdrawal_credentials_commitment: Vec<u64>,
     pub balance_sum: BigUint,
+    pub slashed_balance_sum: BigUint,
+    pub counted_validators: u64,
+    pub effective_balance_sum: BigUint,
+    pub pending_balance_sum: BigUint,
+    pub active_balance_sum: BigUint,
+    pub exited_balance_sum: BigUint,
+    pub withdrawable_balance_sum: BigUint,
+    pub block_root: Option<Vec<u64>>,
+    pub block_slot: Option<BigUint>,
+    pub proof: Vec<u8>,
+}
+
+// A final proof whose only public inputs are the SHA-256 commitment words
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CommittedFinalProof {
+    pub needs_change: bool,
+    pub public_inputs_commitment: Vec<u32>,
+    pub proof: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingDepositsLayerProof {
+    pub needs_change: bool,
+    pub state_root: Vec<u64>,
+    pub withdrawal_credentials_commitment: Vec<u64>,
+    pub balance_sum: BigUint,
+    pub pending_deposits_sum: BigUint,
+    pub total_sum: BigUint,
     pub proof: Vec<u8>,
 }
 
@@ -147,6 +260,24 @@ impl ProofProvider for BalanceProof {
     }
 }
 
+impl NeedsChange for PendingDepositsProof {
+    fn needs_change(&self) -> bool {
+        self.needs_change
+    }
+}
+
+impl KeyProvider for PendingDepositsProof {
+    fn get_key() -> String {
+        get_validator_commitment_constants().pending_deposits_proof_key
+    }
+}
+
+impl ProofProvider for PendingDepositsProof {
+    fn get_proof(&self) -> Vec<u8> {
+        self.proof.clone()
+    }
+}
+
 pub async fn fetch_validator_balance_input(
     con: &mut Connection,
     index: usize,
@@ -164,6 +295,80 @@ pub async fn fetch_validator_balance_input(
     Ok(validator_balance_input)
 }
 
+pub async fn fetch_validator_balance_input_if_exists(
+    con: &mut Connection,
+    index: usize,
+) -> Result<Option<ValidatorBalancesInput>> {
+    let json_str: Option<String> = con
+        .get(format!(
+            "{}:{}",
+            get_validator_commitment_constants().validator_balance_input_key,
+            index
+        ))
+        .await?;
+
+    match json_str {
+        Some(json_str) => Ok(Some(serde_json::from_str(&json_str)?)),
+        None => Ok(None),
+    }
+}
+
+/// Keeps the input the first level proof at `index` was made from, to tell
+/// whether the input of the next epoch has changed.
+pub async fn save_proven_validator_balance_input(
+    con: &mut Connection,
+    index: usize,
+    validator_balance_input: &ValidatorBalancesInput,
+) -> Result<()> {
+    let _: () = con
+        .set(
+            format!(
+                "{}:{}",
+                get_validator_commitment_constants().proven_validator_balance_input_key,
+                index
+            ),
+            serde_json::to_string(validator_balance_input)?,
+        )
+        .await?;
+
+    Ok(())
+}
+
+pub async fn fetch_proven_validator_balance_input(
+    con: &mut Connection,
+    index: usize,
+) -> Result<Option<ValidatorBalancesInput>> {
+    let json_str: Option<String> = con
+        .get(format!(
+            "{}:{}",
+            get_validator_commitment_constants().proven_validator_balance_input_key,
+            index
+        ))
+        .await?;
+
+    match json_str {
+        Some(json_str) => Ok(Some(serde_json::from_str(&json_str)?)),
+        None => Ok(None),
+    }
+}
+
+pub async fn fetch_pending_deposits_input(
+    con: &mut Connection,
+    index: usize,
+) -> Result<PendingDepositsInput> {
+    let json_str: String = con
+        .get(format!(
+            "{}:{}",
+            get_validator_commitment_constants().pending_deposits_input_key,
+            index
+        ))
+        .await?;
+
+    let pending_deposits_input: PendingDepositsInput = serde_json::from_str(&json_str)?;
+
+    Ok(pending_deposits_input)
+}
+
 pub async fn fetch_final_layer_input(con: &mut Connection) -> Result<FinalCircuitInput> {
     let json_str: String = con
         .get(get_validator_commitment_constants().final_proof_input_key)
@@ -174,6 +379,59 @@ pub async fn fetch_final_layer_input(con: &mut Connection) -> Result<FinalCircui
     Ok(final_layer_input)
 }
 
+pub async fn fetch_historical_state_root_input(
+    con: &mut Connection,
+) -> Result<HistoricalStateRootInput> {
+    let json_str: String = con
+        .get(get_validator_commitment_constants().historical_state_root_input_key)
+        .await?;
+
+    let historical_state_root_input: HistoricalStateRootInput = serde_json::from_str(&json_str)?;
+
+    Ok(historical_state_root_input)
+}
+
+pub async fn fetch_pending_deposits_layer_input(
+    con: &mut Connection,
+) -> Result<PendingDepositsLayerInput> {
+    let json_str: String = con
+        .get(get_validator_commitment_constants().pending_deposits_layer_input_key)
+        .await?;
+
+    let pending_deposits_layer_input: PendingDepositsLayerInput = serde_json::from_str(&json_str)?;
+
+    Ok(pending_deposits_layer_input)
+}
+
+pub async fn fetch_final_proof(con: &mut Connection) -> Result<FinalProof> {
+    let json_str: String = con
+        .get(get_validator_commitment_constants().final_layer_proof_key)
+        .await?;
+
+    let final_proof: FinalProof = serde_json::from_str(&json_str)?;
+
+    Ok(final_proof)
+}
+
+pub async fn fetch_committed_final_proof(con: &mut Connection) -> Result<CommittedFinalProof> {
+    let json_str: String = con
+        .get(get_validator_commitment_constants().final_layer_proof_key)
+        .await?;
+
+    let final_proof: CommittedFinalProof = serde_json::from_str(&json_str)?;
+
+    Ok(final_proof)
+}
+
+// Every stored proof is a JSON object with the proof bytes under `proof`
+pub async fn fetch_proof_bytes(con: &mut Connection, key: &str) -> Result<Vec<u8>> {
+    let json_str: String = con.get(key).await?;
+
+    let proof: serde_json::Value = serde_json::from_str(&json_str)?;
+
+    Ok(serde_json::from_value(proof["proof"].clone())?)
+}
+
 pub async fn save_balance_proof(
     con: &mut Connection,
     proof: ProofWithPublicInputs<GoldilocksField, PoseidonGoldilocksConfig, 2>,
@@ -183,10 +441,18 @@ pub async fn save_balance_proof(
     let balance_proof = serde_json::to_string(&BalanceProof {
         needs_change: false,
         range_total_value: proof.get_range_total_value(),
+        range_slashed_total_value: proof.get_range_slashed_total_value(),
+        range_counted_validators: proof.get_range_counted_validators(),
+        range_effective_balance_total_value: proof.get_range_effective_balance_total_value(),
+        range_pending_total_value: proof.get_range_pending_total_value(),
+        range_active_total_value: proof.get_range_active_total_value(),
+        range_exited_total_value: proof.get_range_exited_total_value(),
+        range_withdrawable_total_value: proof.get_range_withdrawable_total_value(),
         balances_hash: proof.get_range_balances_root().to_vec(),
-        withdrawal_credentials: proof.get_withdrawal_credentials(),
+        withdrawal_credentials_commitment: proof.get_withdrawal_credentials_commitment().to_vec(),
         validators_commitment: proof.get_range_validator_commitment().to_vec(),
         current_epoch: proof.get_current_epoch(),
+        range_valid_until_epoch: proof.get_range_valid_until_epoch(),
         proof: proof.to_bytes(),
     })?;
 
@@ -205,15 +471,170 @@ pub async fn save_balance_proof(
     Ok(())
 }
 
-pub async fn save_final_proof(
+/// Replaces the proof with an empty one that needs change, so the jobs using it
+/// wait for it to be proven again.
+pub async fn invalidate_balance_proof(
+    con: &mut Connection,
+    depth: usize,
+    index: usize,
+) -> Result<()> {
+    let balance_proof = serde_json::to_string(&BalanceProof {
+        needs_change: true,
+        range_total_value: BigUint::from(0u64),
+        range_slashed_total_value: BigUint::from(0u64),
+        range_counted_validators: 0,
+        range_effective_balance_total_value: BigUint::from(0u64),
+        range_pending_total_value: BigUint::from(0u64),
+        range_active_total_value: BigUint::from(0u64),
+        range_exited_total_value: BigUint::from(0u64),
+        range_withdrawable_total_value: BigUint::from(0u64),
+        validators_commitment: vec![],
+        balances_hash: vec![],
+        withdrawal_credentials_commitment: vec![],
+        current_epoch: BigUint::from(0u64),
+        range_valid_until_epoch: BigUint::from(0u64),
+        proof: vec![],
+    })?;
+
+    let _: () = con
+        .set(
+            format!(
+                "{}:{}:{}",
+                get_validator_commitment_constants().balance_verification_proof_key,
+                depth,
+                index
+            ),
+            balance_proof,
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Unlike `fetch_proof`, neither waits for the proof nor falls back to the zero
+/// proof. Placeholders that don't hold the public inputs count as missing.
+pub async fn fetch_balance_proof_if_exists(
+    con: &mut Connection,
+    depth: usize,
+    index: usize,
+) -> Result<Option<BalanceProof>> {
+    let json_str: Option<String> = con
+        .get(format!(
+            "{}:{}:{}",
+            get_validator_commitment_constants().balance_verification_proof_key,
+            depth,
+            index
+        ))
+        .await?;
+
+    Ok(json_str.and_then(|json_str| serde_json::from_str(&json_str).ok()))
+}
+
+/// The epoch the inner levels of the balance tree are currently proven for.
+pub async fn save_balance_verification_epoch(
+    con: &mut Connection,
+    current_epoch: &BigUint,
+) -> Result<()> {
+    let _: () = con
+        .set(
+            get_validator_commitment_constants().balance_verification_epoch_key,
+            current_epoch.to_string(),
+        )
+        .await?;
+
+    Ok(())
+}
+
+pub async fn fetch_balance_verification_epoch(con: &mut Connection) -> Result<Option<BigUint>> {
+    let epoch_str: Option<String> = con
+        .get(get_validator_commitment_constants().balance_verification_epoch_key)
+        .await?;
+
+    match epoch_str {
+        Some(epoch_str) => Ok(Some(epoch_str.parse::<BigUint>()?)),
+        None => Ok(None),
+    }
+}
+
+pub async fn save_pending_deposits_proof(
+    con: &mut Connection,
+    proof: ProofWithPublicInputs<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+    depth: usize,
+    index: usize,
+) -> Result<()> {
+    let pending_deposits_proof = serde_json::to_string(&PendingDepositsProof {
+        needs_change: false,
+        range_total_value: proof.get_pending_deposits_total_value(),
+        range_deposits_root: proof.get_pending_deposits_root().to_vec(),
+        withdrawal_credentials_commitment: proof
+            .get_pending_deposits_withdrawal_credentials_commitment()
+            .to_vec(),
+        range_deposits_count: proof.get_pending_deposits_count(),
+        proof: proof.to_bytes(),
+    })?;
+
+    let _: () = con
+        .set(
+            format!(
+                "{}:{}:{}",
+                get_validator_commitment_constants().pending_deposits_proof_key,
+                depth,
+                index
+            ),
+            pending_deposits_proof,
+        )
+        .await?;
+
+    Ok(())
+}
+
+pub async fn save_pending_deposits_layer_proof(
     con: &mut redis::aio::Connection,
     proof: &ProofWithPublicInputs<GoldilocksField, PoseidonGoldilocksConfig, 2>,
 ) -> Result<()> {
-    let final_proof = serde_json::to_string(&FinalProof {
+    let pending_deposits_layer_proof = serde_json::to_string(&PendingDepositsLayerProof {
         needs_change: false,
         state_root: proof.get_final_circuit_state_root().to_vec(),
-        withdrawal_credentials: proof.get_final_circuit_withdrawal_credentials(),
+        withdrawal_credentials_commitment: proof
+            .get_final_circuit_withdrawal_credentials_commitment()
+            .to_vec(),
         balance_sum: proof.get_final_circuit_balance_sum(),
+        pending_deposits_sum: proof.get_pending_deposits_final_circuit_pending_deposits_sum(),
+        total_sum: proof.get_pending_deposits_final_circuit_total_sum(),
+        proof: proof.to_bytes(),
+    })?;
+
+    let _: () = con
+        .set(
+            get_validator_commitment_constants().pending_deposits_layer_proof_key,
+            pending_deposits_layer_proof,
+        )
+        .await?;
+
+    Ok(())
+}
+
+pub async fn save_final_proof(
+    con: &mut redis::aio::Connection,
+    proof: &ProofWithPublicInputs<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+    anchor_to_block_root: bool,
+) -> Result<()> {
+    save_final_proof_with_key(
+        con,
+        get_validator_commitment_constants().final_layer_proof_key,
+        proof,
+        anchor_to_block_root,
+    )
+    .await
+}
+
+pub async fn save_committed_final_proof(
+    con: &mut redis::aio::Connection,
+    proof: &ProofWithPublicInputs<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+) -> Result<()> {
+    let final_proof = serde_json::to_string(&CommittedFinalProof {
+        needs_change: false,
+        public_inputs_commitment: proof.get_final_circuit_public_inputs_commitment().to_vec(),
         proof: proof.to_bytes(),
     })?;
 
@@ -227,6 +648,49 @@ pub async fn save_final_proof(
     Ok(())
 }
 
+pub async fn save_historical_state_root_proof(
+    con: &mut redis::aio::Connection,
+    proof: &ProofWithPublicInputs<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+) -> Result<()> {
+    save_final_proof_with_key(
+        con,
+        get_validator_commitment_constants().historical_layer_proof_key,
+        proof,
+        false,
+    )
+    .await
+}
+
+async fn save_final_proof_with_key(
+    con: &mut redis::aio::Connection,
+    key: String,
+    proof: &ProofWithPublicInputs<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+    anchor_to_block_root: bool,
+) -> Result<()> {
+    let final_proof = serde_json::to_string(&FinalProof {
+        needs_change: false,
+        state_root: proof.get_final_circuit_state_root().to_vec(),
+        withdrawal_credentials_commitment: proof
+            .get_final_circuit_withdrawal_credentials_commitment()
+            .to_vec(),
+        balance_sum: proof.get_final_circuit_balance_sum(),
+        slashed_balance_sum: proof.get_final_circuit_slashed_balance_sum(),
+        counted_validators: proof.get_final_circuit_counted_validators(),
+        effective_balance_sum: proof.get_final_circuit_effective_balance_sum(),
+        pending_balance_sum: proof.get_final_circuit_pending_balance_sum(),
+        active_balance_sum: proof.get_final_circuit_active_balance_sum(),
+        exited_balance_sum: proof.get_final_circuit_exited_balance_sum(),
+        withdrawable_balance_sum: proof.get_final_circuit_withdrawable_balance_sum(),
+        block_root: anchor_to_block_root.then(|| proof.get_final_circuit_block_root().to_vec()),
+        block_slot: anchor_to_block_root.then(|| proof.get_final_circuit_block_slot()),
+        proof: proof.to_bytes(),
+    })?;
+
+    let _: () = con.set(key, final_proof).await?;
+
+    Ok(())
+}
+
 pub async fn fetch_validator(
     con: &mut Connection,
     validator_index: usize,
@@ -243,6 +707,46 @@ pub async fn fetch_validator(
     Ok(validator)
 }
 
+/// Fetches the validators at `first_index..first_index + validators_len`. The
+/// ones that are not stored are past the end of the registry and are zero.
+pub async fn fetch_validators(
+    con: &mut Connection,
+    first_index: usize,
+    validators_len: usize,
+) -> Result<ValidatorsShaInput> {
+    let mut validators = Vec::with_capacity(validators_len);
+    let mut validator_is_zero = Vec::with_capacity(validators_len);
+
+    for validator_index in first_index..first_index + validators_len {
+        let json_str: Option<String> = if validator_index < VALIDATOR_REGISTRY_LIMIT {
+            con.get(format!(
+                "{}:{}",
+                get_validator_commitment_constants().validator_key,
+                validator_index
+            ))
+            .await?
+        } else {
+            None
+        };
+
+        match json_str {
+            Some(json_str) => {
+                validators.push(serde_json::from_str(&json_str)?);
+                validator_is_zero.push(false);
+            }
+            None => {
+                validators.push(ValidatorShaInput::zero());
+                validator_is_zero.push(true);
+            }
+        }
+    }
+
+    Ok(ValidatorsShaInput {
+        validators,
+        validator_is_zero,
+    })
+}
+
 pub async fn save_validator_proof(
     con: &mut Connection,
     proof: ProofWithPublicInputs<GoldilocksField, PoseidonGoldilocksConfig, 2>,
@@ -271,6 +775,50 @@ pub async fn save_validator_proof(
     Ok(())
 }
 
+/// Replaces the proof with an empty one that needs change, so the jobs using it
+/// wait for it to be proven again.
+pub async fn invalidate_validator_proof(
+    con: &mut Connection,
+    depth: usize,
+    index: usize,
+) -> Result<()> {
+    let validator_proof = serde_json::to_string(&ValidatorProof {
+        poseidon_hash: vec![],
+        sha256_hash: vec![],
+        proof: vec![],
+        needs_change: true,
+    })?;
+
+    let _: () = con
+        .set(
+            format!(
+                "{}:{}:{}",
+                get_validator_commitment_constants().validator_proof_key,
+                depth,
+                index
+            ),
+            validator_proof,
+        )
+        .await?;
+
+    Ok(())
+}
+
+pub async fn validator_proof_exists(
+    con: &mut Connection,
+    depth: usize,
+    index: usize,
+) -> Result<bool> {
+    Ok(con
+        .exists(format!(
+            "{}:{}:{}",
+            get_validator_commitment_constants().validator_proof_key,
+            depth,
+            index
+        ))
+        .await?)
+}
+
 pub async fn fetch_proof<T: NeedsChange + KeyProvider + DeserializeOwned>(
     con: &mut Connection,
     depth: usize,
@@ -323,6 +871,22 @@ pub async fn fetch_proofs<T: NeedsChange + KeyProvider + ProofProvider + Deseria
     Ok((proof1.get_proof(), proof2.get_proof()))
 }
 
+/// Fetches the proofs at `indexes[1..]` on level `indexes[0]`.
+pub async fn fetch_children_proofs<
+    T: NeedsChange + KeyProvider + ProofProvider + DeserializeOwned,
+>(
+    con: &mut Connection,
+    indexes: &Vec<usize>,
+) -> Result<Vec<Vec<u8>>> {
+    let mut proofs = Vec::with_capacity(indexes.len() - 1);
+
+    for &index in &indexes[1..] {
+        proofs.push(fetch_proof::<T>(con, indexes[0], index).await?.get_proof());
+    }
+
+    Ok(proofs)
+}
+
 pub fn read_from_file(file_path: &str) -> Result<Vec<u8>> {
     let data = fs::read(file_path)?;
     Ok(data)
@@ -352,3 +916,47 @@ pub fn load_circuit_data(
         .unwrap(),
     )
 }
+
+pub fn save_circuit_data(
+    file_name: &str,
+    circuit_data: &CircuitData<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+) -> Result<()> {
+    let gate_serializer = DendrETHGateSerializer;
+    let generator_serializer = DendrETHGeneratorSerializer {
+        _phantom: PhantomData::<PoseidonGoldilocksConfig>,
+    };
+
+    let circuit_data_bytes = circuit_data
+        .to_bytes(&gate_serializer, &generator_serializer)
+        .unwrap();
+
+    write_to_file(
+        &format!("{}.plonky2_circuit", file_name),
+        &circuit_data_bytes,
+    )
+}
+
+pub const COMMITMENT_MAPPER_CYCLIC_CIRCUIT: &str = "commitment_mapper_cyclic";
+
+// The final circuit differs per fork, so each fork gets its own artifact
+pub fn final_layer_circuit_name(
+    fork: Fork,
+    anchor_to_block_root: bool,
+    commit_public_inputs: bool,
+) -> String {
+    let mut name = format!("final_layer_{}", fork.name());
+
+    if anchor_to_block_root {
+        name.push_str("_anchored");
+    }
+
+    if commit_public_inputs {
+        name.push_str("_committed");
+    }
+
+    name
+}
+
+pub fn pending_deposits_circuit_name(level: usize) -> String {
+    format!("pending_deposits_{}", level)
+}
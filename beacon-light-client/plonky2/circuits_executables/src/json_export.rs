@@ -0,0 +1,133 @@
+use std::fs;
+
+use anyhow::Result;
+use plonky2::{
+    field::goldilocks_field::GoldilocksField,
+    plonk::{
+        circuit_data::{CommonCircuitData, VerifierOnlyCircuitData},
+        config::GenericConfig,
+        proof::ProofWithPublicInputs,
+    },
+};
+
+use crate::crud::write_to_file;
+
+// The file names expected by the gnark Plonky2 verifier
+pub const VERIFIER_ONLY_CIRCUIT_DATA_FILE: &str = "verifier_only_circuit_data.json";
+pub const COMMON_CIRCUIT_DATA_FILE: &str = "common_circuit_data.json";
+pub const PROOF_WITH_PUBLIC_INPUTS_FILE: &str = "proof_with_public_inputs.json";
+
+/// The constants and sigmas cap and the circuit digest.
+pub fn verifier_only_circuit_data_to_json<C: GenericConfig<2, F = GoldilocksField>>(
+    verifier_only: &VerifierOnlyCircuitData<C, 2>,
+) -> Result<String> {
+    Ok(serde_json::to_string(verifier_only)?)
+}
+
+/// The circuit config, the FRI params (including the reduction arity bits that
+/// define the layout of the query rounds) and the gates with their selectors.
+pub fn common_circuit_data_to_json(
+    common: &CommonCircuitData<GoldilocksField, 2>,
+) -> Result<String> {
+    Ok(serde_json::to_string(common)?)
+}
+
+/// The public inputs, the wires, partial products and quotient caps, the
+/// openings and the FRI proof with every query round.
+pub fn proof_to_json<C: GenericConfig<2, F = GoldilocksField>>(
+    proof: &ProofWithPublicInputs<GoldilocksField, C, 2>,
+) -> Result<String> {
+    Ok(serde_json::to_string(proof)?)
+}
+
+pub fn export_circuit_data_json<C: GenericConfig<2, F = GoldilocksField>>(
+    output_dir: &str,
+    verifier_only: &VerifierOnlyCircuitData<C, 2>,
+    common: &CommonCircuitData<GoldilocksField, 2>,
+) -> Result<()> {
+    fs::create_dir_all(output_dir)?;
+
+    write_to_file(
+        &format!("{}/{}", output_dir, VERIFIER_ONLY_CIRCUIT_DATA_FILE),
+        verifier_only_circuit_data_to_json(verifier_only)?.as_bytes(),
+    )?;
+
+    write_to_file(
+        &format!("{}/{}", output_dir, COMMON_CIRCUIT_DATA_FILE),
+        common_circuit_data_to_json(common)?.as_bytes(),
+    )
+}
+
+pub fn export_proof_json<C: GenericConfig<2, F = GoldilocksField>>(
+    output_dir: &str,
+    proof: &ProofWithPublicInputs<GoldilocksField, C, 2>,
+) -> Result<()> {
+    fs::create_dir_all(output_dir)?;
+
+    write_to_file(
+        &format!("{}/{}", output_dir, PROOF_WITH_PUBLIC_INPUTS_FILE),
+        proof_to_json(proof)?.as_bytes(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plonky2::{
+        field::types::Field,
+        iop::witness::{PartialWitness, WitnessWrite},
+        plonk::{
+            circuit_builder::CircuitBuilder, circuit_data::CircuitConfig,
+            config::PoseidonGoldilocksConfig,
+        },
+    };
+    use serde_json::Value;
+
+    #[test]
+    fn test_export_json() -> Result<()> {
+        type F = GoldilocksField;
+
+        let mut builder = CircuitBuilder::<F, 2>::new(CircuitConfig::standard_recursion_config());
+
+        let a = builder.add_virtual_target();
+        let b = builder.add_virtual_target();
+        let c = builder.mul(a, b);
+
+        builder.register_public_input(c);
+
+        let data = builder.build::<PoseidonGoldilocksConfig>();
+
+        let mut pw = PartialWitness::new();
+
+        pw.set_target(a, F::from_canonical_u64(6));
+        pw.set_target(b, F::from_canonical_u64(7));
+
+        let proof = data.prove(pw)?;
+
+        let verifier_only: Value =
+            serde_json::from_str(&verifier_only_circuit_data_to_json(&data.verifier_only)?)?;
+
+        assert!(verifier_only.get("constants_sigmas_cap").is_some());
+        assert!(verifier_only.get("circuit_digest").is_some());
+
+        let common: Value = serde_json::from_str(&common_circuit_data_to_json(&data.common)?)?;
+
+        assert_eq!(
+            common["fri_params"]["reduction_arity_bits"],
+            serde_json::to_value(&data.common.fri_params.reduction_arity_bits)?
+        );
+
+        let proof_json: Value = serde_json::from_str(&proof_to_json(&proof)?)?;
+
+        assert_eq!(proof_json["public_inputs"], serde_json::json!([42]));
+        assert_eq!(
+            proof_json["proof"]["opening_proof"]["query_round_proofs"]
+                .as_array()
+                .unwrap()
+                .len(),
+            data.common.config.fri_config.num_query_rounds
+        );
+
+        Ok(())
+    }
+}
@@ -1,5 +1,11 @@
+pub mod balance_verification_scheduler;
+pub mod circuits_manifest;
+pub mod commitment_mapper_scheduler;
 pub mod crud;
+pub mod json_export;
+pub mod pending_deposits_input;
 pub mod provers;
+pub mod queue_worker;
 pub mod validator;
 pub mod validator_balances_input;
 pub mod validator_commitment_constants;
@@ -0,0 +1,61 @@
+use crate::{
+    crud::{biguint_to_str, biguint_vec_to_str, parse_biguint, parse_biguint_vec},
+    validator::bool_vec_as_int_vec,
+};
+use num::BigUint;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingDepositInput {
+    #[serde(with = "bool_vec_as_int_vec")]
+    pub pubkey: Vec<bool>,
+    #[serde(with = "bool_vec_as_int_vec")]
+    pub withdrawal_credentials: Vec<bool>,
+    #[serde(serialize_with = "biguint_to_str", deserialize_with = "parse_biguint")]
+    pub amount: BigUint,
+    #[serde(with = "bool_vec_as_int_vec")]
+    pub signature: Vec<bool>,
+    #[serde(serialize_with = "biguint_to_str", deserialize_with = "parse_biguint")]
+    pub slot: BigUint,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingDepositsInput {
+    pub pending_deposits: Vec<PendingDepositInput>,
+    #[serde(with = "bool_vec_as_int_vec")]
+    pub deposit_is_zero: Vec<bool>,
+    #[serde(
+        serialize_with = "biguint_vec_to_str",
+        deserialize_with = "parse_biguint_vec"
+    )]
+    pub withdrawal_credentials: Vec<BigUint>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn test_serialize_deserialize() {
+        let input = PendingDepositsInput {
+            pending_deposits: vec![PendingDepositInput {
+                pubkey: vec![true, false],
+                withdrawal_credentials: vec![false, true],
+                amount: BigUint::from(32000000000u64),
+                signature: vec![true, true],
+                slot: BigUint::from(7u64),
+            }],
+            deposit_is_zero: vec![false],
+            withdrawal_credentials: vec![BigUint::from(30u64)],
+        };
+
+        let serialized = serde_json::to_string(&input).unwrap();
+
+        let deserialized: PendingDepositsInput = serde_json::from_str(&serialized).unwrap();
+
+        assert_eq!(input, deserialized);
+    }
+}
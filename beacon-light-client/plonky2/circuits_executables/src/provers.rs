@@ -1,32 +1,42 @@
 use circuits::{
     biguint::WitnessBigUint, build_balance_inner_level_circuit::BalanceInnerCircuitTargets,
+    build_commitment_mapper_cyclic_circuit::CommitmentMapperCyclicCircuitTargets,
     build_commitment_mapper_inner_level_circuit::CommitmentMapperInnerCircuitTargets,
     build_final_circuit::FinalCircuitTargets,
+    build_historical_state_root_circuit::HistoricalStateRootCircuitTargets,
+    build_pending_deposits_final_circuit::PendingDepositsFinalCircuitTargets,
+    build_pending_deposits_inner_level_circuit::PendingDepositsInnerCircuitTargets,
+    pending_deposits_circuit::PendingDepositsVerificationTargets,
     validator_balance_circuit::ValidatorBalanceVerificationTargets,
+    validator_commitment_mapper::ValidatorsCommitmentTargets,
     validator_hash_tree_root::ValidatorShaTargets,
     validator_hash_tree_root_poseidon::ValidatorPoseidonTargets,
 };
 
 use plonky2::{
-    field::goldilocks_field::GoldilocksField,
+    field::{goldilocks_field::GoldilocksField, types::Field},
     iop::{
         target::BoolTarget,
         witness::{PartialWitness, WitnessWrite},
     },
     plonk::{
-        circuit_data::{CircuitData, VerifierCircuitTarget},
+        circuit_data::CircuitData,
         config::PoseidonGoldilocksConfig,
         proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget},
     },
+    recursion::dummy_circuit::cyclic_base_proof,
 };
 
 use crate::{
-    crud::FinalCircuitInput,
-    validator::ValidatorShaInput,
+    crud::{FinalCircuitInput, HistoricalStateRootInput, PendingDepositsLayerInput},
+    pending_deposits_input::PendingDepositsInput,
+    validator::{ValidatorShaInput, ValidatorsShaInput},
     validator_balances_input::{ValidatorBalancesInput, ValidatorPoseidonInput},
 };
 
 use anyhow::Result;
+use num::BigUint;
+use std::collections::HashMap;
 
 pub fn handle_generic_inner_level_proof(
     proof1_bytes: Vec<u8>,
@@ -34,7 +44,6 @@ pub fn handle_generic_inner_level_proof(
     inner_circuit_data: &CircuitData<GoldilocksField, PoseidonGoldilocksConfig, 2>,
     proof1_target: &ProofWithPublicInputsTarget<2>,
     proof2_target: &ProofWithPublicInputsTarget<2>,
-    verifier_circuit_target: &VerifierCircuitTarget,
     is_zero_target: Option<BoolTarget>,
     is_zero: Option<bool>,
     circuit_data: &CircuitData<GoldilocksField, PoseidonGoldilocksConfig, 2>,
@@ -56,16 +65,6 @@ pub fn handle_generic_inner_level_proof(
     pw.set_proof_with_pis_target(proof1_target, &inner_proof1);
     pw.set_proof_with_pis_target(proof2_target, &inner_proof2);
 
-    pw.set_cap_target(
-        &verifier_circuit_target.constants_sigmas_cap,
-        &inner_circuit_data.verifier_only.constants_sigmas_cap,
-    );
-
-    pw.set_hash_target(
-        verifier_circuit_target.circuit_digest,
-        inner_circuit_data.verifier_only.circuit_digest,
-    );
-
     if let (Some(target), Some(value)) = (is_zero_target, is_zero) {
         pw.set_bool_target(target, value);
     }
@@ -87,19 +86,137 @@ pub fn handle_commitment_mapper_inner_level_proof(
         inner_circuit_data,
         &inner_circuit_targets.proof1,
         &inner_circuit_targets.proof2,
-        &inner_circuit_targets.verifier_circuit_target,
         Some(inner_circuit_targets.is_zero),
         Some(is_zero),
         circuit_data,
     )
 }
 
-pub fn handle_balance_inner_level_proof(
+// Leaf proofs are verified when `is_leaf_level` and cyclic proofs otherwise. The
+// other pair of proof targets is filled with dummy proofs.
+pub fn handle_commitment_mapper_cyclic_proof(
     proof1_bytes: Vec<u8>,
     proof2_bytes: Vec<u8>,
+    leaf_circuit_data: &CircuitData<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+    cyclic_circuit_targets: &CommitmentMapperCyclicCircuitTargets,
+    cyclic_circuit_data: &CircuitData<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+    is_leaf_level: bool,
+    is_zero: bool,
+) -> Result<ProofWithPublicInputs<GoldilocksField, PoseidonGoldilocksConfig, 2>> {
+    let children_common_data = if is_leaf_level {
+        &leaf_circuit_data.common
+    } else {
+        &cyclic_circuit_data.common
+    };
+
+    let inner_proof1 =
+        ProofWithPublicInputs::<GoldilocksField, PoseidonGoldilocksConfig, 2>::from_bytes(
+            proof1_bytes,
+            children_common_data,
+        )?;
+
+    let inner_proof2 =
+        ProofWithPublicInputs::<GoldilocksField, PoseidonGoldilocksConfig, 2>::from_bytes(
+            proof2_bytes,
+            children_common_data,
+        )?;
+
+    let (proof_targets, dummy_proof_targets, dummy_proof) = if is_leaf_level {
+        (
+            [
+                &cyclic_circuit_targets.leaf_proof1,
+                &cyclic_circuit_targets.leaf_proof2,
+            ],
+            [
+                &cyclic_circuit_targets.cyclic_proof1,
+                &cyclic_circuit_targets.cyclic_proof2,
+            ],
+            cyclic_base_proof(
+                &cyclic_circuit_data.common,
+                &cyclic_circuit_data.verifier_only,
+                HashMap::new(),
+            ),
+        )
+    } else {
+        (
+            [
+                &cyclic_circuit_targets.cyclic_proof1,
+                &cyclic_circuit_targets.cyclic_proof2,
+            ],
+            [
+                &cyclic_circuit_targets.leaf_proof1,
+                &cyclic_circuit_targets.leaf_proof2,
+            ],
+            cyclic_base_proof(
+                &leaf_circuit_data.common,
+                &leaf_circuit_data.verifier_only,
+                HashMap::new(),
+            ),
+        )
+    };
+
+    let mut pw = PartialWitness::new();
+
+    pw.set_proof_with_pis_target(proof_targets[0], &inner_proof1);
+    pw.set_proof_with_pis_target(proof_targets[1], &inner_proof2);
+
+    for dummy_proof_target in dummy_proof_targets {
+        pw.set_proof_with_pis_target(dummy_proof_target, &dummy_proof);
+    }
+
+    pw.set_verifier_data_target(
+        &cyclic_circuit_targets.verifier_circuit_target,
+        &cyclic_circuit_data.verifier_only,
+    );
+
+    pw.set_bool_target(cyclic_circuit_targets.is_leaf_level, is_leaf_level);
+    pw.set_bool_target(cyclic_circuit_targets.is_zero, is_zero);
+
+    Ok(cyclic_circuit_data.prove(pw)?)
+}
+
+/// Proves the node for `current_epoch`.
+pub fn handle_balance_inner_level_proof(
+    proofs_bytes: Vec<Vec<u8>>,
     inner_circuit_data: &CircuitData<GoldilocksField, PoseidonGoldilocksConfig, 2>,
     inner_circuit_targets: &BalanceInnerCircuitTargets,
     circuit_data: &CircuitData<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+    current_epoch: &BigUint,
+) -> Result<ProofWithPublicInputs<GoldilocksField, PoseidonGoldilocksConfig, 2>> {
+    if proofs_bytes.len() != inner_circuit_targets.proofs.len() {
+        return Err(anyhow::anyhow!(
+            "Expected {} proofs, got {}",
+            inner_circuit_targets.proofs.len(),
+            proofs_bytes.len()
+        ));
+    }
+
+    let mut pw = PartialWitness::new();
+
+    for (proof_bytes, proof_target) in proofs_bytes
+        .into_iter()
+        .zip(inner_circuit_targets.proofs.iter())
+    {
+        let inner_proof =
+            ProofWithPublicInputs::<GoldilocksField, PoseidonGoldilocksConfig, 2>::from_bytes(
+                proof_bytes,
+                &inner_circuit_data.common,
+            )?;
+
+        pw.set_proof_with_pis_target(proof_target, &inner_proof);
+    }
+
+    pw.set_biguint_target(&inner_circuit_targets.current_epoch, current_epoch);
+
+    Ok(circuit_data.prove(pw)?)
+}
+
+pub fn handle_pending_deposits_inner_level_proof(
+    proof1_bytes: Vec<u8>,
+    proof2_bytes: Vec<u8>,
+    inner_circuit_data: &CircuitData<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+    inner_circuit_targets: &PendingDepositsInnerCircuitTargets,
+    circuit_data: &CircuitData<GoldilocksField, PoseidonGoldilocksConfig, 2>,
 ) -> Result<ProofWithPublicInputs<GoldilocksField, PoseidonGoldilocksConfig, 2>> {
     handle_generic_inner_level_proof(
         proof1_bytes,
@@ -107,7 +224,6 @@ pub fn handle_balance_inner_level_proof(
         inner_circuit_data,
         &inner_circuit_targets.proof1,
         &inner_circuit_targets.proof2,
-        &inner_circuit_targets.verifier_circuit_target,
         None,
         None,
         circuit_data,
@@ -169,7 +285,12 @@ impl SetPWValues<ValidatorBalancesInput> for ValidatorBalanceVerificationTargets
             self.validators[i].set_pw_values(pw, &source.validators[i]);
         }
 
-        pw.set_biguint_target(&self.withdrawal_credentials, &source.withdrawal_credentials);
+        for i in 0..self.withdrawal_credentials.len() {
+            pw.set_biguint_target(
+                &self.withdrawal_credentials[i],
+                &source.withdrawal_credentials[i],
+            );
+        }
 
         set_boolean_pw_values(pw, &self.validator_is_zero, &source.validator_is_zero);
 
@@ -177,6 +298,42 @@ impl SetPWValues<ValidatorBalancesInput> for ValidatorBalanceVerificationTargets
     }
 }
 
+impl SetPWValues<PendingDepositsInput> for PendingDepositsVerificationTargets {
+    fn set_pw_values(
+        &self,
+        pw: &mut PartialWitness<GoldilocksField>,
+        source: &PendingDepositsInput,
+    ) {
+        for i in 0..self.pending_deposits.len() {
+            let target = &self.pending_deposits[i];
+            let pending_deposit = &source.pending_deposits[i];
+
+            set_boolean_pw_values(pw, &target.pubkey, &pending_deposit.pubkey);
+
+            set_boolean_pw_values(
+                pw,
+                &target.withdrawal_credentials,
+                &pending_deposit.withdrawal_credentials,
+            );
+
+            pw.set_biguint_target(&target.amount, &pending_deposit.amount);
+
+            set_boolean_pw_values(pw, &target.signature, &pending_deposit.signature);
+
+            pw.set_biguint_target(&target.slot, &pending_deposit.slot);
+        }
+
+        for i in 0..self.withdrawal_credentials.len() {
+            pw.set_biguint_target(
+                &self.withdrawal_credentials[i],
+                &source.withdrawal_credentials[i],
+            );
+        }
+
+        set_boolean_pw_values(pw, &self.deposit_is_zero, &source.deposit_is_zero);
+    }
+}
+
 impl SetPWValues<ValidatorShaInput> for ValidatorShaTargets {
     fn set_pw_values(&self, pw: &mut PartialWitness<GoldilocksField>, source: &ValidatorShaInput) {
         set_boolean_pw_values(pw, &self.pubkey, &source.pubkey);
@@ -205,26 +362,94 @@ impl SetPWValues<ValidatorShaInput> for ValidatorShaTargets {
     }
 }
 
+impl SetPWValues<ValidatorsShaInput> for ValidatorsCommitmentTargets {
+    fn set_pw_values(&self, pw: &mut PartialWitness<GoldilocksField>, source: &ValidatorsShaInput) {
+        for (validator_targets, validator) in self.validators.iter().zip(source.validators.iter()) {
+            validator_targets.set_pw_values(pw, validator);
+        }
+
+        for (target, is_zero) in self
+            .validator_is_zero
+            .iter()
+            .zip(source.validator_is_zero.iter())
+        {
+            pw.set_bool_target(*target, *is_zero);
+        }
+    }
+}
+
 impl SetPWValues<FinalCircuitInput> for FinalCircuitTargets {
     fn set_pw_values(&self, pw: &mut PartialWitness<GoldilocksField>, source: &FinalCircuitInput) {
         set_boolean_pw_values(pw, &self.state_root, &source.state_root);
 
         pw.set_biguint_target(&self.slot, &source.slot);
 
-        for i in 0..source.slot_branch.len() {
-            set_boolean_pw_values(pw, &self.slot_branch[i], &source.slot_branch[i]);
+        for i in 0..source.state_root_multiproof.len() {
+            set_boolean_pw_values(
+                pw,
+                &self.state_root_multiproof[i],
+                &source.state_root_multiproof[i],
+            );
         }
 
-        pw.set_biguint_target(&self.withdrawal_credentials, &source.withdrawal_credentials);
+        set_boolean_pw_values(pw, &self.validator_size_bits, &source.validators_size_bits);
 
-        for i in 0..source.balance_branch.len() {
-            set_boolean_pw_values(pw, &self.balance_branch[i], &source.balance_branch[i]);
+        if let (Some(block_header), Some(block_header_input)) =
+            (&self.block_header, &source.block_header)
+        {
+            pw.set_biguint_target(
+                &block_header.proposer_index,
+                &block_header_input.proposer_index,
+            );
+
+            set_boolean_pw_values(
+                pw,
+                &block_header.parent_root,
+                &block_header_input.parent_root,
+            );
+
+            set_boolean_pw_values(pw, &block_header.body_root, &block_header_input.body_root);
         }
+    }
+}
 
-        for i in 0..source.validators_branch.len() {
-            set_boolean_pw_values(pw, &self.validators_branch[i], &source.validators_branch[i]);
+impl SetPWValues<HistoricalStateRootInput> for HistoricalStateRootCircuitTargets {
+    fn set_pw_values(
+        &self,
+        pw: &mut PartialWitness<GoldilocksField>,
+        source: &HistoricalStateRootInput,
+    ) {
+        set_boolean_pw_values(pw, &self.state_root, &source.state_root);
+
+        for i in 0..source.branch.len() {
+            set_boolean_pw_values(pw, &self.branch[i], &source.branch[i]);
         }
 
-        set_boolean_pw_values(pw, &self.validator_size_bits, &source.validators_size_bits);
+        pw.set_target(
+            self.state_roots_index,
+            GoldilocksField::from_canonical_u64(source.state_roots_index),
+        );
+
+        if let (Some(historical_summary_index), Some(historical_summary_index_value)) = (
+            self.historical_summary_index,
+            source.historical_summary_index,
+        ) {
+            pw.set_target(
+                historical_summary_index,
+                GoldilocksField::from_canonical_u64(historical_summary_index_value),
+            );
+        }
+    }
+}
+
+impl SetPWValues<PendingDepositsLayerInput> for PendingDepositsFinalCircuitTargets {
+    fn set_pw_values(
+        &self,
+        pw: &mut PartialWitness<GoldilocksField>,
+        source: &PendingDepositsLayerInput,
+    ) {
+        for i in 0..source.branch.len() {
+            set_boolean_pw_values(pw, &self.pending_deposits_branch[i], &source.branch[i]);
+        }
     }
 }
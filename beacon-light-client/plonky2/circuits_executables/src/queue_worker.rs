@@ -0,0 +1,70 @@
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use redis::aio::Connection;
+use redis_work_queue::{Item, WorkQueue};
+
+/// Leases the jobs of a level worker until the queue stays empty for
+/// `stop_after` seconds or the worker has run for `time_to_run`.
+pub struct QueueWorker {
+    queue: WorkQueue,
+    start: Instant,
+    time_to_run: Option<Duration>,
+    stop_after: u64,
+    lease_for: u64,
+}
+
+impl QueueWorker {
+    pub fn new(
+        queue: WorkQueue,
+        time_to_run: Option<Duration>,
+        stop_after: u64,
+        lease_for: u64,
+    ) -> Self {
+        QueueWorker {
+            queue,
+            start: Instant::now(),
+            time_to_run,
+            stop_after,
+            lease_for,
+        }
+    }
+
+    pub fn queue(&self) -> &WorkQueue {
+        &self.queue
+    }
+
+    /// Returns the next job with data, completing the empty ones, or `None` once
+    /// the worker should stop.
+    pub async fn next_job(&self, con: &mut Connection) -> Result<Option<Item>> {
+        while self.time_to_run.is_none() || self.start.elapsed() < self.time_to_run.unwrap() {
+            let job = match self
+                .queue
+                .lease(
+                    con,
+                    Some(Duration::from_secs(self.stop_after)),
+                    Duration::from_secs(self.lease_for),
+                )
+                .await?
+            {
+                Some(job) => job,
+                None => {
+                    println!("No jobs left in queue");
+
+                    return Ok(None);
+                }
+            };
+
+            if job.data.is_empty() {
+                println!("Skipping empty data job");
+                self.queue.complete(con, &job).await?;
+
+                continue;
+            }
+
+            return Ok(Some(job));
+        }
+
+        Ok(None)
+    }
+}
@@ -146,6 +146,29 @@ pub struct ValidatorShaInput {
     pub withdrawable_epoch: Vec<bool>,
 }
 
+impl ValidatorShaInput {
+    /// The input for a validator that is not in the registry.
+    pub fn zero() -> ValidatorShaInput {
+        ValidatorShaInput {
+            pubkey: vec![false; 384],
+            withdrawal_credentials: vec![false; 256],
+            effective_balance: vec![false; 256],
+            slashed: vec![false; 256],
+            activation_eligibility_epoch: vec![false; 256],
+            activation_epoch: vec![false; 256],
+            exit_epoch: vec![false; 256],
+            withdrawable_epoch: vec![false; 256],
+        }
+    }
+}
+
+/// The input of a commitment mapper leaf with several validators.
+#[derive(Debug)]
+pub struct ValidatorsShaInput {
+    pub validators: Vec<ValidatorShaInput>,
+    pub validator_is_zero: Vec<bool>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
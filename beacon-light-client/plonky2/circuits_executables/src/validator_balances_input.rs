@@ -1,5 +1,5 @@
 use crate::{
-    crud::{biguint_to_str, parse_biguint},
+    crud::{biguint_to_str, biguint_vec_to_str, parse_biguint, parse_biguint_vec},
     validator::{bool_vec_as_int_vec, bool_vec_as_int_vec_nested},
 };
 use num::BigUint;
@@ -49,14 +49,27 @@ pub struct ValidatorBalancesInput {
     pub validators: Vec<ValidatorPoseidonInput>,
     #[serde(with = "bool_vec_as_int_vec_nested")]
     pub balances: Vec<Vec<bool>>,
-    #[serde(serialize_with = "biguint_to_str", deserialize_with = "parse_biguint")]
-    pub withdrawal_credentials: BigUint,
+    #[serde(
+        serialize_with = "biguint_vec_to_str",
+        deserialize_with = "parse_biguint_vec"
+    )]
+    pub withdrawal_credentials: Vec<BigUint>,
     #[serde(serialize_with = "biguint_to_str", deserialize_with = "parse_biguint")]
     pub current_epoch: BigUint,
     #[serde(with = "bool_vec_as_int_vec")]
     pub validator_is_zero: Vec<bool>,
 }
 
+impl ValidatorBalancesInput {
+    /// Whether the input differs from `previous` only in the current epoch.
+    pub fn is_unchanged_from(&self, previous: &ValidatorBalancesInput) -> bool {
+        self.validators == previous.validators
+            && self.balances == previous.balances
+            && self.withdrawal_credentials == previous.withdrawal_credentials
+            && self.validator_is_zero == previous.validator_is_zero
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -76,7 +89,7 @@ mod tests {
                 withdrawable_epoch: BigUint::from(7u64),
             }],
             balances: vec![vec![true, false, true], vec![false, true, false]],
-            withdrawal_credentials: BigUint::from(30u64),
+            withdrawal_credentials: vec![BigUint::from(30u64), BigUint::from(31u64)],
             current_epoch: BigUint::from(40u64),
             validator_is_zero: vec![false, false, false],
         };
@@ -90,4 +103,20 @@ mod tests {
         // Check that the original and deserialized structs are equal
         assert_eq!(input, deserialized);
     }
+
+    #[test]
+    fn test_is_unchanged_from() {
+        let input = |balances: Vec<Vec<bool>>, current_epoch: u64| ValidatorBalancesInput {
+            validators: vec![],
+            balances,
+            withdrawal_credentials: vec![BigUint::from(30u64)],
+            current_epoch: BigUint::from(current_epoch),
+            validator_is_zero: vec![],
+        };
+
+        let previous = input(vec![vec![true, false]], 40);
+
+        assert!(input(vec![vec![true, false]], 41).is_unchanged_from(&previous));
+        assert!(!input(vec![vec![false, false]], 41).is_unchanged_from(&previous));
+    }
 }
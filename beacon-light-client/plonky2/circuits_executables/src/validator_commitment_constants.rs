@@ -9,8 +9,17 @@ pub struct ValidatorCommitmentConstants {
     pub validator_balance_input_key: String,
     pub balance_verification_queue: String,
     pub balance_verification_proof_key: String,
+    pub balance_verification_epoch_key: String,
+    pub proven_validator_balance_input_key: String,
     pub final_proof_input_key: String,
     pub final_layer_proof_key: String,
+    pub historical_state_root_input_key: String,
+    pub historical_layer_proof_key: String,
+    pub pending_deposits_input_key: String,
+    pub pending_deposits_queue: String,
+    pub pending_deposits_proof_key: String,
+    pub pending_deposits_layer_input_key: String,
+    pub pending_deposits_layer_proof_key: String,
 }
 
 pub fn get_validator_commitment_constants() -> ValidatorCommitmentConstants {
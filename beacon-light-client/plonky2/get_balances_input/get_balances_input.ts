@@ -1,4 +1,8 @@
-import { Tree } from '@chainsafe/persistent-merkle-tree';
+import {
+  MultiProof,
+  ProofType,
+  Tree,
+} from '@chainsafe/persistent-merkle-tree';
 import { Redis as RedisLocal } from '../../../relay/implementations/redis';
 import Redis from 'ioredis';
 import { BeaconApi } from '../../../relay/implementations/beacon-api';
@@ -119,17 +123,21 @@ let TAKE;
           .fill('')
           .map(() => ''.padStart(256, '0').split('').map(Number)),
         validators: Array(CIRCUIT_SIZE).fill(getZeroValidator()),
-        withdrawalCredentials: computeNumberFromLittleEndianBits(
-          hexToBits(
-            '0x01000000000000000000000015f4b914a0ccd14333d850ff311d6dafbfbaa32b',
-          ),
-        ).toString(),
+        withdrawalCredentials: [
+          computeNumberFromLittleEndianBits(
+            hexToBits(
+              '0x01000000000000000000000015f4b914a0ccd14333d850ff311d6dafbfbaa32b',
+            ),
+          ).toString(),
+        ],
         currentEpoch: computeEpochAt(beaconState.slot).toString(),
         validatorIsZero: Array(CIRCUIT_SIZE).fill(1),
       }),
     },
   ]);
 
+  await redis.saveBalanceVerificationEpoch(computeEpochAt(beaconState.slot));
+
   const buffer = new ArrayBuffer(8);
   const dataView = new DataView(buffer);
 
@@ -193,11 +201,13 @@ let TAKE;
                 Math.min((j + 1) * CIRCUIT_SIZE, validators.length),
             ).fill(getZeroValidator()),
           ],
-          withdrawalCredentials: computeNumberFromLittleEndianBits(
-            hexToBits(
-              '0x01000000000000000000000015f4b914a0ccd14333d850ff311d6dafbfbaa32b',
-            ),
-          ).toString(),
+          withdrawalCredentials: [
+            computeNumberFromLittleEndianBits(
+              hexToBits(
+                '0x01000000000000000000000015f4b914a0ccd14333d850ff311d6dafbfbaa32b',
+              ),
+            ).toString(),
+          ],
           currentEpoch: computeEpochAt(beaconState.slot).toString(),
           validatorIsZero: array.concat(new Array(CIRCUIT_SIZE - size).fill(1)),
         }),
@@ -262,21 +272,22 @@ let TAKE;
       bytesToHex(ssz.capella.BeaconState.hashTreeRoot(beaconState)),
     ),
     slot: beaconState.slot.toString(),
-    slotBranch: beaconStateTree
-      .getSingleProof(34n)
-      .map(x => hexToBits(bytesToHex(x))),
-    withdrawalCredentials: computeNumberFromLittleEndianBits(
-      hexToBits(
-        '0x01000000000000000000000015f4b914a0ccd14333d850ff311d6dafbfbaa32b',
-      ),
-    ).toString(),
-    balanceBranch: beaconStateTree
-      .getSingleProof(44n)
-      .map(x => hexToBits(bytesToHex(x))),
-    validatorsBranch: beaconStateTree
-      .getSingleProof(43n)
-      .map(x => hexToBits(bytesToHex(x))),
+    stateRootMultiproof: (
+      beaconStateTree.getProof({
+        type: ProofType.multi,
+        gindices: ['validators', 'balances', 'slot'].map(
+          field => ssz.capella.BeaconState.getPathInfo([field]).gindex,
+        ),
+      }) as MultiProof
+    ).witnesses.map(x => hexToBits(bytesToHex(x))),
     validatorsSizeBits: hexToBits(bytesToHex(ssz.UintNum64.hashTreeRoot(TAKE))),
+    blockHeader: {
+      proposerIndex: beaconState.latestBlockHeader.proposerIndex.toString(),
+      parentRoot: hexToBits(
+        bytesToHex(beaconState.latestBlockHeader.parentRoot),
+      ),
+      bodyRoot: hexToBits(bytesToHex(beaconState.latestBlockHeader.bodyRoot)),
+    },
   });
 
   queues[39].addItem(db, new Item(new ArrayBuffer(0)));
@@ -146,14 +146,25 @@ export class Redis implements IRedis {
     await this.redisClient.mSet(result);
   }
 
+  async saveBalanceVerificationEpoch(epoch: number) {
+    await this.waitForConnection();
+
+    await this.redisClient.set(
+      validator_commitment_constants.balanceVerificationEpochKey,
+      epoch.toString(),
+    );
+  }
+
   async saveFinalProofInput(input: {
     stateRoot: number[];
     slot: string;
-    slotBranch: number[][];
-    withdrawalCredentials: string;
-    balanceBranch: number[][];
-    validatorsBranch: number[][];
+    stateRootMultiproof: number[][];
     validatorsSizeBits: number[];
+    blockHeader?: {
+      proposerIndex: string;
+      parentRoot: number[];
+      bodyRoot: number[];
+    };
   }) {
     await this.waitForConnection();
 
@@ -192,7 +203,7 @@ export class Redis implements IRedis {
       validatorsCommitment: [],
       proof: [],
       balancesHash: [],
-      withdrawalCredentials: '0',
+      withdrawalCredentialsCommitment: [],
     },
   ): Promise<void> {
     await this.waitForConnection();
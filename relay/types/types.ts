@@ -176,5 +176,5 @@ export interface BalanceProof {
   validatorsCommitment: number[];
   proof: number[];
   balancesHash: number[];
-  withdrawalCredentials: string;
+  withdrawalCredentialsCommitment: number[];
 }